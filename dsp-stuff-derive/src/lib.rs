@@ -10,10 +10,20 @@ use syn::{parse_macro_input, DeriveInput};
 #[derive(FromMeta)]
 struct SliderOptions {
     range: syn::Expr,
+    /// Replace the start or end of `range` with an expression evaluated each
+    /// time the range is needed, so it can follow other fields through `self`
+    #[darling(default)]
+    range_from: Option<syn::Expr>,
+    #[darling(default)]
+    range_to: Option<syn::Expr>,
     logarithmic: Flag,
     as_input: Flag,
     #[darling(default)]
     suffix: Option<String>,
+    /// A function taking the node and a new value, returning the value to
+    /// store, for keeping the field consistent with others
+    #[darling(default)]
+    clamp_with: Option<syn::Expr>,
 }
 
 #[derive(FromField)]
@@ -33,6 +43,10 @@ struct FieldOpts {
     #[darling(default)]
     label: SpannedValue<Option<String>>,
 
+    /// Shown when hovering over the field's widget
+    #[darling(default)]
+    tooltip: Option<String>,
+
     /// Display this field as a slider with a given range
     #[darling(default)]
     slider: Option<SliderOptions>,
@@ -40,6 +54,13 @@ struct FieldOpts {
     /// Display this field as a select menu
     select: Flag,
 
+    /// Display this field as a checkbox
+    checkbox: Flag,
+
+    /// Display this field as a single line text box, the field should be a
+    /// `Mutex<String>`
+    text: Flag,
+
     #[darling(default)]
     default: Option<syn::Expr>,
 }
@@ -60,6 +81,32 @@ struct Dsp {
     #[darling(default)]
     after_settings_change: Option<syn::Expr>,
 
+    /// Called to clear the node's internal state (delay lines, filter memories, ...)
+    #[darling(default)]
+    reset_state: Option<syn::Expr>,
+
+    /// Called when the sample rate changes, defaults to `after_settings_change`
+    #[darling(default)]
+    refresh_settings: Option<syn::Expr>,
+
+    /// Called to find which end of a bus the node is, if any
+    #[darling(default)]
+    bus: Option<syn::Expr>,
+
+    /// Links into the node are always summed, whatever the input mixing setting
+    #[darling(default)]
+    sum_inputs: Flag,
+
+    /// The node can close a feedback loop, it outputs a block it's holding on
+    /// to before waiting on its inputs
+    #[darling(default)]
+    breaks_cycles: Flag,
+
+    /// The node holds on to something set up for the sample rate it was made
+    /// at, which `refresh_settings` can't retune
+    #[darling(default)]
+    rate_dependent: Flag,
+
     #[darling(multiple, rename = "input")]
     inputs: Vec<String>,
 
@@ -84,11 +131,21 @@ pub fn derive_dsp(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
 fn do_node(dsp: &Dsp) -> darling::Result<TokenStream> {
     let meta = do_meta(dsp);
+    let bus = do_bus(dsp);
     let getters = do_getters(&dsp.data)?;
     let render = do_render(&dsp.data, &dsp.custom_render, &dsp.after_settings_change)?;
     let (cfg_struct, save, restore) =
         do_save_restore(&dsp.ident, &dsp.after_settings_change, &dsp.data);
     let new = do_new(&dsp.inputs, &dsp.outputs, &dsp.data);
+    let params = do_params(&dsp.data, &dsp.after_settings_change);
+    let choices = do_choices(&dsp.data, &dsp.after_settings_change);
+    let reset_state = do_reset_state(&dsp.reset_state);
+    let refresh_settings = do_refresh_settings(
+        dsp.refresh_settings
+            .as_ref()
+            .or(dsp.after_settings_change.as_ref()),
+    );
+    let reset_defaults = do_reset_defaults(&dsp.data, &dsp.after_settings_change);
     let helpers = do_slider_as_input_helpers(&dsp.data);
 
     let ident = &dsp.ident;
@@ -98,11 +155,23 @@ fn do_node(dsp: &Dsp) -> darling::Result<TokenStream> {
         impl crate::node::Node for #ident {
             #meta
 
+            #bus
+
             #getters
 
             #save
 
             #render
+
+            #params
+
+            #choices
+
+            #reset_state
+
+            #refresh_settings
+
+            #reset_defaults
         }
 
         impl crate::node::NodeStatic for #ident {
@@ -119,6 +188,291 @@ fn do_node(dsp: &Dsp) -> darling::Result<TokenStream> {
     Ok(tokens)
 }
 
+fn do_params(
+    data: &ast::Data<darling::util::Ignored, FieldOpts>,
+    after_settings_change: &Option<syn::Expr>,
+) -> TokenStream {
+    let fields = data.as_ref().take_struct().unwrap();
+    let sliders = fields
+        .iter()
+        .filter(|f| f.slider.is_some())
+        .collect::<Vec<_>>();
+
+    // nodes without any sliders just use the default (empty) implementation
+    if sliders.is_empty() {
+        return quote! {};
+    }
+
+    let slider_fields = sliders
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let setters = sliders
+        .iter()
+        .map(|f| {
+            let ident = f.ident.as_ref().unwrap();
+            let r = f.slider.as_ref().unwrap();
+            let range = slider_range_f64(r);
+
+            // the same as setting it from the slider
+            let value_expr = if is_atomic_integer(&f.ty) {
+                quote! { v.round() as _ }
+            } else {
+                quote! { v as _ }
+            };
+
+            let clamp_expr = if let Some(e) = &r.clamp_with {
+                quote! {
+                    let v = (#e)(self, v);
+                }
+            } else {
+                quote! {}
+            };
+
+            quote! {
+                // max and min rather than clamp, which panics if a computed
+                // range ends up backwards
+                let range = #range;
+                let v = value.max(*range.start()).min(*range.end());
+                let v = #value_expr;
+                #clamp_expr
+                self.#ident.store(v, ::std::sync::atomic::Ordering::Relaxed);
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let names = slider_fields
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>();
+
+    let after_settings_change_expr = if let Some(e) = after_settings_change {
+        quote! {
+            (#e)(self);
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        fn list_params(&self) -> ::std::vec::Vec<(&'static ::std::primitive::str, ::std::primitive::f32)> {
+            ::std::vec![
+                #((#names, self.#slider_fields.load(::std::sync::atomic::Ordering::Relaxed) as ::std::primitive::f32)),*
+            ]
+        }
+
+        fn set_param(&self, name: &::std::primitive::str, value: ::std::primitive::f64) -> ::std::primitive::bool {
+            match name {
+                #(#names => {
+                    #setters
+                })*
+                _ => return false,
+            }
+
+            #after_settings_change_expr
+
+            true
+        }
+    }
+}
+
+fn do_bus(dsp: &Dsp) -> TokenStream {
+    let bus = if let Some(e) = &dsp.bus {
+        quote! {
+            fn bus(&self) -> ::std::option::Option<crate::node::BusEnd> {
+                (#e)(self)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let sum_inputs = if dsp.sum_inputs.is_present() {
+        quote! {
+            fn sums_inputs(&self) -> ::std::primitive::bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let breaks_cycles = if dsp.breaks_cycles.is_present() {
+        quote! {
+            fn breaks_cycles(&self) -> ::std::primitive::bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let rate_dependent = if dsp.rate_dependent.is_present() {
+        quote! {
+            fn rate_dependent(&self) -> ::std::primitive::bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #bus
+
+        #sum_inputs
+
+        #breaks_cycles
+
+        #rate_dependent
+    }
+}
+
+fn do_reset_state(reset_state: &Option<syn::Expr>) -> TokenStream {
+    if let Some(e) = reset_state {
+        quote! {
+            fn reset_state(&self) {
+                (#e)(self);
+            }
+        }
+    } else {
+        quote! {}
+    }
+}
+
+fn do_refresh_settings(refresh_settings: Option<&syn::Expr>) -> TokenStream {
+    if let Some(e) = refresh_settings {
+        quote! {
+            fn refresh_settings(&self) {
+                (#e)(self);
+            }
+        }
+    } else {
+        quote! {}
+    }
+}
+
+fn do_reset_defaults(
+    data: &ast::Data<darling::util::Ignored, FieldOpts>,
+    after_settings_change: &Option<syn::Expr>,
+) -> TokenStream {
+    let fields = data.as_ref().take_struct().unwrap();
+    let param_fields = fields
+        .iter()
+        .filter(|f| {
+            f.slider.is_some()
+                || f.select.is_present()
+                || f.checkbox.is_present()
+                || f.text.is_present()
+        })
+        .collect::<Vec<_>>();
+
+    if param_fields.is_empty() {
+        return quote! {};
+    }
+
+    // build the value the same way `new` would, then move it out of the fresh atomic
+    let resetters = param_fields.iter().map(|f| {
+        let ident = f.ident.as_ref().unwrap();
+        let ty = &f.ty;
+
+        let val = if let Some(v) = &f.default {
+            quote! { (#v).into() }
+        } else {
+            quote! { ::std::default::Default::default() }
+        };
+
+        if f.text.is_present() {
+            quote! {
+                let v: #ty = #val;
+                *self.#ident.lock().unwrap() = v.into_inner().unwrap();
+            }
+        } else {
+            quote! {
+                let v: #ty = #val;
+                self.#ident.store(v.into_inner(), ::std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+    });
+
+    let after_settings_change_expr = if let Some(e) = after_settings_change {
+        quote! {
+            (#e)(self);
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        fn reset_defaults(&self) {
+            #({ #resetters })*
+
+            #after_settings_change_expr
+        }
+    }
+}
+
+fn do_choices(
+    data: &ast::Data<darling::util::Ignored, FieldOpts>,
+    after_settings_change: &Option<syn::Expr>,
+) -> TokenStream {
+    let fields = data.as_ref().take_struct().unwrap();
+    let select_fields = fields
+        .iter()
+        .filter(|f| f.select.is_present())
+        .collect::<Vec<_>>();
+
+    // nodes without any selects just use the default (empty) implementation
+    if select_fields.is_empty() {
+        return quote! {};
+    }
+
+    let idents = select_fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let tys = select_fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let names = idents.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+
+    let after_settings_change_expr = if let Some(e) = after_settings_change {
+        quote! {
+            (#e)(self);
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        fn list_choices(&self) -> ::std::vec::Vec<(&'static ::std::primitive::str, &'static ::std::primitive::str)> {
+            ::std::vec![
+                #((#names, <&'static ::std::primitive::str>::from(self.#idents.load(::std::sync::atomic::Ordering::Relaxed)))),*
+            ]
+        }
+
+        fn set_choice(&self, name: &::std::primitive::str, value: &::std::primitive::str) -> ::std::primitive::bool {
+            fn enum_as_iter<A: ::std::convert::From<T>, T: ::strum::IntoEnumIterator>() -> impl ::std::iter::Iterator<Item = T> {
+                T::iter()
+            }
+
+            match name {
+                #(#names => {
+                    let ::std::option::Option::Some(v) = enum_as_iter::<#tys, _>()
+                        .find(|v| <&'static ::std::primitive::str>::from(*v) == value) else {
+                        return false;
+                    };
+
+                    self.#idents.store(v, ::std::sync::atomic::Ordering::Relaxed);
+                })*
+                _ => return false,
+            }
+
+            #after_settings_change_expr
+
+            true
+        }
+    }
+}
+
 fn do_slider_as_input_helpers(data: &ast::Data<darling::util::Ignored, FieldOpts>) -> TokenStream {
     let fields = data.as_ref().take_struct().unwrap();
     let slider_input_fields = fields
@@ -129,7 +483,7 @@ fn do_slider_as_input_helpers(data: &ast::Data<darling::util::Ignored, FieldOpts
         .map(|f| {
             let ident = f.ident.as_ref().unwrap();
             let helper_name = quote::format_ident!("{}_input", ident);
-            let range = &f.slider.as_ref().unwrap().range;
+            let range = slider_range(f.slider.as_ref().unwrap());
 
             quote! {
                 fn #helper_name(&self, inputs: &crate::node::ProcessInput, out_buf: &mut [f32]) {
@@ -247,37 +601,47 @@ fn do_save_restore(
                 );
                 let ident = f.ident.as_ref().unwrap();
 
-                Some((ident, ty, should_wrap, false))
+                Some((ident, ty, should_wrap, false, false))
             } else if f.id.is_present() || f.inputs.is_present() || f.outputs.is_present() {
                 let ty = f.ty.to_token_stream();
                 let ident = f.ident.as_ref().unwrap();
 
-                Some((ident, ty, false, f.id.is_present()))
+                Some((
+                    ident,
+                    ty,
+                    false,
+                    f.id.is_present(),
+                    f.inputs.is_present() || f.outputs.is_present(),
+                ))
             } else {
                 None
             }
         })
         .collect::<Vec<_>>();
 
-    let idents = struct_fields.iter().map(|(i, _, _, _)| i);
+    let idents = struct_fields.iter().map(|(i, _, _, _, _)| i);
 
     let cfg_struct_name = quote::format_ident!("{}Config", name);
 
+    // fields are optional so that configs saved before a field was added still load
     let struct_defn = quote! {
         #[derive(::serde::Deserialize, ::serde::Serialize)]
         struct #cfg_struct_name {
-            #(#idents: ::serde_json::Value),*
+            #(
+                #[serde(default)]
+                #idents: ::std::option::Option<::serde_json::Value>
+            ),*
         }
     };
 
-    let save_getters = struct_fields.iter().map(|(i, ty, wrap, _)| {
+    let save_getters = struct_fields.iter().map(|(i, ty, wrap, _, _)| {
         if *wrap {
             quote! {
-                #i: ::serde_json::to_value(<#ty>::from(&self.#i)).unwrap()
+                #i: ::std::option::Option::Some(::serde_json::to_value(<#ty>::from(&self.#i)).unwrap())
             }
         } else {
             quote! {
-                #i: ::serde_json::to_value(&self.#i).unwrap()
+                #i: ::std::option::Option::Some(::serde_json::to_value(&self.#i).unwrap())
             }
         }
     });
@@ -292,21 +656,38 @@ fn do_save_restore(
         }
     };
 
-    let restore_setters =
-        struct_fields
-            .iter()
-            .filter(|(_, _, _, is_id)| !*is_id)
-            .map(|(i, ty, wrap, _)| {
-                if *wrap {
-                    quote! {
-                        this.#i = ::serde_json::from_value::<#ty>(cfg.#i).unwrap().into();
+    let restore_setters = struct_fields
+        .iter()
+        .filter(|(_, _, _, is_id, _)| !*is_id)
+        .map(|(i, ty, wrap, _, is_ports)| {
+            if *is_ports {
+                // ports added to the node since the config was saved won't be
+                // in it, so carry them over from the freshly made storage
+                quote! {
+                    if let ::std::option::Option::Some(v) = cfg.#i {
+                        let ports = ::serde_json::from_value::<#ty>(v)?;
+                        for name in this.#i.get_all().into_keys() {
+                            if ports.get_id(&name).is_none() {
+                                ports.add(name);
+                            }
+                        }
+                        this.#i = ports;
                     }
-                } else {
-                    quote! {
-                        this.#i = ::serde_json::from_value::<#ty>(cfg.#i).unwrap();
+                }
+            } else if *wrap {
+                quote! {
+                    if let ::std::option::Option::Some(v) = cfg.#i {
+                        this.#i = ::serde_json::from_value::<#ty>(v)?.into();
                     }
                 }
-            });
+            } else {
+                quote! {
+                    if let ::std::option::Option::Some(v) = cfg.#i {
+                        this.#i = ::serde_json::from_value::<#ty>(v)?;
+                    }
+                }
+            }
+        });
 
     let id_field = fields
         .iter()
@@ -324,18 +705,23 @@ fn do_save_restore(
         quote! {}
     };
 
+    let id_field_name = id_field.to_string();
+
     let restore_defn = quote! {
-        fn restore(value: ::serde_json::Value) -> Self {
-            let cfg: #cfg_struct_name = serde_json::from_value(value).unwrap();
+        fn restore(value: ::serde_json::Value) -> ::std::result::Result<Self, ::serde_json::Error> {
+            let cfg: #cfg_struct_name = serde_json::from_value(value)?;
 
-            let id = serde_json::from_value(cfg.#id_field).unwrap();
+            let id = cfg.#id_field.ok_or_else(|| {
+                <::serde_json::Error as ::serde::de::Error>::missing_field(#id_field_name)
+            })?;
+            let id = serde_json::from_value(id)?;
             let mut this = Self::new(id);
 
             #(#restore_setters)*
 
             #after_settings_change_expr
 
-            this
+            ::std::result::Result::Ok(this)
         }
     };
 
@@ -442,7 +828,11 @@ fn do_render(
     let mut rendered_fields = fields
         .iter()
         .filter_map(|&f| {
-            if !(f.slider.is_some() || f.select.is_present()) {
+            if !(f.slider.is_some()
+                || f.select.is_present()
+                || f.checkbox.is_present()
+                || f.text.is_present())
+            {
                 return None;
             }
 
@@ -453,6 +843,26 @@ fn do_render(
                 );
             }
 
+            if f.checkbox.is_present() && (f.slider.is_some() || f.select.is_present()) {
+                errors.push(
+                    darling::Error::custom(
+                        "A field cannot be both a checkbox and a slider or select",
+                    )
+                    .with_span(&f.checkbox.span()),
+                );
+            }
+
+            if f.text.is_present()
+                && (f.slider.is_some() || f.select.is_present() || f.checkbox.is_present())
+            {
+                errors.push(
+                    darling::Error::custom(
+                        "A field cannot be both a text box and a slider, select or checkbox",
+                    )
+                    .with_span(&f.text.span()),
+                );
+            }
+
             let ident = errors.handle(
                 f.ident
                     .as_ref()
@@ -465,8 +875,16 @@ fn do_render(
                 .to_owned()
                 .unwrap_or_else(|| capitalize(f.ident.as_ref().unwrap().to_string()));
 
+            let tooltip_expr = if let Some(tooltip) = &f.tooltip {
+                quote! {
+                    .on_hover_text(#tooltip)
+                }
+            } else {
+                quote! {}
+            };
+
             let tokens = if let Some(r) = &f.slider {
-                let range = &r.range;
+                let range = slider_range_f64(r);
                 let suffix_expr = if let Some(suffix) = &r.suffix {
                     quote! {
                         .suffix(#suffix)
@@ -483,13 +901,31 @@ fn do_render(
                     quote! {}
                 };
 
+                // the slider works in f64 whatever the field is, so integer
+                // fields need telling to step by whole numbers
+                let (integer_expr, value_expr) = if is_atomic_integer(&f.ty) {
+                    (quote! { .integer() }, quote! { v.round() as _ })
+                } else {
+                    (quote! {}, quote! { v as _ })
+                };
+
+                let clamp_expr = if let Some(e) = &r.clamp_with {
+                    quote! {
+                        let v = (#e)(self, v);
+                    }
+                } else {
+                    quote! {}
+                };
+
                 quote! {
                     let r = ui.add(::eframe::egui::Slider::from_get_set(#range, |v| {
                         if let ::std::option::Option::Some(v) = v {
-                            self.#ident.store(v as _, ::std::sync::atomic::Ordering::Relaxed);
+                            let v = #value_expr;
+                            #clamp_expr
+                            self.#ident.store(v, ::std::sync::atomic::Ordering::Relaxed);
                         }
                         self.#ident.load(::std::sync::atomic::Ordering::Relaxed) as ::std::primitive::f64
-                    }).text(#label) #suffix_expr #logarithmic_expr);
+                    }).text(#label) #suffix_expr #logarithmic_expr #integer_expr) #tooltip_expr;
 
                     if r.changed() {
                         changed |= true;
@@ -517,7 +953,9 @@ fn do_render(
                                        <&'static ::std::primitive::str>::from(possible_selection)
                                    );
                                }
-                            });
+                            })
+                            .response
+                            #tooltip_expr;
 
                         if selected != current_selected {
                             self.#ident.store(selected, ::std::sync::atomic::Ordering::Relaxed);
@@ -525,6 +963,33 @@ fn do_render(
                         }
                     }
                 }
+            } else if f.checkbox.is_present() {
+                quote! {
+                    {
+                        let mut checked = self.#ident.load(::std::sync::atomic::Ordering::Relaxed);
+
+                        if ui.checkbox(&mut checked, #label) #tooltip_expr .changed() {
+                            self.#ident.store(checked, ::std::sync::atomic::Ordering::Relaxed);
+                            changed |= true;
+                        }
+                    }
+                }
+            } else if f.text.is_present() {
+                quote! {
+                    {
+                        // edit a copy so the lock isn't held while the ui is laid out
+                        let mut text = self.#ident.lock().unwrap().clone();
+
+                        ui.horizontal(|ui| {
+                            ui.label(#label);
+
+                            if ui.text_edit_singleline(&mut text) #tooltip_expr .changed() {
+                                *self.#ident.lock().unwrap() = text;
+                                changed |= true;
+                            }
+                        });
+                    }
+                }
             } else {
                 unreachable!()
             };
@@ -580,6 +1045,77 @@ fn do_render(
     Ok(tokens)
 }
 
+/// The slider's range, with `range_from` and `range_to` substituted in
+fn slider_range(s: &SliderOptions) -> TokenStream {
+    let range = &s.range;
+
+    if s.range_from.is_none() && s.range_to.is_none() {
+        return quote! { #range };
+    }
+
+    let start = s
+        .range_from
+        .as_ref()
+        .map_or_else(|| quote! { *range.start() }, |e| quote! { #e });
+    let end = s
+        .range_to
+        .as_ref()
+        .map_or_else(|| quote! { *range.end() }, |e| quote! { #e });
+
+    quote! {
+        {
+            // unused when both ends are replaced
+            #[allow(unused_variables)]
+            let range = #range;
+            (#start)..=(#end)
+        }
+    }
+}
+
+/// [`slider_range`] with its ends cast to f64, the type egui's sliders take,
+/// so integer fields can be given integer ranges
+fn slider_range_f64(s: &SliderOptions) -> TokenStream {
+    let range = slider_range(s);
+
+    quote! {
+        {
+            let range = #range;
+            (*range.start() as ::std::primitive::f64)..=(*range.end() as ::std::primitive::f64)
+        }
+    }
+}
+
+/// Whether `ty` is an `Atomic` of one of the integer primitives
+fn is_atomic_integer(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+
+    if segment.ident != "Atomic" {
+        return false;
+    }
+
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return false;
+    };
+
+    matches!(
+        args.args.first(),
+        Some(syn::GenericArgument::Type(syn::Type::Path(inner)))
+            if inner.path.get_ident().map_or(false, |i| {
+                [
+                    "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize",
+                ]
+                .iter()
+                .any(|t| i == t)
+            })
+    )
+}
+
 fn capitalize(s: String) -> String {
     let mut it = s.chars();
     if let Some(c) = it.next() {
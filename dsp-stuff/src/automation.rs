@@ -0,0 +1,136 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ids::NodeId, node::Node, nodes::Nodes};
+
+/// The clock that automation is recorded against and played back from
+pub struct Transport {
+    started: Instant,
+}
+
+impl Transport {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.started = Instant::now();
+    }
+
+    /// Seconds since the transport was last restarted
+    pub fn position(&self) -> f32 {
+        self.started.elapsed().as_secs_f32()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum LaneState {
+    #[default]
+    Stopped,
+    Recording,
+    Playing,
+}
+
+/// A recording of the movements of a single node parameter
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct AutomationLane {
+    pub target: Option<(NodeId, String)>,
+
+    /// (time, value) pairs, time is in seconds since the start of the recording
+    points: Vec<(f32, f32)>,
+
+    /// Length of the loop in seconds
+    length: f32,
+
+    state: LaneState,
+}
+
+impl AutomationLane {
+    pub fn state(&self) -> LaneState {
+        self.state
+    }
+
+    pub fn has_recording(&self) -> bool {
+        !self.points.is_empty() && self.length > 0.0
+    }
+
+    pub fn set_target(&mut self, target: Option<(NodeId, String)>) {
+        if target != self.target {
+            self.target = target;
+            self.points.clear();
+            self.length = 0.0;
+            self.state = LaneState::Stopped;
+        }
+    }
+
+    pub fn record(&mut self, transport: &mut Transport) {
+        transport.restart();
+        self.points.clear();
+        self.length = 0.0;
+        self.state = LaneState::Recording;
+    }
+
+    pub fn play(&mut self, transport: &mut Transport) {
+        if self.has_recording() {
+            transport.restart();
+            self.state = LaneState::Playing;
+        }
+    }
+
+    pub fn stop(&mut self, transport: &Transport) {
+        if self.state == LaneState::Recording {
+            self.length = transport.position();
+        }
+
+        self.state = LaneState::Stopped;
+    }
+
+    /// Fix up the state of a lane that was just loaded from a config
+    pub fn resume(&mut self, transport: &mut Transport) {
+        match self.state {
+            LaneState::Recording => self.state = LaneState::Stopped,
+            LaneState::Playing => self.play(transport),
+            LaneState::Stopped => {}
+        }
+    }
+
+    /// Record or play back the parameter on the target node
+    pub fn tick(&mut self, transport: &Transport, node: &Nodes) {
+        let Some((_, param)) = &self.target else {
+            return;
+        };
+
+        let current = node
+            .list_params()
+            .into_iter()
+            .find(|(name, _)| name == param)
+            .map(|(_, v)| v);
+
+        let Some(current) = current else {
+            return;
+        };
+
+        match self.state {
+            LaneState::Stopped => {}
+            LaneState::Recording => {
+                if self.points.last().is_none_or(|(_, v)| *v != current) {
+                    self.points.push((transport.position(), current));
+                }
+            }
+            LaneState::Playing => {
+                let t = transport.position() % self.length;
+
+                // the value is held until the next recorded movement
+                let idx = self.points.partition_point(|(pt, _)| *pt <= t);
+                let (_, value) = self.points[idx.saturating_sub(1)];
+
+                if value != current {
+                    node.set_param(param, value.into());
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use std::{
-    collections::HashMap,
-    sync::{atomic::AtomicU8, Arc},
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize},
+        Arc, Mutex, Weak,
+    },
+    time::Instant,
 };
 
 use collect_slice::CollectSlice;
@@ -8,7 +13,7 @@ use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     Sample, SampleRate,
 };
-use dasp_interpolate::sinc::Sinc;
+use dasp_interpolate::{linear::Linear, sinc::Sinc, Interpolator};
 use dasp_sample::{FromSample, ToSample};
 use dasp_signal::{interpolate::Converter, Signal};
 use itertools::Itertools;
@@ -20,6 +25,260 @@ use rivulet::{
 
 use crate::ids::DeviceId;
 
+/// Rate the graph runs at, taken from the first device opened and kept for
+/// the rest of the session
+static SAMPLE_RATE: AtomicU32 = AtomicU32::new(48000);
+
+/// Whether a device has picked [`SAMPLE_RATE`] yet
+static SAMPLE_RATE_CHOSEN: AtomicBool = AtomicBool::new(false);
+
+pub fn sample_rate() -> u32 {
+    SAMPLE_RATE.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Run `f` with the graph at `rate`
+///
+/// Tests share the rate, so this also keeps tests that depend on it from
+/// running at the same time.
+#[cfg(test)]
+pub fn with_sample_rate<T>(rate: u32, f: impl FnOnce() -> T) -> T {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    SAMPLE_RATE.store(rate, std::sync::atomic::Ordering::Relaxed);
+    f()
+}
+
+/// The graph's rate, adopting `preferred` if this is the first device opened
+fn choose_sample_rate(preferred: u32) -> u32 {
+    if SAMPLE_RATE_CHOSEN
+        .compare_exchange(
+            false,
+            true,
+            std::sync::atomic::Ordering::Relaxed,
+            std::sync::atomic::Ordering::Relaxed,
+        )
+        .is_ok()
+    {
+        SAMPLE_RATE.store(preferred, std::sync::atomic::Ordering::Relaxed);
+        tracing::info!(sample_rate = preferred, "Selected graph sample rate");
+    }
+
+    sample_rate()
+}
+
+/// How many output callbacks a resync stays armed for
+static RESYNC_CALLBACKS: AtomicU8 = AtomicU8::new(5);
+
+pub fn set_catchup(resync_callbacks: u8) {
+    RESYNC_CALLBACKS.store(resync_callbacks, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// The latency new outputs aim for, in milliseconds
+pub const DEFAULT_TARGET_LATENCY_MS: u32 = 20;
+
+/// Settings of an output stream that its node can change while it plays
+pub struct OutputControls {
+    /// For devices with more than two channels, the first of the pair written
+    /// to
+    pub first_channel: AtomicUsize,
+    /// How much audio may be left queued for the device, in milliseconds, once
+    /// a resync has caught up
+    pub target_latency_ms: AtomicU32,
+    /// Callbacks left until a resync stops being armed
+    resync: AtomicU8,
+}
+
+impl Default for OutputControls {
+    fn default() -> Self {
+        Self {
+            first_channel: AtomicUsize::new(0),
+            target_latency_ms: AtomicU32::new(DEFAULT_TARGET_LATENCY_MS),
+            resync: AtomicU8::new(0),
+        }
+    }
+}
+
+impl OutputControls {
+    /// How many of the `backlog` frames queued beyond what this callback needs
+    /// should be dropped, only the excess over the target latency is dropped
+    /// and only while a resync is armed
+    fn catchup(&self, backlog: usize) -> usize {
+        let armed = self
+            .resync
+            .fetch_update(
+                std::sync::atomic::Ordering::SeqCst,
+                std::sync::atomic::Ordering::SeqCst,
+                |x| Some(x.saturating_sub(1)),
+            )
+            .unwrap()
+            > 0;
+
+        let target = self
+            .target_latency_ms
+            .load(std::sync::atomic::Ordering::Relaxed) as usize
+            * sample_rate() as usize
+            / 1000;
+
+        if armed {
+            backlog.saturating_sub(target)
+        } else {
+            0
+        }
+    }
+}
+
+/// Factor the graph's output is upsampled by before being converted to the
+/// device's rate, 1 disables oversampling
+static OUTPUT_OVERSAMPLING: AtomicU32 = AtomicU32::new(1);
+
+/// Only affects outputs opened after the change
+pub fn set_output_oversampling(factor: u32) {
+    OUTPUT_OVERSAMPLING.store(factor.max(1), std::sync::atomic::Ordering::Relaxed);
+}
+
+/// How long (in ms) outputs fade out and back in for when switching device
+static SWITCH_FADE: AtomicU32 = AtomicU32::new(20);
+
+pub fn set_switch_fade(ms: u32) {
+    SWITCH_FADE.store(ms, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn switch_fade_samples() -> usize {
+    SWITCH_FADE.load(std::sync::atomic::Ordering::Relaxed) as usize * sample_rate() as usize / 1000
+}
+
+/// Whether audio threads should try to run at real-time priority
+static ELEVATED_PRIORITY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_elevated_priority(enabled: bool) {
+    ELEVATED_PRIORITY.store(enabled, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Raise the priority of the calling thread if enabled, this is cheap to call
+/// repeatedly as each thread only tries once
+pub fn elevate_current_thread() {
+    thread_local! {
+        static ATTEMPTED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    if !ELEVATED_PRIORITY.load(std::sync::atomic::Ordering::Relaxed) || ATTEMPTED.get() {
+        return;
+    }
+
+    ATTEMPTED.set(true);
+
+    let thread = std::thread::current();
+    match thread_priority::set_current_thread_priority(thread_priority::ThreadPriority::Max) {
+        Ok(()) => tracing::debug!(thread = ?thread.name(), "Elevated thread priority"),
+        Err(e) => tracing::warn!(
+            thread = ?thread.name(),
+            "Couldn't elevate thread priority, continuing at normal priority: {:?}",
+            e
+        ),
+    }
+}
+
+/// Gain applied to everything written to an output device, stored as the bits
+/// of an f32
+static MASTER_GAIN: AtomicU32 = AtomicU32::new(1.0f32.to_bits());
+
+pub fn set_master_gain(gain: f32, muted: bool) {
+    let gain = if muted { 0.0 } else { gain };
+    MASTER_GAIN.store(gain.to_bits(), std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Moves an output's gain to the master gain over the length of a callback,
+/// so changes (including muting) take effect immediately without clicking
+struct GainRamp {
+    current: f32,
+    /// Cleared while another output is soloed
+    audible: Arc<AtomicBool>,
+}
+
+impl GainRamp {
+    fn new(audible: Arc<AtomicBool>) -> Self {
+        let mut this = Self {
+            current: 0.0,
+            audible,
+        };
+        this.current = this.target();
+        this
+    }
+
+    fn target(&self) -> f32 {
+        if self.audible.load(std::sync::atomic::Ordering::Relaxed) {
+            f32::from_bits(MASTER_GAIN.load(std::sync::atomic::Ordering::Relaxed))
+        } else {
+            0.0
+        }
+    }
+
+    fn steps(&mut self, len: usize) -> impl Iterator<Item = f32> {
+        let start = self.current;
+        let target = self.target();
+        self.current = target;
+
+        let step = (target - start) / len.max(1) as f32;
+
+        (1..=len).map(move |i| start + step * i as f32)
+    }
+}
+
+/// Live statistics of an output stream, updated from its callback
+#[derive(Default, Debug)]
+pub struct OutputStats {
+    /// Samples that were waiting to be sent to the device at the last callback
+    pub queued: AtomicUsize,
+
+    /// Callbacks that had to play silence as not enough samples were ready
+    pub underruns: AtomicU64,
+
+    /// Fraction of the time a callback covers that was spent producing it
+    pub load: atomig::Atomic<f32>,
+}
+
+impl OutputStats {
+    fn record(&self, queued: Option<usize>) {
+        match queued {
+            Some(n) => self.queued.store(n, std::sync::atomic::Ordering::Relaxed),
+            None => {
+                self.queued.store(0, std::sync::atomic::Ordering::Relaxed);
+                self.underruns
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn record_load(&self, started: Instant, frames: usize, sample_rate: usize) {
+        let available = frames as f32 / sample_rate as f32;
+        self.load.store(
+            started.elapsed().as_secs_f32() / available,
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+}
+
+/// The stats of every output that's been opened, dead ones are pruned lazily
+static OUTPUT_STATS: Lazy<Mutex<Vec<Weak<OutputStats>>>> = Lazy::new(Default::default);
+
+/// Stats of all currently open outputs
+pub fn all_output_stats() -> Vec<Arc<OutputStats>> {
+    let mut stats = OUTPUT_STATS.lock().unwrap();
+    stats.retain(|s| s.strong_count() > 0);
+    stats.iter().filter_map(Weak::upgrade).collect()
+}
+
+/// How often a device's stream couldn't keep up with the graph, see
+/// [`DeviceCommand::GetStats`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeviceStats {
+    /// Input callbacks dropped as the graph hadn't taken the previous samples
+    pub overruns: u64,
+    /// Output callbacks that played silence as the graph hadn't produced enough
+    pub underruns: u64,
+}
+
 type DeviceCmdChan = std::sync::mpsc::SyncSender<(DeviceCommand, oneshot::Sender<DeviceResponse>)>;
 
 static DEVICE_CMD_CHAN: Lazy<DeviceCmdChan> = Lazy::new(|| {
@@ -29,8 +288,24 @@ static DEVICE_CMD_CHAN: Lazy<DeviceCmdChan> = Lazy::new(|| {
     ) = std::sync::mpsc::sync_channel(1);
 
     std::thread::spawn(move || {
+        elevate_current_thread();
+
         let mut devices: HashMap<DeviceId, cpal::Stream> = HashMap::new();
-        let mut resync_counters: HashMap<DeviceId, Arc<AtomicU8>> = HashMap::new();
+        let mut output_controls: HashMap<DeviceId, Arc<OutputControls>> = HashMap::new();
+        let mut input_overruns: HashMap<DeviceId, Arc<AtomicU64>> = HashMap::new();
+        let mut output_stats: HashMap<DeviceId, Arc<OutputStats>> = HashMap::new();
+        let mut audible: HashMap<DeviceId, Arc<AtomicBool>> = HashMap::new();
+        let mut soloed: HashSet<DeviceId> = HashSet::new();
+
+        let apply_solo = |audible: &HashMap<DeviceId, Arc<AtomicBool>>,
+                          soloed: &HashSet<DeviceId>| {
+            for (id, flag) in audible {
+                flag.store(
+                    soloed.is_empty() || soloed.contains(id),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
+            }
+        };
 
         for (cmd, resp_chan) in receiver {
             match cmd {
@@ -40,62 +315,46 @@ static DEVICE_CMD_CHAN: Lazy<DeviceCmdChan> = Lazy::new(|| {
                         .unwrap();
                 }
                 DeviceCommand::ListInputs(host) => {
-                    let host = cpal::host_from_id(
-                        cpal::available_hosts()
-                            .into_iter()
-                            .find(|id| *id == host)
-                            .unwrap(),
-                    )
-                    .unwrap();
-
-                    let devices = host
-                        .input_devices()
-                        .unwrap()
-                        .filter_map(|d| d.name().ok())
-                        .collect::<Vec<_>>();
+                    let devices = find_host(host)
+                        .and_then(|host| host.input_devices().ok())
+                        .map_or_else(Vec::new, |devices| {
+                            devices.filter_map(|d| d.name().ok()).collect()
+                        });
 
                     resp_chan.send(DeviceResponse::Devices(devices)).unwrap();
                 }
                 DeviceCommand::ListOutputs(host) => {
-                    let host = cpal::host_from_id(
-                        cpal::available_hosts()
-                            .into_iter()
-                            .find(|id| *id == host)
-                            .unwrap(),
-                    )
-                    .unwrap();
-
-                    let devices = host
-                        .output_devices()
-                        .unwrap()
-                        .filter_map(|d| d.name().ok())
-                        .collect::<Vec<_>>();
+                    let devices = find_host(host)
+                        .and_then(|host| host.output_devices().ok())
+                        .map_or_else(Vec::new, |devices| {
+                            devices.filter_map(|d| d.name().ok()).collect()
+                        });
 
                     resp_chan.send(DeviceResponse::Devices(devices)).unwrap();
                 }
-                DeviceCommand::OpenInput(host, dev) => {
+                DeviceCommand::OpenInput(host, dev, first_channel) => {
                     tracing::info!("Opening input device {dev:?}");
-                    let host = cpal::host_from_id(
-                        cpal::available_hosts()
-                            .into_iter()
-                            .find(|id| *id == host)
-                            .unwrap(),
-                    )
-                    .unwrap();
 
-                    let device = host
-                        .input_devices()
-                        .unwrap()
-                        .find(|d| d.name().ok().as_ref() == Some(&dev))
-                        .unwrap();
+                    // configs saved on another machine can name devices that
+                    // aren't here
+                    let Some(device) = find_host(host).and_then(|host| {
+                        host.input_devices()
+                            .ok()?
+                            .find(|d| d.name().ok().as_ref() == Some(&dev))
+                    }) else {
+                        tracing::warn!("Input device {dev:?} isn't available");
+                        resp_chan.send(DeviceResponse::InputOpened(None)).unwrap();
+                        continue;
+                    };
 
-                    let r = match input_stream(device) {
-                        Ok((stream, source)) => {
+                    let r = match input_stream(device, first_channel) {
+                        Ok((stream, source, overruns, channels, device_channels)) => {
                             stream.play().unwrap();
                             let id = DeviceId::generate();
                             devices.insert(id, stream);
+                            input_overruns.insert(id, overruns);
 
-                            Some((id, source))
+                            Some((id, source, channels, device_channels))
                         }
                         Err(e) => {
                             tracing::error!("Opening input failed: {:#}", e);
@@ -105,30 +364,37 @@ static DEVICE_CMD_CHAN: Lazy<DeviceCmdChan> = Lazy::new(|| {
 
                     resp_chan.send(DeviceResponse::InputOpened(r)).unwrap();
                 }
-                DeviceCommand::OpenOutput(host, dev) => {
+                DeviceCommand::OpenOutput(host, dev, controls, quality) => {
                     tracing::info!("Opening output device {dev:?}");
-                    let host = cpal::host_from_id(
-                        cpal::available_hosts()
-                            .into_iter()
-                            .find(|id| *id == host)
-                            .unwrap(),
-                    )
-                    .unwrap();
 
-                    let device = host
-                        .output_devices()
-                        .unwrap()
-                        .find(|d| d.name().ok().as_ref() == Some(&dev))
-                        .unwrap();
+                    let Some(device) = find_host(host).and_then(|host| {
+                        host.output_devices()
+                            .ok()?
+                            .find(|d| d.name().ok().as_ref() == Some(&dev))
+                    }) else {
+                        tracing::warn!("Output device {dev:?} isn't available");
+                        resp_chan.send(DeviceResponse::OutputOpened(None)).unwrap();
+                        continue;
+                    };
 
-                    let r = match output_stream(device) {
-                        Ok((stream, sink, resync)) => {
+                    // quiet from the start if something else is soloed
+                    let is_audible = Arc::new(AtomicBool::new(soloed.is_empty()));
+
+                    let r = match output_stream(
+                        device,
+                        Arc::clone(&is_audible),
+                        Arc::clone(&controls),
+                        quality,
+                    ) {
+                        Ok((stream, sink, stats, channels, device_channels)) => {
                             stream.play().unwrap();
                             let id = DeviceId::generate();
                             devices.insert(id, stream);
-                            resync_counters.insert(id, resync);
+                            output_controls.insert(id, controls);
+                            output_stats.insert(id, Arc::clone(&stats));
+                            audible.insert(id, is_audible);
 
-                            Some((id, sink))
+                            Some((id, sink, stats, channels, device_channels))
                         }
                         Err(e) => {
                             tracing::error!("Opening output failed: {:#}", e);
@@ -144,16 +410,55 @@ static DEVICE_CMD_CHAN: Lazy<DeviceCmdChan> = Lazy::new(|| {
                     if let Some(dev) = devices.remove(&dev) {
                         let _ = dev.pause();
                     }
+                    output_controls.remove(&dev);
+                    input_overruns.remove(&dev);
+                    output_stats.remove(&dev);
+
+                    // closing the only soloed output unmutes the others
+                    audible.remove(&dev);
+                    if soloed.remove(&dev) {
+                        apply_solo(&audible, &soloed);
+                    }
 
                     resp_chan.send(DeviceResponse::DeviceClosed).unwrap();
                 }
                 DeviceCommand::TriggerResync => {
-                    for counter in resync_counters.values() {
-                        counter.fetch_add(5, std::sync::atomic::Ordering::Relaxed);
+                    let callbacks = RESYNC_CALLBACKS.load(std::sync::atomic::Ordering::Relaxed);
+                    for controls in output_controls.values() {
+                        let _ = controls.resync.fetch_update(
+                            std::sync::atomic::Ordering::Relaxed,
+                            std::sync::atomic::Ordering::Relaxed,
+                            |x| Some(x.saturating_add(callbacks)),
+                        );
                     }
 
                     resp_chan.send(DeviceResponse::Resynced).unwrap();
                 }
+                DeviceCommand::SetSolo(dev, solo) => {
+                    if solo {
+                        soloed.insert(dev);
+                    } else {
+                        soloed.remove(&dev);
+                    }
+                    apply_solo(&audible, &soloed);
+
+                    resp_chan.send(DeviceResponse::SoloSet).unwrap();
+                }
+                DeviceCommand::GetStats(dev) => {
+                    let overruns = input_overruns
+                        .get(&dev)
+                        .map(|n| n.load(std::sync::atomic::Ordering::Relaxed));
+                    let underruns = output_stats
+                        .get(&dev)
+                        .map(|s| s.underruns.load(std::sync::atomic::Ordering::Relaxed));
+
+                    let stats = (overruns.is_some() || underruns.is_some()).then(|| DeviceStats {
+                        overruns: overruns.unwrap_or(0),
+                        underruns: underruns.unwrap_or(0),
+                    });
+
+                    resp_chan.send(DeviceResponse::Stats(stats)).unwrap();
+                }
             }
         }
     });
@@ -161,6 +466,14 @@ static DEVICE_CMD_CHAN: Lazy<DeviceCmdChan> = Lazy::new(|| {
     sender
 });
 
+/// The host with the given id, if it's available on this machine
+fn find_host(id: cpal::HostId) -> Option<cpal::Host> {
+    cpal::available_hosts()
+        .into_iter()
+        .find(|h| *h == id)
+        .and_then(|id| cpal::host_from_id(id).ok())
+}
+
 pub fn invoke(cmd: DeviceCommand) -> DeviceResponse {
     let (resp_in, resp_out) = oneshot::channel();
     DEVICE_CMD_CHAN.send((cmd, resp_in)).unwrap();
@@ -171,19 +484,36 @@ pub enum DeviceCommand {
     ListHosts,
     ListInputs(cpal::HostId),
     ListOutputs(cpal::HostId),
-    OpenInput(cpal::HostId, String),
-    OpenOutput(cpal::HostId, String),
+    /// Devices with more than two channels are opened as stereo, using the
+    /// pair of channels starting at the given one
+    OpenInput(cpal::HostId, String, Arc<AtomicUsize>),
+    OpenOutput(cpal::HostId, String, Arc<OutputControls>, ResamplerQuality),
     CloseDevice(DeviceId),
     TriggerResync,
+    /// While any output is soloed, only soloed outputs are audible
+    SetSolo(DeviceId, bool),
+    /// Overrun and underrun counts of an open device
+    GetStats(DeviceId),
 }
 
+/// A device's samples, interleaved if it has more than one channel, along with
+/// how many channels are interleaved and how many the device really has
+pub type OpenedInput = (DeviceId, splittable::View<Source<f32>>, usize, usize);
+
+/// Where to write a device's samples, interleaved if it has more than one
+/// channel, along with how many channels are interleaved and how many the
+/// device really has
+pub type OpenedOutput = (DeviceId, Sink<f32>, Arc<OutputStats>, usize, usize);
+
 pub enum DeviceResponse {
     Hosts(Vec<cpal::HostId>),
     Devices(Vec<String>),
-    InputOpened(Option<(DeviceId, splittable::View<Source<f32>>)>),
-    OutputOpened(Option<(DeviceId, Sink<f32>)>),
+    InputOpened(Option<OpenedInput>),
+    OutputOpened(Option<OpenedOutput>),
     DeviceClosed,
     Resynced,
+    SoloSet,
+    Stats(Option<DeviceStats>),
 }
 
 impl DeviceResponse {
@@ -201,20 +531,27 @@ impl DeviceResponse {
         }
     }
 
-    pub fn input_opened(self) -> Option<Option<(DeviceId, splittable::View<Source<f32>>)>> {
+    pub fn input_opened(self) -> Option<Option<OpenedInput>> {
         match self {
             Self::InputOpened(v) => Some(v),
             _ => None,
         }
     }
 
-    pub fn output_opened(self) -> Option<Option<(DeviceId, Sink<f32>)>> {
+    pub fn output_opened(self) -> Option<Option<OpenedOutput>> {
         match self {
             Self::OutputOpened(v) => Some(v),
             _ => None,
         }
     }
 
+    pub fn stats(self) -> Option<Option<DeviceStats>> {
+        match self {
+            Self::Stats(v) => Some(v),
+            _ => None,
+        }
+    }
+
     #[allow(unused)]
     pub fn device_closed(self) -> Option<()> {
         match self {
@@ -224,7 +561,7 @@ impl DeviceResponse {
     }
 }
 
-fn do_read_1<T>(data: &[T], sink: &mut Sink<f32>)
+fn do_read_1<T>(data: &[T], sink: &mut Sink<f32>, overruns: &AtomicU64)
 where
     T: Sample + ToSample<f32>,
 {
@@ -236,36 +573,71 @@ where
             .collect_slice(&mut buf[..data.len()]);
         sink.release(data.len());
     } else {
-        // println!("input fuck");
         // input will fall behind
+        overruns.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     };
 }
 
-fn do_read_2<T>(data: &[T], sink: &mut Sink<f32>)
+fn do_read_2<T>(data: &[T], sink: &mut Sink<f32>, overruns: &AtomicU64)
 where
     T: Sample + ToSample<f32>,
 {
-    let buf_len = data.len() / 2;
+    // the channels are kept interleaved, the input node splits them
+    let buf_len = data.len() - data.len() % 2;
     if sink.try_grant(buf_len).unwrap() {
         let buf = sink.view_mut();
-        data.iter()
+        data[..buf_len]
+            .iter()
             .copied()
             .map(<T as Sample>::to_sample)
-            .array_chunks::<2>()
-            .map(|[a, b]| a + b)
             .collect_slice(&mut buf[..buf_len]);
         sink.release(buf_len);
     } else {
-        // println!("input fuck");
         // input will fall behind
+        overruns.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     };
 }
 
+/// Like [`do_read_2`] for devices with more than two channels, keeping the
+/// pair of channels starting at `first_channel`
+fn do_read_n<T>(
+    data: &[T],
+    sink: &mut Sink<f32>,
+    overruns: &AtomicU64,
+    channels: usize,
+    first_channel: &AtomicUsize,
+) where
+    T: Sample + ToSample<f32>,
+{
+    let first = first_channel
+        .load(std::sync::atomic::Ordering::Relaxed)
+        .min(channels - 2);
+    let frames = data.len() / channels;
+
+    if sink.try_grant(frames * 2).unwrap() {
+        let buf = sink.view_mut();
+        for (frame, out) in data.chunks_exact(channels).zip(buf.chunks_exact_mut(2)) {
+            out[0] = <T as Sample>::to_sample(frame[first]);
+            out[1] = <T as Sample>::to_sample(frame[first + 1]);
+        }
+        sink.release(frames * 2);
+    } else {
+        // input will fall behind
+        overruns.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    };
+}
+
+/// Sort key for a device config's channel count, stereo first, then mono, and
+/// anything else last as the extra channels go unused
+fn channel_preference(channels: u16) -> (u16, u16) {
+    (channels.abs_diff(2), channels)
+}
+
 macro_rules! handle_inps {
-    ($fmt:ident, $dev:ident, $cfg:ident, $read_fn:ident, $sink:ident, $err_cb:ident, $($typ:ty: $tyn:tt),*) => {
+    ($fmt:ident, $dev:ident, $cfg:ident, $read_fn:expr, $sink:ident, $overruns:ident, $err_cb:ident, $($typ:ty: $tyn:tt),*) => {
         match $fmt {
             $(
-                cpal::SampleFormat::$tyn => { $dev.build_input_stream(&$cfg, move |data: &[$typ], _| $read_fn(data, &mut $sink), $err_cb, None)? }
+                cpal::SampleFormat::$tyn => { $dev.build_input_stream(&$cfg, move |data: &[$typ], _| { elevate_current_thread(); $read_fn(data, &mut $sink, &$overruns) }, $err_cb, None)? }
             ),*
                 f => { return Err(::color_eyre::eyre::eyre!("I don't know how to handle {} samples", f)) }
         }
@@ -274,16 +646,26 @@ macro_rules! handle_inps {
 
 fn input_stream(
     dev: cpal::Device,
-) -> color_eyre::Result<(cpal::Stream, splittable::View<Source<f32>>)> {
+    first_channel: Arc<AtomicUsize>,
+) -> color_eyre::Result<(
+    cpal::Stream,
+    splittable::View<Source<f32>>,
+    Arc<AtomicU64>,
+    usize,
+    usize,
+)> {
+    // inputs aren't resampled, so they have to run at the graph's rate
+    let rate = SampleRate(choose_sample_rate(
+        dev.default_input_config()?.sample_rate().0,
+    ));
+
     let (cfg, fmt) = if let Some(cfg) = dev
         .supported_input_configs()?
-        .filter(|cfg| {
-            cfg.min_sample_rate() <= SampleRate(48000) && cfg.max_sample_rate() >= SampleRate(48000)
-        })
-        .sorted_by_key(|cfg| cfg.channels())
+        .filter(|cfg| cfg.min_sample_rate() <= rate && cfg.max_sample_rate() >= rate)
+        .sorted_by_key(|cfg| channel_preference(cfg.channels()))
         .next()
     {
-        let cfg = cfg.with_sample_rate(SampleRate(48000));
+        let cfg = cfg.with_sample_rate(rate);
         // let buf_size = match cfg.buffer_size() {
         //     cpal::SupportedBufferSize::Range { min, max: _ } => BufferSize::Fixed(*min),
         //     cpal::SupportedBufferSize::Unknown => BufferSize::Default,
@@ -302,7 +684,12 @@ fn input_stream(
 
     tracing::info!(?cfg, "Selected input cfg");
 
-    let (mut sink, source) = rivulet::circular_buffer::<f32>(8192);
+    let device_channels = cfg.channels as usize;
+    let channels = device_channels.min(2);
+    let (mut sink, source) = rivulet::circular_buffer::<f32>(8192 * channels);
+
+    let overruns = Arc::new(AtomicU64::new(0));
+    let overruns_out = Arc::clone(&overruns);
 
     let err_cb = |err| tracing::warn!("output message: {:#?}", err);
 
@@ -313,6 +700,7 @@ fn input_stream(
             cfg,
             do_read_1,
             sink,
+            overruns,
             err_cb,
             i8: I8,
             i16: I16,
@@ -331,6 +719,7 @@ fn input_stream(
             cfg,
             do_read_2,
             sink,
+            overruns,
             err_cb,
             i8: I8,
             i16: I16,
@@ -344,14 +733,43 @@ fn input_stream(
             f64: F64
         ),
         n => {
-            return Err(color_eyre::eyre::eyre!(
-                "I don't know how to support devices with {} channels, idk complain on github",
-                n
-            ));
+            tracing::info!("Using two of the input's {} channels", n);
+
+            handle_inps!(
+                fmt,
+                dev,
+                cfg,
+                |data, sink, overruns| do_read_n(
+                    data,
+                    sink,
+                    overruns,
+                    device_channels,
+                    &first_channel,
+                ),
+                sink,
+                overruns,
+                err_cb,
+                i8: I8,
+                i16: I16,
+                i32: I32,
+                i64: I64,
+                u8: U8,
+                u16: U16,
+                u32: U32,
+                u64: U64,
+                f32: F32,
+                f64: F64
+            )
         }
     };
 
-    Ok((stream, source.into_view()))
+    Ok((
+        stream,
+        source.into_view(),
+        overruns_out,
+        channels,
+        device_channels,
+    ))
 }
 
 struct CountingSignal {
@@ -372,6 +790,15 @@ impl CountingSignal {
         self.inner.extend_from_slice(buf);
         self.index = 0;
     }
+
+    /// Like [`CountingSignal::prep`], taking one channel out of interleaved
+    /// frames
+    fn prep_channel(&mut self, frames: &[f32], channel: usize, channels: usize) {
+        self.inner.clear();
+        self.inner
+            .extend(frames.iter().skip(channel).step_by(channels));
+        self.index = 0;
+    }
 }
 
 impl dasp_signal::Signal for CountingSignal {
@@ -391,119 +818,289 @@ impl dasp_signal::Signal for CountingSignal {
     }
 }
 
+/// How the graph's output is resampled to an output device's rate, in
+/// increasing order of quality and cost
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+)]
+#[repr(u8)]
+pub enum ResamplerQuality {
+    /// Straight lines between samples, cheap but lets through aliasing
+    Linear,
+    #[default]
+    #[strum(serialize = "Sinc (16 taps)")]
+    Sinc16,
+    #[strum(serialize = "Sinc (64 taps)")]
+    Sinc64,
+}
+
+/// The interpolator picked by a [`ResamplerQuality`]
+enum ResamplerInterpolator {
+    Linear(Linear<f32>),
+    Sinc16(Sinc<[f32; 16]>),
+    Sinc64(Sinc<[f32; 64]>),
+}
+
+impl ResamplerInterpolator {
+    fn new(quality: ResamplerQuality) -> Self {
+        match quality {
+            ResamplerQuality::Linear => Self::Linear(Linear::new(0.0, 0.0)),
+            ResamplerQuality::Sinc16 => {
+                Self::Sinc16(Sinc::new(dasp_ring_buffer::Fixed::from([0.0; 16])))
+            }
+            ResamplerQuality::Sinc64 => {
+                Self::Sinc64(Sinc::new(dasp_ring_buffer::Fixed::from([0.0; 64])))
+            }
+        }
+    }
+}
+
+impl Interpolator for ResamplerInterpolator {
+    type Frame = f32;
+
+    fn interpolate(&self, x: f64) -> Self::Frame {
+        match self {
+            ResamplerInterpolator::Linear(i) => i.interpolate(x),
+            ResamplerInterpolator::Sinc16(i) => i.interpolate(x),
+            ResamplerInterpolator::Sinc64(i) => i.interpolate(x),
+        }
+    }
+
+    fn next_source_frame(&mut self, source_frame: Self::Frame) {
+        match self {
+            ResamplerInterpolator::Linear(i) => i.next_source_frame(source_frame),
+            ResamplerInterpolator::Sinc16(i) => i.next_source_frame(source_frame),
+            ResamplerInterpolator::Sinc64(i) => i.next_source_frame(source_frame),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            ResamplerInterpolator::Linear(i) => i.reset(),
+            ResamplerInterpolator::Sinc16(i) => i.reset(),
+            ResamplerInterpolator::Sinc64(i) => i.reset(),
+        }
+    }
+}
+
+type ResamplingConverter<S> = Converter<S, ResamplerInterpolator>;
+
+/// Converts the graph's output to the rate of an output device
+enum Resampler {
+    Direct(ResamplingConverter<CountingSignal>),
+    /// Upsample by an integer factor first, so the final conversion has less
+    /// imaging to deal with
+    Oversampled(ResamplingConverter<ResamplingConverter<CountingSignal>>),
+}
+
+impl Resampler {
+    fn new(target_sample_rate: usize, oversampling: u32, quality: ResamplerQuality) -> Self {
+        let source_rate = sample_rate() as f64;
+
+        if oversampling <= 1 {
+            return Self::Direct(Converter::from_hz_to_hz(
+                CountingSignal::new(),
+                ResamplerInterpolator::new(quality),
+                source_rate,
+                target_sample_rate as f64,
+            ));
+        }
+
+        let oversampled_rate = source_rate * oversampling as f64;
+        let upsampler = Converter::from_hz_to_hz(
+            CountingSignal::new(),
+            ResamplerInterpolator::new(quality),
+            source_rate,
+            oversampled_rate,
+        );
+
+        Self::Oversampled(Converter::from_hz_to_hz(
+            upsampler,
+            ResamplerInterpolator::new(quality),
+            oversampled_rate,
+            target_sample_rate as f64,
+        ))
+    }
+
+    fn counter(&self) -> &CountingSignal {
+        match self {
+            Resampler::Direct(c) => c.source(),
+            Resampler::Oversampled(c) => c.source().source(),
+        }
+    }
+
+    fn counter_mut(&mut self) -> &mut CountingSignal {
+        match self {
+            Resampler::Direct(c) => c.source_mut(),
+            Resampler::Oversampled(c) => c.source_mut().source_mut(),
+        }
+    }
+}
+
+impl dasp_signal::Signal for Resampler {
+    type Frame = f32;
+
+    fn next(&mut self) -> Self::Frame {
+        match self {
+            Resampler::Direct(c) => c.next(),
+            Resampler::Oversampled(c) => c.next(),
+        }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        match self {
+            Resampler::Direct(c) => c.is_exhausted(),
+            Resampler::Oversampled(c) => c.is_exhausted(),
+        }
+    }
+}
+
 fn do_write_1<T: Sample + FromSample<f32> + dasp_frame::Frame>(
     data: &mut [T],
     source: &mut splittable::View<Source<f32>>,
-    trigger_catchup: &mut Arc<AtomicU8>,
+    controls: &OutputControls,
     target_sample_rate: usize,
-    mut resampler: &mut Converter<CountingSignal, Sinc<[f32; 16]>>,
+    mut resampler: &mut Resampler,
+    gain: &mut GainRamp,
+    stats: &OutputStats,
 ) {
-    let input_len = (data.len() as f32 * (48_000.0 / target_sample_rate as f32)) as usize;
+    let started = Instant::now();
+    let input_len =
+        (data.len() as f32 * (sample_rate() as f32 / target_sample_rate as f32)) as usize;
 
     if source.try_grant(input_len).unwrap() {
         let input_view = source.view();
+        stats.record(Some(input_view.len()));
 
-        let offs = input_view.len() - input_len;
-
-        let allowed_latency = 2;
+        let skip = controls.catchup(input_view.len() - input_len);
+        if skip > 0 {
+            tracing::debug!("Skipping {} samples so the output catches up", skip);
+        }
 
-        if (trigger_catchup
-            .fetch_update(
-                atomig::Ordering::SeqCst,
-                std::sync::atomic::Ordering::SeqCst,
-                |x| Some(x.saturating_sub(1)),
-            )
-            .unwrap()
-            > 0)
-            && offs >= (input_len * allowed_latency)
-        {
-            tracing::debug!("Skipping {} samples so the output catches up", offs);
-            resampler.source_mut().prep(&input_view[offs..]);
-
-            Signal::until_exhausted(resampler)
-                .map(|x| <T as Sample>::from_sample(x))
-                .collect_slice(data);
-            let len = input_view.len();
-            source.release(len);
-        } else {
-            resampler.source_mut().prep(input_view);
+        resampler.counter_mut().prep(&input_view[skip..]);
 
-            Signal::until_exhausted(&mut resampler)
-                .map(|x| <T as Sample>::from_sample(x))
-                .collect_slice(data);
-            source.release(resampler.source().index);
-        }
+        Signal::until_exhausted(&mut resampler)
+            .zip(gain.steps(data.len()))
+            .map(|(x, g)| <T as Sample>::from_sample(x * g))
+            .collect_slice(data);
+        source.release(skip + resampler.counter().index);
     } else {
+        stats.record(None);
         data.fill(<T as Sample>::from_sample(0.0f32));
         // println!("output fuck");
         // oops
     };
+
+    stats.record_load(started, data.len(), target_sample_rate);
 }
 
 fn do_write_2<T: Sample + FromSample<f32>>(
     data: &mut [T],
     source: &mut splittable::View<Source<f32>>,
-    trigger_catchup: &mut Arc<AtomicU8>,
+    controls: &OutputControls,
     target_sample_rate: usize,
-    resampler: &mut Converter<CountingSignal, Sinc<[f32; 16]>>,
+    resamplers: &mut [Resampler; 2],
+    gain: &mut GainRamp,
+    stats: &OutputStats,
 ) {
-    let input_len = ((data.len() / 2) as f32 * (48_000.0 / target_sample_rate as f32)) as usize;
+    do_write_n(
+        data,
+        source,
+        controls,
+        target_sample_rate,
+        resamplers,
+        gain,
+        stats,
+        2,
+    );
+}
 
-    if source.try_grant(input_len).unwrap() {
+/// Like [`do_write_2`] for devices with `channels` channels, writing to the
+/// pair starting at the controls' `first_channel` and leaving the rest silent
+#[allow(clippy::too_many_arguments)]
+fn do_write_n<T: Sample + FromSample<f32>>(
+    data: &mut [T],
+    source: &mut splittable::View<Source<f32>>,
+    controls: &OutputControls,
+    target_sample_rate: usize,
+    resamplers: &mut [Resampler; 2],
+    gain: &mut GainRamp,
+    stats: &OutputStats,
+    channels: usize,
+) {
+    let started = Instant::now();
+    let first = controls
+        .first_channel
+        .load(std::sync::atomic::Ordering::Relaxed)
+        .min(channels - 2);
+    let input_len = ((data.len() / channels) as f32
+        * (sample_rate() as f32 / target_sample_rate as f32)) as usize;
+
+    // the source holds interleaved frames, lengths here are in frames
+    if source.try_grant(input_len * 2).unwrap() {
         let input_view = source.view();
+        let queued = input_view.len() / 2;
+        stats.record(Some(queued));
 
-        let offs = input_view.len() - input_len;
-
-        let allowed_latency = 2;
-
-        if (trigger_catchup
-            .fetch_update(
-                atomig::Ordering::SeqCst,
-                std::sync::atomic::Ordering::SeqCst,
-                |x| Some(x.saturating_sub(1)),
-            )
-            .unwrap()
-            > 0)
-            && offs >= (input_len * allowed_latency)
-        {
-            tracing::info!(
-                "Skipping {} samples so the output catches up (max buffer: {})",
-                offs,
-                input_len * allowed_latency
-            );
-            resampler.source_mut().prep(&input_view[offs..]);
-
-            for o in data.chunks_mut(2) {
-                let x = <T as Sample>::from_sample(resampler.next());
-
-                o.fill(x);
-            }
-
-            let len = input_view.len();
-            source.release(len);
-        } else {
-            resampler.source_mut().prep(input_view);
-
-            for o in data.chunks_mut(2) {
-                let x = <T as Sample>::from_sample(resampler.next());
+        let skip = controls.catchup(queued - input_len);
+        if skip > 0 {
+            tracing::info!("Skipping {} frames so the output catches up", skip);
+        }
 
-                o.fill(x);
-            }
+        prep_stereo(resamplers, &input_view[skip * 2..queued * 2]);
+        write_stereo(data, channels, first, resamplers, gain);
 
-            source.release(resampler.source().index);
-        }
+        source.release((skip + resamplers[0].counter().index) * 2);
     } else {
+        stats.record(None);
         data.fill(<T as Sample>::from_sample(0.0f32));
         // println!("output fuck");
         // oops
     };
+
+    stats.record_load(started, data.len() / channels, target_sample_rate);
+}
+
+/// Split interleaved frames between the left and right resamplers
+fn prep_stereo(resamplers: &mut [Resampler; 2], frames: &[f32]) {
+    for (channel, resampler) in resamplers.iter_mut().enumerate() {
+        resampler.counter_mut().prep_channel(frames, channel, 2);
+    }
+}
+
+/// Write the resampled left and right channels to `channels` channel frames,
+/// at `first` and the channel after it
+fn write_stereo<T: Sample + FromSample<f32>>(
+    data: &mut [T],
+    channels: usize,
+    first: usize,
+    resamplers: &mut [Resampler; 2],
+    gain: &mut GainRamp,
+) {
+    let steps = gain.steps(data.len() / channels);
+    for (o, g) in data.chunks_exact_mut(channels).zip(steps) {
+        o.fill(<T as Sample>::from_sample(0.0f32));
+        for (v, resampler) in o[first..].iter_mut().zip(resamplers.iter_mut()) {
+            *v = <T as Sample>::from_sample(resampler.next() * g);
+        }
+    }
 }
 
 macro_rules! handle_outs {
-    ($fmt:ident, $dev:ident, $cfg:ident, $write_fn:ident, $source:ident, $trigger_catchup:ident, $target_sample_rate:ident, $resampler:ident, $err_cb:ident, $($typ:ty: $tyn:tt),*) => {
+    ($fmt:ident, $dev:ident, $cfg:ident, $write_fn:expr, $source:ident, $controls:ident, $target_sample_rate:ident, $resampler:ident, $gain:ident, $stats:ident, $err_cb:ident, $($typ:ty: $tyn:tt),*) => {
         match $fmt {
             $(
-                cpal::SampleFormat::$tyn => { $dev.build_output_stream(&$cfg, move |data: &mut [$typ], _| $write_fn(data, &mut $source, &mut $trigger_catchup, $target_sample_rate, &mut $resampler), $err_cb, None)? }
+                cpal::SampleFormat::$tyn => { $dev.build_output_stream(&$cfg, move |data: &mut [$typ], _| { elevate_current_thread(); $write_fn(data, &mut $source, &$controls, $target_sample_rate, &mut $resampler, &mut $gain, &$stats) }, $err_cb, None)? }
             ),*
                 f => { return Err(::color_eyre::eyre::eyre!("I don't know how to handle {} samples", f)) }
         }
@@ -512,10 +1109,20 @@ macro_rules! handle_outs {
 
 fn output_stream(
     dev: cpal::Device,
-) -> color_eyre::Result<(cpal::Stream, Sink<f32>, Arc<AtomicU8>)> {
+    audible: Arc<AtomicBool>,
+    controls: Arc<OutputControls>,
+    quality: ResamplerQuality,
+) -> color_eyre::Result<(cpal::Stream, Sink<f32>, Arc<OutputStats>, usize, usize)> {
+    let rate = choose_sample_rate(dev.default_output_config()?.sample_rate().0);
+
     let (cfg, fmt) = if let Some(cfg) = dev
         .supported_output_configs()?
-        .sorted_by_key(|cfg| (cfg.channels(), cfg.max_sample_rate().0.abs_diff(48_000)))
+        .sorted_by_key(|cfg| {
+            (
+                channel_preference(cfg.channels()),
+                cfg.max_sample_rate().0.abs_diff(rate),
+            )
+        })
         .next()
     {
         let cfg = cfg.with_max_sample_rate();
@@ -538,73 +1145,171 @@ fn output_stream(
 
     tracing::info!(?cfg, "Selected output cfg");
 
-    let (sink, source) = rivulet::circular_buffer::<f32>(8192);
+    let device_channels = cfg.channels as usize;
+    let channels = device_channels.min(2);
+    let (sink, source) = rivulet::circular_buffer::<f32>(8192 * channels);
     let mut source = source.into_view();
 
     let err_cb = |err| tracing::warn!("output message: {:#?}", err);
 
-    let mut trigger_catchup = Arc::new(AtomicU8::new(0));
-    let trigger_catchup_out = Arc::clone(&trigger_catchup);
+    let stats = Arc::new(OutputStats::default());
+    OUTPUT_STATS.lock().unwrap().push(Arc::downgrade(&stats));
+    let stats_out = Arc::clone(&stats);
 
     let target_sample_rate = cfg.sample_rate.0 as usize;
-    let sinc = Sinc::new(dasp_ring_buffer::Fixed::from([0.0; 16]));
-    let mut resampler = Converter::from_hz_to_hz(
-        CountingSignal::new(),
-        sinc,
-        48_000.0,
-        target_sample_rate as f64,
-    );
+    let oversampling = OUTPUT_OVERSAMPLING.load(std::sync::atomic::Ordering::Relaxed);
+    let mut gain = GainRamp::new(audible);
 
     let stream = match cfg.channels {
-        1 => handle_outs!(
-            fmt,
-            dev,
-            cfg,
-            do_write_1,
-            source,
-            trigger_catchup,
-            target_sample_rate,
-            resampler,
-            err_cb,
-            i8: I8,
-            i16: I16,
-            i32: I32,
-            i64: I64,
-            u8: U8,
-            u16: U16,
-            u32: U32,
-            u64: U64,
-            f32: F32,
-            f64: F64
-        ),
-        2 => handle_outs!(
-            fmt,
-            dev,
-            cfg,
-            do_write_2,
-            source,
-            trigger_catchup,
-            target_sample_rate,
-            resampler,
-            err_cb,
-            i8: I8,
-            i16: I16,
-            i32: I32,
-            i64: I64,
-            u8: U8,
-            u16: U16,
-            u32: U32,
-            u64: U64,
-            f32: F32,
-            f64: F64
-        ),
+        1 => {
+            let mut resampler = Resampler::new(target_sample_rate, oversampling, quality);
+
+            handle_outs!(
+                fmt,
+                dev,
+                cfg,
+                do_write_1,
+                source,
+                controls,
+                target_sample_rate,
+                resampler,
+                gain,
+                stats,
+                err_cb,
+                i8: I8,
+                i16: I16,
+                i32: I32,
+                i64: I64,
+                u8: U8,
+                u16: U16,
+                u32: U32,
+                u64: U64,
+                f32: F32,
+                f64: F64
+            )
+        }
+        2 => {
+            let mut resamplers = [
+                Resampler::new(target_sample_rate, oversampling, quality),
+                Resampler::new(target_sample_rate, oversampling, quality),
+            ];
+
+            handle_outs!(
+                fmt,
+                dev,
+                cfg,
+                do_write_2,
+                source,
+                controls,
+                target_sample_rate,
+                resamplers,
+                gain,
+                stats,
+                err_cb,
+                i8: I8,
+                i16: I16,
+                i32: I32,
+                i64: I64,
+                u8: U8,
+                u16: U16,
+                u32: U32,
+                u64: U64,
+                f32: F32,
+                f64: F64
+            )
+        }
         n => {
-            return Err(color_eyre::eyre::eyre!(
-                "I don't know how to support devices with {} channels, idk complain on github",
-                n
-            ));
+            tracing::info!("Using two of the output's {} channels", n);
+
+            let mut resamplers = [
+                Resampler::new(target_sample_rate, oversampling, quality),
+                Resampler::new(target_sample_rate, oversampling, quality),
+            ];
+
+            handle_outs!(
+                fmt,
+                dev,
+                cfg,
+                |data, source, controls, target_sample_rate, resamplers, gain, stats| {
+                    do_write_n(
+                        data,
+                        source,
+                        controls,
+                        target_sample_rate,
+                        resamplers,
+                        gain,
+                        stats,
+                        n as usize,
+                    )
+                },
+                source,
+                controls,
+                target_sample_rate,
+                resamplers,
+                gain,
+                stats,
+                err_cb,
+                i8: I8,
+                i16: I16,
+                i32: I32,
+                i64: I64,
+                u8: U8,
+                u16: U16,
+                u32: U32,
+                u64: U64,
+                f32: F32,
+                f64: F64
+            )
         }
     };
 
-    Ok((stream, sink, trigger_catchup_out))
+    Ok((stream, sink, stats_out, channels, device_channels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ids::NodeId,
+        node::{run_block, Node, NodeStatic},
+        nodes::{peak_eq::PeakEq, signal_gen::SignalGen},
+    };
+
+    /// The global rate can only be chosen once, so everything that depends on
+    /// it is checked here
+    #[test]
+    fn nodes_follow_a_44100hz_device() {
+        with_sample_rate(48000, || {
+            let early_eq = PeakEq::new(NodeId::generate());
+            early_eq.set_param("gain", 12.0);
+
+            // as opening a 44.1khz device first would, later devices don't change it
+            assert_eq!(choose_sample_rate(44100), 44100);
+            assert_eq!(choose_sample_rate(48000), 44100);
+
+            let gen = SignalGen::new(NodeId::generate());
+            gen.set_param("frequency", 441.0);
+
+            let second = run_block(&gen, &vec![0.0; 44100]);
+            let cycles = second
+                .iter()
+                .tuple_windows()
+                .filter(|(a, b)| **a < 0.0 && **b >= 0.0)
+                .count();
+            assert!((cycles as i32 - 441).abs() <= 1, "{cycles} cycles");
+
+            let eq = PeakEq::new(NodeId::generate());
+            eq.set_param("gain", 12.0);
+
+            let impulse = (0..256)
+                .map(|i| if i == 0 { 1.0 } else { 0.0 })
+                .collect_vec();
+            assert_ne!(run_block(&early_eq, &impulse), run_block(&eq, &impulse));
+
+            early_eq.refresh_settings();
+            early_eq.reset_state();
+            eq.reset_state();
+            assert_eq!(run_block(&early_eq, &impulse), run_block(&eq, &impulse));
+        });
+    }
 }
@@ -3,18 +3,32 @@
 
 use clap::Parser;
 
+mod automation;
 mod devices;
 mod ids;
+mod midi;
+mod morph;
 mod node;
 mod nodes;
+#[cfg(feature = "osc")]
+mod osc;
+mod palette;
+mod profiler;
 mod runtime;
+mod settings;
 mod theme;
+mod util;
 
 #[derive(Parser)]
 pub struct Params {
     /// Start up with a clean state
     #[clap(short, long)]
     clean: bool,
+
+    /// Listen for OSC messages setting node parameters on this UDP port
+    #[cfg(feature = "osc")]
+    #[clap(long)]
+    osc_port: Option<u16>,
 }
 
 fn install_tracing() -> color_eyre::Result<()> {
@@ -60,6 +74,9 @@ fn install_tracing() -> color_eyre::Result<()> {
     //Ok(Box::new(()))
 }
 
+/// Also where eframe keeps its storage, see [`eframe::storage_dir`]
+pub const APP_NAME: &str = "DSP Stuff";
+
 fn main() -> color_eyre::Result<()> {
     let params = Params::parse();
 
@@ -70,7 +87,7 @@ fn main() -> color_eyre::Result<()> {
     let options = eframe::NativeOptions::default();
 
     eframe::run_native(
-        "DSP Stuff",
+        APP_NAME,
         options,
         Box::new(move |cc| Ok(Box::new(runtime::UiContext::new(cc, &params)))),
     )?;
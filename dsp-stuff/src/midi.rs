@@ -0,0 +1,206 @@
+//! MIDI input
+//!
+//! Like audio devices, connections are owned by a thread of their own and are
+//! opened and closed by sending it commands. Incoming messages are written to a
+//! [`MidiState`] that nodes read from.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use atomig::Atomic;
+use once_cell::sync::Lazy;
+
+use crate::ids::DeviceId;
+
+/// Name we show up as to other MIDI software
+const CLIENT_NAME: &str = "dsp-stuff";
+
+/// What's been received on a MIDI port
+///
+/// Written from the MIDI callback thread and read by nodes.
+pub struct MidiState {
+    /// Frequency (in hz) of the most recently pressed note that's still held,
+    /// or the last note released
+    pub frequency: Atomic<f32>,
+    /// Whether any note is held
+    pub gate: Atomic<bool>,
+    /// Latest value of every controller, from 0 to 1
+    pub controllers: Box<[Atomic<f32>]>,
+
+    /// Notes being held, most recently pressed last
+    held: Mutex<Vec<u8>>,
+}
+
+impl Default for MidiState {
+    fn default() -> Self {
+        Self {
+            frequency: Atomic::new(440.0),
+            gate: Atomic::new(false),
+            controllers: (0..128).map(|_| Atomic::new(0.0)).collect(),
+            held: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl MidiState {
+    fn handle(&self, message: &[u8]) {
+        let [status, data1, data2, ..] = *message else {
+            return;
+        };
+
+        match status & 0xf0 {
+            // note on, with a velocity of zero meaning note off
+            0x90 if data2 > 0 => {
+                let mut held = self.held.lock().unwrap();
+                held.retain(|n| *n != data1);
+                held.push(data1);
+
+                self.frequency
+                    .store(note_frequency(data1), std::sync::atomic::Ordering::Relaxed);
+                self.gate.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            0x80 | 0x90 => {
+                let mut held = self.held.lock().unwrap();
+                held.retain(|n| *n != data1);
+
+                // fall back to whichever note is still held, like a mono synth
+                if let Some(note) = held.last() {
+                    self.frequency
+                        .store(note_frequency(*note), std::sync::atomic::Ordering::Relaxed);
+                } else {
+                    self.gate.store(false, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+            0xb0 => {
+                if let Some(controller) = self.controllers.get(data1 as usize) {
+                    controller.store(data2 as f32 / 127.0, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Equal tempered frequency of a MIDI note, note 69 is A4 at 440hz
+fn note_frequency(note: u8) -> f32 {
+    440.0 * 2.0f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+type MidiCmdChan = std::sync::mpsc::SyncSender<(MidiCommand, oneshot::Sender<MidiResponse>)>;
+
+static MIDI_CMD_CHAN: Lazy<MidiCmdChan> = Lazy::new(|| {
+    let (sender, receiver): (
+        _,
+        std::sync::mpsc::Receiver<(MidiCommand, oneshot::Sender<MidiResponse>)>,
+    ) = std::sync::mpsc::sync_channel(1);
+
+    std::thread::spawn(move || {
+        let mut connections: HashMap<DeviceId, midir::MidiInputConnection<()>> = HashMap::new();
+
+        for (cmd, resp_chan) in receiver {
+            match cmd {
+                MidiCommand::ListPorts => {
+                    let ports = match midir::MidiInput::new(CLIENT_NAME) {
+                        Ok(input) => input
+                            .ports()
+                            .iter()
+                            .filter_map(|p| input.port_name(p).ok())
+                            .collect(),
+                        Err(e) => {
+                            tracing::error!("Couldn't list MIDI ports: {:#}", e);
+                            Vec::new()
+                        }
+                    };
+
+                    resp_chan.send(MidiResponse::Ports(ports)).unwrap();
+                }
+                MidiCommand::Open(name, state) => {
+                    tracing::info!("Opening MIDI port {name:?}");
+
+                    let r = match open(&name, state) {
+                        Ok(connection) => {
+                            let id = DeviceId::generate();
+                            connections.insert(id, connection);
+
+                            Some(id)
+                        }
+                        Err(e) => {
+                            tracing::error!("Opening MIDI port failed: {:#}", e);
+                            None
+                        }
+                    };
+
+                    resp_chan.send(MidiResponse::Opened(r)).unwrap();
+                }
+                MidiCommand::Close(id) => {
+                    tracing::info!("Closing MIDI port {id:?}");
+
+                    if let Some(connection) = connections.remove(&id) {
+                        connection.close();
+                    }
+
+                    resp_chan.send(MidiResponse::Closed).unwrap();
+                }
+            }
+        }
+    });
+
+    sender
+});
+
+fn open(name: &str, state: Arc<MidiState>) -> color_eyre::Result<midir::MidiInputConnection<()>> {
+    let input = midir::MidiInput::new(CLIENT_NAME)?;
+
+    let port = input
+        .ports()
+        .into_iter()
+        .find(|p| input.port_name(p).ok().as_deref() == Some(name))
+        .ok_or_else(|| color_eyre::eyre::eyre!("There's no MIDI port called {name:?}"))?;
+
+    let connection = input
+        .connect(
+            &port,
+            CLIENT_NAME,
+            move |_stamp, message, _| state.handle(message),
+            (),
+        )
+        .map_err(|e| color_eyre::eyre::eyre!("{e}"))?;
+
+    Ok(connection)
+}
+
+pub fn invoke(cmd: MidiCommand) -> MidiResponse {
+    let (resp_in, resp_out) = oneshot::channel();
+    MIDI_CMD_CHAN.send((cmd, resp_in)).unwrap();
+    resp_out.recv().unwrap()
+}
+
+pub enum MidiCommand {
+    ListPorts,
+    Open(String, Arc<MidiState>),
+    Close(DeviceId),
+}
+
+pub enum MidiResponse {
+    Ports(Vec<String>),
+    Opened(Option<DeviceId>),
+    Closed,
+}
+
+impl MidiResponse {
+    pub fn ports(self) -> Option<Vec<String>> {
+        match self {
+            Self::Ports(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn opened(self) -> Option<Option<DeviceId>> {
+        match self {
+            Self::Opened(v) => Some(v),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ids::NodeId, node::Node, nodes::Nodes};
+
+/// The parameter values of a set of nodes at one point in time
+///
+/// Only values are captured, the structure of the graph (nodes, ports and
+/// links) is left alone.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct Snapshot {
+    params: HashMap<NodeId, HashMap<String, f32>>,
+    choices: HashMap<NodeId, HashMap<String, String>>,
+}
+
+impl Snapshot {
+    pub fn capture<'a>(nodes: impl IntoIterator<Item = (NodeId, &'a Nodes)>) -> Self {
+        let mut this = Self::default();
+
+        for (id, node) in nodes {
+            this.params.insert(
+                id,
+                node.list_params()
+                    .into_iter()
+                    .map(|(name, v)| (name.to_owned(), v))
+                    .collect(),
+            );
+            this.choices.insert(
+                id,
+                node.list_choices()
+                    .into_iter()
+                    .map(|(name, v)| (name.to_owned(), v.to_owned()))
+                    .collect(),
+            );
+        }
+
+        this
+    }
+}
+
+/// Two snapshots and how far between them the patch currently is
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct Morph {
+    pub a: Option<Snapshot>,
+    pub b: Option<Snapshot>,
+
+    /// 0.0 is entirely a, 1.0 is entirely b
+    pub position: f32,
+}
+
+impl Morph {
+    pub fn is_ready(&self) -> bool {
+        self.a.is_some() && self.b.is_some()
+    }
+
+    /// Write the values at the current position to every node present in both
+    /// snapshots
+    ///
+    /// Numeric parameters are interpolated, options switch over at the midpoint.
+    pub fn apply<'a>(&self, lookup: impl Fn(NodeId) -> Option<&'a Nodes>) {
+        let (Some(a), Some(b)) = (&self.a, &self.b) else {
+            return;
+        };
+
+        let t = self.position.clamp(0.0, 1.0);
+
+        for (id, a_params) in &a.params {
+            let (Some(node), Some(b_params)) = (lookup(*id), b.params.get(id)) else {
+                continue;
+            };
+
+            let current = node.list_params();
+
+            for (name, a_v) in a_params {
+                let Some(b_v) = b_params.get(name) else {
+                    continue;
+                };
+
+                let v = a_v + (b_v - a_v) * t;
+
+                // avoid re-running a node's settings change hook when nothing moved
+                if current.iter().any(|(n, c)| *n == name.as_str() && *c != v) {
+                    node.set_param(name, v.into());
+                }
+            }
+        }
+
+        let choices = if t < 0.5 { &a.choices } else { &b.choices };
+
+        for (id, node_choices) in choices {
+            if !(a.choices.contains_key(id) && b.choices.contains_key(id)) {
+                continue;
+            }
+
+            let Some(node) = lookup(*id) else {
+                continue;
+            };
+
+            let current = node.list_choices();
+
+            for (name, v) in node_choices {
+                if current
+                    .iter()
+                    .any(|(n, c)| *n == name.as_str() && *c != v.as_str())
+                {
+                    node.set_choice(name, v);
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize},
+        Arc,
+    },
+};
 use eframe::egui;
 use once_cell::sync::Lazy;
 use rivulet::{
@@ -11,7 +17,7 @@ use sharded_slab::{
     pool::{OwnedRefMut, RefMut},
     Clear, Pool,
 };
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
 
 use crate::ids::{NodeId, PortId};
 
@@ -24,6 +30,22 @@ pub struct PortStorageInner {
     pub local_indexes: HashMap<String, usize>,
     pub portid_indexes: HashMap<PortId, usize>,
     pub deleted: Vec<PortId>,
+    /// Recent peak level of each port, by local index
+    ///
+    /// These store the bits of a non-negative f32, which order the same way
+    /// as the floats themselves so `fetch_max` does what you'd expect.
+    pub levels: Vec<Arc<AtomicU32>>,
+    /// Peak level over every port since the runtime last took it, stored the
+    /// same way as `levels`
+    pub block_peak: Arc<AtomicU32>,
+    /// Only used on output ports, see [`GainMatch`]
+    pub gain_match: Arc<GainMatch>,
+    /// Only used on output ports, output just what the node adds to the
+    /// signal passing through it, see [`can_wet_solo`]
+    pub wet_solo: Arc<AtomicBool>,
+    /// Only used on output ports, samples output since the node was last
+    /// started, for fading in
+    pub since_start: Arc<AtomicUsize>,
 }
 
 impl PortStorageInner {
@@ -34,11 +56,17 @@ impl PortStorageInner {
             .map(|(i, k)| (k.to_owned(), i))
             .collect();
         let portid_indexes = ports.values().enumerate().map(|(i, v)| (*v, i)).collect();
+        let levels = ports.iter().map(|_| Arc::new(AtomicU32::new(0))).collect();
         Self {
             ports,
             local_indexes,
             portid_indexes,
             deleted: Vec::new(),
+            levels,
+            block_peak: Arc::default(),
+            gain_match: Arc::default(),
+            wet_solo: Arc::default(),
+            since_start: Arc::default(),
         }
     }
 }
@@ -78,6 +106,7 @@ impl PortStorage {
         inner.ports.insert(name.clone(), pid);
         inner.local_indexes.insert(name, idx);
         inner.portid_indexes.insert(pid, idx);
+        inner.levels.push(Arc::new(AtomicU32::new(0)));
     }
 
     pub fn get_id(&self, name: &str) -> Option<PortId> {
@@ -99,6 +128,111 @@ impl PortStorage {
     pub fn get_idxs(&self) -> HashMap<PortId, usize> {
         self.0.read().unwrap().portid_indexes.clone()
     }
+
+    /// The other half of a stereo pair, ports are paired by name: "left" with
+    /// "right", "in_left" with "in_right", "out_l" with "out_r" and so on
+    pub fn stereo_partner(&self, id: PortId) -> Option<PortId> {
+        let inner = self.0.read().unwrap();
+        let (name, _) = inner.ports.iter().find(|(_, v)| **v == id)?;
+
+        // keep looking until a suffix gives a partner that's really a port
+        [
+            ("left", "right"),
+            ("right", "left"),
+            ("_l", "_r"),
+            ("_r", "_l"),
+        ]
+        .iter()
+        .find_map(|(side, other)| {
+            let partner = format!("{}{other}", name.strip_suffix(side)?);
+            inner.ports.get(&partner).copied()
+        })
+    }
+
+    /// Note the peak level of a block of samples passing through a port
+    pub fn record_level(&self, idx: usize, block: &[f32]) {
+        let peak = block.iter().fold(0.0f32, |acc, x| acc.max(x.abs()));
+
+        let inner = self.0.read().unwrap();
+
+        if let Some(level) = inner.levels.get(idx) {
+            level.fetch_max(peak.to_bits(), std::sync::atomic::Ordering::Relaxed);
+        }
+
+        inner
+            .block_peak
+            .fetch_max(peak.to_bits(), std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn gain_match(&self) -> Arc<GainMatch> {
+        Arc::clone(&self.0.read().unwrap().gain_match)
+    }
+
+    pub fn wet_solo(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.0.read().unwrap().wet_solo)
+    }
+
+    /// Fade the node's output in again, it's being started over
+    pub fn restart_fade(&self) {
+        self.0
+            .read()
+            .unwrap()
+            .since_start
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Note that `n` more samples have been output, returning how many had
+    /// been before
+    fn advance_fade(&self, n: usize) -> usize {
+        self.0
+            .read()
+            .unwrap()
+            .since_start
+            .fetch_update(
+                std::sync::atomic::Ordering::Relaxed,
+                std::sync::atomic::Ordering::Relaxed,
+                |pos| Some(pos.saturating_add(n)),
+            )
+            .unwrap()
+    }
+
+    /// Read and reset the peak level over every port since this was last
+    /// called, unlike `take_level` this isn't shared with the UI
+    pub fn take_block_peak(&self) -> f32 {
+        let bits = self
+            .0
+            .read()
+            .unwrap()
+            .block_peak
+            .swap(0, std::sync::atomic::Ordering::Relaxed);
+
+        f32::from_bits(bits)
+    }
+
+    /// Read the recent peak level of a port, decaying it so that ports which
+    /// go quiet fade out over a few frames
+    pub fn take_level(&self, id: PortId) -> f32 {
+        const LEVEL_DECAY: f32 = 0.8;
+
+        let inner = self.0.read().unwrap();
+        let Some(level) = inner
+            .portid_indexes
+            .get(&id)
+            .and_then(|idx| inner.levels.get(*idx))
+        else {
+            return 0.0;
+        };
+
+        let bits = level
+            .fetch_update(
+                std::sync::atomic::Ordering::Relaxed,
+                std::sync::atomic::Ordering::Relaxed,
+                |bits| Some((f32::from_bits(bits) * LEVEL_DECAY).to_bits()),
+            )
+            .unwrap();
+
+        f32::from_bits(bits)
+    }
 }
 
 #[enum_dispatch::enum_dispatch]
@@ -120,6 +254,74 @@ pub trait Node: Send + Sync {
     fn render(&self, ui: &mut egui::Ui);
 
     fn save(&self) -> serde_json::Value;
+
+    /// The names and current values of the adjustable parameters of this node
+    fn list_params(&self) -> Vec<(&'static str, f32)> {
+        Vec::new()
+    }
+
+    /// Set the parameter `name` to `value`, clamped to the parameter's range
+    ///
+    /// Returns false if the node has no parameter with the given name
+    fn set_param(&self, _name: &str, _value: f64) -> bool {
+        false
+    }
+
+    /// The names and currently selected options of the non-numeric parameters
+    /// of this node
+    fn list_choices(&self) -> Vec<(&'static str, &'static str)> {
+        Vec::new()
+    }
+
+    /// Select the option named `value` for the parameter `name`
+    ///
+    /// Returns false if the node has no such parameter, or it has no such option
+    fn set_choice(&self, _name: &str, _value: &str) -> bool {
+        false
+    }
+
+    /// Clear any internal state (delay lines, filter memories, envelopes),
+    /// leaving parameters untouched
+    fn reset_state(&self) {}
+
+    /// Work out anything derived from the settings again, such as filter
+    /// coefficients that depend on the sample rate
+    fn refresh_settings(&self) {}
+
+    /// Put every parameter back to the value it has on a freshly created node
+    fn reset_defaults(&self) {}
+
+    /// Which end of a bus the node is, every send is linked to every return
+    /// on the same bus
+    fn bus(&self) -> Option<BusEnd> {
+        None
+    }
+
+    /// Whether links into the node are always summed, whatever the input
+    /// mixing setting is
+    fn sums_inputs(&self) -> bool {
+        false
+    }
+
+    /// Whether the node can be part of a feedback loop, loops through other
+    /// nodes stall as each node in them waits on the others
+    fn breaks_cycles(&self) -> bool {
+        false
+    }
+
+    /// Whether the node holds on to something set up for the sample rate it
+    /// was made at (a plugin instance, a recording), which
+    /// [`Node::refresh_settings`] can't retune
+    fn rate_dependent(&self) -> bool {
+        false
+    }
+}
+
+/// One end of a bus, along with the number of the bus
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BusEnd {
+    Send(u8),
+    Return(u8),
 }
 
 pub trait NodeStatic {
@@ -127,7 +329,7 @@ pub trait NodeStatic {
     where
         Self: Sized;
 
-    fn restore(value: serde_json::Value) -> Self
+    fn restore(value: serde_json::Value) -> Result<Self, serde_json::Error>
     where
         Self: Sized;
 }
@@ -159,9 +361,164 @@ pub trait Perform: Node {
     );
 }
 
+/// How multiple links into the same input are combined
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum InputMixing {
+    /// Keeps the level the same no matter how many links are connected
+    #[default]
+    Average,
+    /// Behaves like a mixer, each link adds its full level
+    Sum,
+}
+
+static SUM_INPUTS: AtomicBool = AtomicBool::new(false);
+
+/// How long (in ms) outputs fade in for when a node starts
+static START_FADE: AtomicU32 = AtomicU32::new(5);
+
+pub fn set_start_fade(ms: u32) {
+    START_FADE.store(ms, std::sync::atomic::Ordering::Relaxed);
+}
+
+fn start_fade_samples() -> usize {
+    START_FADE.load(std::sync::atomic::Ordering::Relaxed) as usize
+        * crate::devices::sample_rate() as usize
+        / 1000
+}
+
+pub fn set_input_mixing(mode: InputMixing) {
+    SUM_INPUTS.store(
+        mode == InputMixing::Sum,
+        std::sync::atomic::Ordering::Relaxed,
+    );
+}
+
+pub fn input_mixing() -> InputMixing {
+    if SUM_INPUTS.load(std::sync::atomic::Ordering::Relaxed) {
+        InputMixing::Sum
+    } else {
+        InputMixing::Average
+    }
+}
+
+/// Seconds the loudness of the dry and processed signals are averaged over
+/// when matching gain
+const GAIN_MATCH_WINDOW: f32 = 0.4;
+
+/// Most the gain match will boost by, +12dB, so a node that's gone silent
+/// doesn't have its noise floor dragged up to the input's level
+const GAIN_MATCH_MAX: f32 = 4.0;
+
+/// Input and output ports carrying the signal itself through a node, rather
+/// than control inputs or extra taps, these are what gain matching and wet
+/// solo compare
+const SIGNAL_PORTS: &[(&str, &str)] = &[
+    ("in", "out"),
+    ("left", "left"),
+    ("right", "right"),
+    ("in_left", "out_left"),
+    ("in_right", "out_right"),
+    ("in_l", "out_l"),
+    ("in_r", "out_r"),
+];
+
+/// The input feeding the signal through to an output, by name, for taking the
+/// node out of a path
+pub fn signal_input(inputs: &PortStorage, output: &str) -> Option<PortId> {
+    SIGNAL_PORTS
+        .iter()
+        .filter(|(_, o)| *o == output)
+        .find_map(|(i, _)| inputs.get_id(i))
+        .or_else(|| inputs.get_id(output))
+}
+
+/// Whether the node has a signal passing through it, and so whether there's
+/// a dry signal to take away from the output when soloing the wet signal
+pub fn can_wet_solo(inputs: &PortStorage, outputs: &PortStorage) -> bool {
+    SIGNAL_PORTS
+        .iter()
+        .any(|(i, o)| inputs.get_idx(i).is_some() && outputs.get_idx(o).is_some())
+}
+
+/// Take the dry signal away from each output, leaving only what the node added
+fn wet_solo(inputs: &ProcessInput, outputs: &mut ProcessOutput) {
+    for (input, output) in SIGNAL_PORTS {
+        if let (Some(dry), Some(out)) = (inputs.get(input), outputs.get(output)) {
+            for (x, d) in out.iter_mut().zip(dry) {
+                *x -= d;
+            }
+        }
+    }
+}
+
+/// Trims a node's output so it's as loud as its input, for comparing an effect
+/// against the dry signal without the louder of the two sounding better
+///
+/// This is off by default. The loudness of each is its mean square averaged
+/// over [`GAIN_MATCH_WINDOW`]. Nodes without any of the usual signal ports,
+/// and those implementing [`Perform`] themselves, are left as they are.
+#[derive(Debug, Default)]
+pub struct GainMatch {
+    pub enabled: AtomicBool,
+    state: Mutex<GainMatchState>,
+}
+
+#[derive(Debug, Default)]
+struct GainMatchState {
+    dry: f32,
+    wet: f32,
+    gain: Option<f32>,
+}
+
+impl GainMatch {
+    fn trim(&self, inputs: &ProcessInput, outputs: &mut ProcessOutput) {
+        let power = |block: &[f32]| block.iter().map(|x| x * x).sum::<f32>() / block.len() as f32;
+
+        let dry = SIGNAL_PORTS
+            .iter()
+            .filter_map(|(name, _)| inputs.get_checked(name))
+            .map(power)
+            .sum::<f32>();
+
+        let wet = SIGNAL_PORTS
+            .iter()
+            .filter_map(|(_, name)| outputs.get(name).map(|out| power(out)))
+            .sum::<f32>();
+
+        let coef = (-(buf_size() as f32)
+            / (GAIN_MATCH_WINDOW * crate::devices::sample_rate() as f32))
+            .exp();
+
+        let mut state = self.state.lock().unwrap();
+        state.dry = dry + (state.dry - dry) * coef;
+        state.wet = wet + (state.wet - wet) * coef;
+
+        let target = if state.wet > 1e-12 {
+            (state.dry / state.wet).sqrt().min(GAIN_MATCH_MAX)
+        } else {
+            state.gain.unwrap_or(1.0)
+        };
+
+        // ramp over the block so the gain doesn't step
+        let from = state.gain.unwrap_or(target);
+        state.gain = Some(target);
+        drop(state);
+
+        for (_, name) in SIGNAL_PORTS {
+            if let Some(out) = outputs.get(name) {
+                let len = out.len() as f32;
+                for (i, x) in out.iter_mut().enumerate() {
+                    *x *= from + (target - from) * (i + 1) as f32 / len;
+                }
+            }
+        }
+    }
+}
+
 pub async fn collect_and_average(
     output: &mut [f32],
     input: &mut [&mut splittable::View<Source<f32>>],
+    mode: InputMixing,
 ) -> bool {
     let mut num_frames = 0.0001;
 
@@ -186,8 +543,10 @@ pub async fn collect_and_average(
     // NOTE: this function doesn't release the views, that should be done later
     // as an atomic operation
 
-    for v in output.iter_mut() {
-        *v /= num_frames;
+    if mode == InputMixing::Average {
+        for v in output.iter_mut() {
+            *v /= num_frames;
+        }
     }
 
     r
@@ -250,11 +609,160 @@ impl<'ports, 'buf, 'ps> ProcessOutput<'ports, 'buf, 'ps> {
     }
 }
 
+/// Most ports of either kind a node run by [`process_nested`] can have before
+/// its port buffers come from the heap
+const MAX_NESTED_PORTS: usize = 8;
+
+/// Run a node inside another node's `process`, over one block
+///
+/// Only the named ports are connected, the node's other inputs read as
+/// silence (so sliders that can be inputs use their own value) and its other
+/// outputs are thrown away. This doesn't allocate unless the node has more than
+/// [`MAX_NESTED_PORTS`] of either kind, so it's fine to call from the audio
+/// path.
+pub fn process_nested(node: &impl SimpleNode, input: (&str, &[f32]), output: (&str, &mut [f32])) {
+    let len = input.1.len();
+
+    let num_inputs = node.inputs().0.read().unwrap().ports.len();
+    let num_outputs = node.outputs().0.read().unwrap().ports.len();
+
+    if num_inputs > MAX_NESTED_PORTS || num_outputs > MAX_NESTED_PORTS {
+        let silence = vec![0.0; len];
+        let mut inputs = vec![&silence[..]; num_inputs];
+        let mut present = vec![false; num_inputs];
+        let mut buffers = vec![vec![0.0; len]; num_outputs];
+        let mut outputs = buffers.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+
+        run_nested(node, input, output, &mut inputs, &mut present, &mut outputs);
+        return;
+    }
+
+    let silence = [0.0; MAX_BUF_SIZE];
+    let mut inputs = [&silence[..len]; MAX_NESTED_PORTS];
+    let mut present = [false; MAX_NESTED_PORTS];
+    let mut buffers = [[0.0; MAX_BUF_SIZE]; MAX_NESTED_PORTS];
+    let mut outputs = buffers.each_mut().map(|b| &mut b[..len]);
+
+    run_nested(
+        node,
+        input,
+        output,
+        &mut inputs[..num_inputs],
+        &mut present[..num_inputs],
+        &mut outputs[..num_outputs],
+    );
+}
+
+/// The body of [`process_nested`], given a buffer for each of the node's ports
+fn run_nested<'a>(
+    node: &impl SimpleNode,
+    input: (&str, &'a [f32]),
+    output: (&str, &mut [f32]),
+    inputs: &mut [&'a [f32]],
+    present: &mut [bool],
+    outputs: &mut [&mut [f32]],
+) {
+    if let Some(idx) = node.inputs().get_idx(input.0) {
+        inputs[idx] = input.1;
+        present[idx] = true;
+    }
+
+    node.process(
+        ProcessInput {
+            storage: node.inputs(),
+            inputs,
+            present,
+        },
+        ProcessOutput {
+            storage: node.outputs(),
+            outputs,
+        },
+    );
+
+    if let Some(idx) = node.outputs().get_idx(output.0) {
+        output.1.copy_from_slice(outputs[idx]);
+    }
+}
+
+/// Run a node over `input` without any links, devices, or UI
+///
+/// `input` is fed to the first input port declared by the node's `new` in
+/// blocks of [`buf_size`] (the final block is zero padded), all other inputs are
+/// left unconnected. The output of the first declared output port is collected
+/// and returned with the same length as `input`, nodes without outputs return
+/// nothing.
+#[allow(unused)]
+pub fn run_block(node: &impl SimpleNode, input: &[f32]) -> Vec<f32> {
+    let num_inputs = node.inputs().get_all().len();
+    let num_outputs = node.outputs().get_all().len();
+
+    let buf_size = buf_size();
+    let mut output = Vec::with_capacity(input.len());
+
+    for chunk in input.chunks(buf_size) {
+        let mut input_buf = vec![0.0; num_inputs * buf_size];
+        let mut present = vec![false; num_inputs];
+
+        if num_inputs > 0 {
+            input_buf[..chunk.len()].copy_from_slice(chunk);
+            present[0] = true;
+        }
+
+        let mut output_buf = vec![0.0; num_outputs * buf_size];
+
+        let input_slices = input_buf.chunks(buf_size).collect::<Vec<_>>();
+        let mut output_slices = output_buf.chunks_mut(buf_size).collect::<Vec<_>>();
+
+        node.process(
+            ProcessInput {
+                storage: node.inputs(),
+                inputs: &input_slices,
+                present: &present,
+            },
+            ProcessOutput {
+                storage: node.outputs(),
+                outputs: &mut output_slices,
+            },
+        );
+
+        if num_outputs > 0 {
+            output.extend_from_slice(&output_buf[..chunk.len()]);
+        }
+    }
+
+    output
+}
+
 static PRESENT_INPUT_POOL: Lazy<Arc<Pool<Vec<bool>>>> = Lazy::new(|| Arc::new(Pool::new()));
 static BUF_POOL: Lazy<Arc<Pool<Vec<f32>>>> = Lazy::new(|| Arc::new(Pool::new()));
 static REF_POOL: Lazy<Arc<Pool<NoClear<Slice<[f32]>>>>> = Lazy::new(|| Arc::new(Pool::new()));
 
-pub const BUF_SIZE: usize = 128;
+/// Samples each node processes at a time, see [`buf_size`]
+static BUF_SIZE: AtomicUsize = AtomicUsize::new(128);
+
+/// Smallest block size that can be picked, any smaller and the time spent
+/// passing blocks between nodes outweighs processing them
+pub const MIN_BUF_SIZE: usize = 32;
+
+/// Largest block size that can be picked, buffers nodes keep on the stack are
+/// this big and only the first [`buf_size`] samples of them are used
+pub const MAX_BUF_SIZE: usize = 1024;
+
+/// Samples each node processes at a time
+///
+/// Smaller blocks lower the latency of the graph, larger ones spend less time
+/// passing blocks around. Device callbacks are rarely a multiple of an odd
+/// size, so this is kept to a power of two.
+pub fn buf_size() -> usize {
+    BUF_SIZE.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Nodes expect every block to be the same size, so this should only be
+/// changed while the graph is stopped
+pub fn set_buf_size(size: usize) {
+    let size = size.clamp(MIN_BUF_SIZE, MAX_BUF_SIZE).next_power_of_two();
+    BUF_SIZE.store(size, std::sync::atomic::Ordering::Relaxed);
+}
 
 fn drop_key<T: Clear + Default>(x: OwnedRefMut<T>) -> usize {
     x.key()
@@ -266,18 +774,20 @@ fn drop_key_<T: Clear + Default>(x: RefMut<T>) -> usize {
 
 impl<T: SimpleNode> Perform for T {
     async fn perform(&self, inputs: NodeInputs<'_, '_, '_>, outputs: NodeOutputs<'_, '_, '_>) {
+        let buf_size = buf_size();
+
         // prep outputs
 
         let mut output_buf = BUF_POOL.clone().create_owned().unwrap();
-        output_buf.resize(outputs.len() * BUF_SIZE, 0.0);
+        output_buf.resize(outputs.len() * buf_size, 0.0);
 
         let mut output_slice_slice = REF_POOL.clone().create_owned().unwrap();
-        let output_slice = output_slice_slice.from_iter_mut(output_buf.chunks_mut(BUF_SIZE));
+        let output_slice = output_slice_slice.from_iter_mut(output_buf.chunks_mut(buf_size));
 
         for (idx, output_port) in outputs.iter_mut().enumerate() {
             tracing::trace!(name = self.title(), id = ?self.id(), "Waiting for {} outputs on port {}", output_port.len(), idx);
             for output_pipe in output_port.iter_mut() {
-                output_pipe.grant(BUF_SIZE).await.unwrap();
+                output_pipe.grant(buf_size).await.unwrap();
             }
         }
 
@@ -285,21 +795,30 @@ impl<T: SimpleNode> Perform for T {
 
         let mut present_inputs = PRESENT_INPUT_POOL.clone().create_owned().unwrap();
         let mut input_buf = BUF_POOL.clone().create_owned().unwrap();
-        input_buf.resize(inputs.len() * BUF_SIZE, 0.0);
+        input_buf.resize(inputs.len() * buf_size, 0.0);
+
+        let mode = if self.sums_inputs() {
+            InputMixing::Sum
+        } else {
+            input_mixing()
+        };
 
         for (idx, (pipes, buf)) in inputs
             .iter_mut()
-            .zip(input_buf.chunks_mut(BUF_SIZE))
+            .zip(input_buf.chunks_mut(buf_size))
             .enumerate()
         {
             tracing::trace!(name = self.title(), id = ?self.id(), "Waiting for {} inputs on port {}", pipes.len(), idx);
 
-            let present = collect_and_average(buf, pipes).await;
+            let present = collect_and_average(buf, pipes, mode).await;
+            if present {
+                self.inputs().record_level(idx, buf);
+            }
             present_inputs.push(present);
         }
 
         let mut input_slice_slice = REF_POOL.create().unwrap();
-        let input_slice = input_slice_slice.from_iter(input_buf.chunks(BUF_SIZE));
+        let input_slice = input_slice_slice.from_iter(input_buf.chunks(buf_size));
 
         // run process
 
@@ -316,11 +835,60 @@ impl<T: SimpleNode> Perform for T {
 
         self.process(pinput, poutput);
 
+        let soloed = self
+            .outputs()
+            .wet_solo()
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let gain_match = self.outputs().gain_match();
+        let matched = gain_match
+            .enabled
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        if soloed || matched {
+            let pinput = ProcessInput {
+                storage: self.inputs(),
+                inputs: input_slice,
+                present: &present_inputs,
+            };
+
+            let mut poutput = ProcessOutput {
+                storage: self.outputs(),
+                outputs: output_slice,
+            };
+
+            // the point of soloing is to hear what the node adds as it is, so
+            // that isn't trimmed
+            if soloed {
+                wet_solo(&pinput, &mut poutput);
+            } else {
+                gain_match.trim(&pinput, &mut poutput);
+            }
+        }
+
+        // links jump when a node restarts, so ease the output back in
+        let fade = start_fade_samples();
+        let pos = self.outputs().advance_fade(buf_size);
+        if pos < fade {
+            for buf in output_slice.iter_mut() {
+                for (i, x) in buf.iter_mut().enumerate() {
+                    *x *= ((pos + i) as f32 / fade as f32).min(1.0);
+                }
+            }
+        }
+
         // copy outputs
 
-        for (output_port, buf) in outputs.iter_mut().zip(output_buf.chunks(BUF_SIZE)) {
+        for (idx, (output_port, buf)) in outputs
+            .iter_mut()
+            .zip(output_buf.chunks(buf_size))
+            .enumerate()
+        {
+            if !output_port.is_empty() {
+                self.outputs().record_level(idx, buf);
+            }
+
             for output_pipe in output_port.iter_mut() {
-                output_pipe.view_mut()[..BUF_SIZE].copy_from_slice(buf);
+                output_pipe.view_mut()[..buf_size].copy_from_slice(buf);
             }
         }
 
@@ -331,7 +899,7 @@ impl<T: SimpleNode> Perform for T {
                 // if the view is less than the buf size, then we didn't actually read from it
                 // but skip it anyway
                 // however this shouldn't actuall happen
-                input_pipe.release(BUF_SIZE.min(input_pipe.view().len()));
+                input_pipe.release(buf_size.min(input_pipe.view().len()));
             }
         }
 
@@ -339,7 +907,7 @@ impl<T: SimpleNode> Perform for T {
 
         for output_port in outputs.iter_mut() {
             for output_pipe in output_port.iter_mut() {
-                output_pipe.release(BUF_SIZE);
+                output_pipe.release(buf_size);
             }
         }
 
@@ -350,3 +918,107 @@ impl<T: SimpleNode> Perform for T {
         assert!(PRESENT_INPUT_POOL.clear(drop_key(present_inputs)));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rivulet::SplittableView;
+
+    use super::*;
+
+    #[test]
+    fn signal_input_pairs_by_name() {
+        let inputs = PortStorage::default();
+        for name in ["in_right", "in_left", "cutoff"] {
+            inputs.add(name.to_owned());
+        }
+
+        assert_eq!(signal_input(&inputs, "out_left"), inputs.get_id("in_left"));
+        assert_eq!(
+            signal_input(&inputs, "out_right"),
+            inputs.get_id("in_right")
+        );
+        assert_eq!(signal_input(&inputs, "cutoff"), inputs.get_id("cutoff"));
+        assert_eq!(signal_input(&inputs, "out"), None);
+    }
+
+    #[test]
+    fn stereo_partner_needs_an_existing_port() {
+        let ports = PortStorage::default();
+        for name in ["in_left", "in_right", "side_l", "dry_r"] {
+            ports.add(name.to_owned());
+        }
+
+        let partner = |name| {
+            let id = ports.stereo_partner(ports.get_id(name).unwrap());
+            id.map(|id| {
+                ports
+                    .get_all()
+                    .into_iter()
+                    .find(|(_, v)| *v == id)
+                    .unwrap()
+                    .0
+            })
+        };
+
+        assert_eq!(partner("in_left").as_deref(), Some("in_right"));
+        assert_eq!(partner("in_right").as_deref(), Some("in_left"));
+        assert_eq!(partner("side_l"), None);
+        assert_eq!(partner("dry_r"), None);
+    }
+
+    /// Puts the input mixing back to the default when dropped, so a failed
+    /// assertion doesn't leave other tests summing
+    struct ResetMixing;
+
+    impl Drop for ResetMixing {
+        fn drop(&mut self) {
+            set_input_mixing(InputMixing::default());
+        }
+    }
+
+    /// Mix a block from each of two links, `a` and `b`, into one input
+    fn mix_two(a: f32, b: f32) -> Vec<f32> {
+        const LEN: usize = 16;
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            // the sinks are kept so the links aren't closed while being read
+            let mut sinks = Vec::new();
+            let mut sources = Vec::new();
+
+            for level in [a, b] {
+                let (mut sink, source) = rivulet::circular_buffer::<f32>(2 * LEN);
+                sink.grant(LEN).await.unwrap();
+                sink.view_mut()[..LEN].fill(level);
+                sink.release(LEN);
+                sinks.push(sink);
+                sources.push(source.into_view());
+            }
+
+            let mut sources = sources.iter_mut().collect::<Vec<_>>();
+            let mut output = vec![0.0; LEN];
+            assert!(collect_and_average(&mut output, &mut sources, input_mixing()).await);
+
+            output
+        })
+    }
+
+    #[test]
+    fn input_mixing_averages_or_sums() {
+        let _reset = ResetMixing;
+        let close = |x: f32, expected: f32| (x - expected).abs() < 1e-3;
+
+        set_input_mixing(InputMixing::Average);
+        assert_eq!(input_mixing(), InputMixing::Average);
+        let averaged = mix_two(0.2, 0.6);
+        assert!(averaged.iter().all(|x| close(*x, 0.4)), "{averaged:?}");
+
+        set_input_mixing(InputMixing::Sum);
+        assert_eq!(input_mixing(), InputMixing::Sum);
+        let summed = mix_two(0.2, 0.6);
+        assert!(summed.iter().all(|x| close(*x, 0.8)), "{summed:?}");
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::atomic::AtomicBool;
+
+use atomig::Atomic;
+use eframe::egui::Ui;
+
+use crate::{ids::NodeId, node::*};
+
+/// The gate counts as held while it's above this
+const GATE_THRESHOLD: f32 = 0.5;
+
+/// How long (in ms) the fire button holds the gate for
+const FIRE_HOLD: f32 = 100.0;
+
+#[derive(atomig::Atom, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// How far a stage lasting `ms` moves each sample
+fn step(ms: f32) -> f32 {
+    let samples = ms * crate::devices::sample_rate() as f32 / 1000.0;
+
+    if samples <= 1.0 {
+        1.0
+    } else {
+        1.0 / samples
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "gate",
+    output = "out",
+    title = "ADSR",
+    cfg_name = "adsr",
+    description = "Envelope between 0 and 1 that rises while the gate is held and falls once it's let go",
+    custom_render = "Adsr::render",
+    reset_state = "Adsr::reset"
+)]
+pub struct Adsr {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// Time to rise from 0 to 1
+    #[dsp(slider(range = "0.0..=5000.0", suffix = " ms"), save, default = "10.0")]
+    attack: Atomic<f32>,
+
+    /// Time to fall from 1 to the sustain level
+    #[dsp(
+        slider(range = "0.0..=5000.0", suffix = " ms"),
+        save,
+        default = "100.0"
+    )]
+    decay: Atomic<f32>,
+
+    /// Level held for as long as the gate is
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.7")]
+    sustain: Atomic<f32>,
+
+    /// Time to fall from 1 to 0 once the gate is let go
+    #[dsp(
+        slider(range = "0.0..=5000.0", suffix = " ms"),
+        save,
+        default = "300.0"
+    )]
+    release: Atomic<f32>,
+
+    #[dsp(default = "Stage::Idle")]
+    stage: Atomic<Stage>,
+
+    level: Atomic<f32>,
+
+    /// The last gate sample of the previous block, so edges on the block
+    /// boundary aren't missed
+    last_gate: Atomic<f32>,
+
+    /// Set from the ui to hold the gate briefly, as if it had been triggered
+    fired: AtomicBool,
+
+    /// Samples the fire button is still holding the gate for
+    fire_held: Atomic<usize>,
+}
+
+impl Adsr {
+    fn reset(&self) {
+        self.stage
+            .store(Stage::Idle, std::sync::atomic::Ordering::Relaxed);
+        self.level.store(0.0, std::sync::atomic::Ordering::Relaxed);
+        self.last_gate
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
+        self.fire_held
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        if ui
+            .button("Fire")
+            .on_hover_text_at_pointer("Hold the gate briefly, for trying it out without a gate")
+            .clicked()
+        {
+            self.fired.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+impl SimpleNode for Adsr {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let attack = step(self.attack.load(std::sync::atomic::Ordering::Relaxed));
+        let decay = step(self.decay.load(std::sync::atomic::Ordering::Relaxed));
+        let sustain = self
+            .sustain
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .clamp(0.0, 1.0);
+        let release = step(self.release.load(std::sync::atomic::Ordering::Relaxed));
+
+        let input = inputs.get("gate").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let mut stage = self.stage.load(std::sync::atomic::Ordering::Relaxed);
+        let mut level = self.level.load(std::sync::atomic::Ordering::Relaxed);
+        let mut last_gate = self.last_gate.load(std::sync::atomic::Ordering::Relaxed);
+        let mut fire_held = self.fire_held.load(std::sync::atomic::Ordering::Relaxed);
+
+        if self.fired.swap(false, std::sync::atomic::Ordering::Relaxed) {
+            let samples_per_ms = crate::devices::sample_rate() as f32 / 1000.0;
+            fire_held = (FIRE_HOLD * samples_per_ms) as usize;
+        }
+
+        for (gate, out) in input.iter().zip(output.iter_mut()) {
+            // the fire button holds the gate as if it was high
+            let gate = if fire_held > 0 {
+                fire_held -= 1;
+                1.0
+            } else {
+                *gate
+            };
+
+            let was_held = last_gate > GATE_THRESHOLD;
+            let held = gate > GATE_THRESHOLD;
+            last_gate = gate;
+
+            if held && !was_held {
+                // retriggering starts from the current level so there's no jump
+                stage = Stage::Attack;
+            } else if !held && was_held {
+                stage = Stage::Release;
+            }
+
+            match stage {
+                Stage::Idle => level = 0.0,
+                Stage::Attack => {
+                    level += attack;
+                    if level >= 1.0 {
+                        level = 1.0;
+                        stage = Stage::Decay;
+                    }
+                }
+                Stage::Decay => {
+                    level -= decay * (1.0 - sustain);
+                    if level <= sustain {
+                        level = sustain;
+                        stage = Stage::Sustain;
+                    }
+                }
+                Stage::Sustain => level = sustain,
+                Stage::Release => {
+                    level -= release;
+                    if level <= 0.0 {
+                        level = 0.0;
+                        stage = Stage::Idle;
+                    }
+                }
+            }
+
+            *out = level;
+        }
+
+        self.stage
+            .store(stage, std::sync::atomic::Ordering::Relaxed);
+        self.level
+            .store(level, std::sync::atomic::Ordering::Relaxed);
+        self.last_gate
+            .store(last_gate, std::sync::atomic::Ordering::Relaxed);
+        self.fire_held
+            .store(fire_held, std::sync::atomic::Ordering::Relaxed);
+    }
+}
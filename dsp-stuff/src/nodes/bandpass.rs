@@ -0,0 +1,98 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use biquad::{Biquad as _, DirectForm1, ToHertz};
+use collect_slice::CollectSlice;
+
+const DEFAULT_FREQUENCY: f32 = 1000.0;
+const DEFAULT_Q: f32 = 0.707;
+
+fn coefficients(frequency: f32, q: f32) -> Option<biquad::Coefficients<f32>> {
+    match biquad::Coefficients::<f32>::from_params(
+        biquad::Type::BandPass,
+        (crate::devices::sample_rate() as f32).hz(),
+        frequency.hz(),
+        q,
+    ) {
+        Ok(c) => Some(c),
+        Err(e) => {
+            tracing::warn!("Couldn't compute band pass coefficients: {:?}", e);
+            None
+        }
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Band Pass",
+    cfg_name = "bandpass",
+    description = "Two pole filter passing only a band of frequencies around a centre frequency",
+    after_settings_change = "BandPass::regenerate",
+    reset_state = "BandPass::reset"
+)]
+pub struct BandPass {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "20.0..=20000.0", logarithmic, suffix = " hz"),
+        save,
+        default = "DEFAULT_FREQUENCY"
+    )]
+    frequency: Atomic<f32>,
+
+    /// Higher is a narrower band
+    #[dsp(
+        slider(range = "0.1..=20.0", logarithmic),
+        label = "Q",
+        save,
+        default = "DEFAULT_Q"
+    )]
+    q: Atomic<f32>,
+
+    #[dsp(default = "BandPass::initial_filter()")]
+    filter: Arc<Mutex<biquad::DirectForm1<f32>>>,
+}
+
+impl BandPass {
+    fn initial_filter() -> Arc<Mutex<biquad::DirectForm1<f32>>> {
+        // the defaults are always valid
+        let coeffs = coefficients(DEFAULT_FREQUENCY, DEFAULT_Q).unwrap();
+
+        Arc::new(Mutex::new(DirectForm1::<f32>::new(coeffs)))
+    }
+
+    fn reset(&self) {
+        self.filter.lock().unwrap().reset_state();
+    }
+
+    fn regenerate(&self) {
+        let Some(coeffs) = coefficients(
+            self.frequency.load(atomig::Ordering::Relaxed),
+            self.q.load(atomig::Ordering::Relaxed),
+        ) else {
+            return;
+        };
+
+        self.filter.lock().unwrap().update_coefficients(coeffs);
+    }
+}
+
+impl SimpleNode for BandPass {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let mut filter = self.filter.lock().unwrap();
+
+        input.iter().map(|x| filter.run(*x)).collect_slice(output);
+    }
+}
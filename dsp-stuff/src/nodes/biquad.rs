@@ -2,8 +2,33 @@ use std::sync::{Arc, Mutex};
 
 use crate::{ids::NodeId, node::*};
 use atomig::Atomic;
-use biquad::{Biquad as _, DirectForm1};
+use biquad::{Biquad as _, DirectForm1, ToHertz};
 use collect_slice::CollectSlice;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+)]
+#[repr(u8)]
+enum FilterType {
+    /// Use the coefficients as given
+    Manual,
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
 
 #[derive(dsp_stuff_derive::DspNode)]
 #[dsp(
@@ -12,7 +37,8 @@ use collect_slice::CollectSlice;
     title = "Biquad",
     cfg_name = "biquad",
     description = "Generic biquad filter",
-    after_settings_change = "BiQuad::regenerate_filter"
+    after_settings_change = "BiQuad::regenerate_filter",
+    reset_state = "BiQuad::reset"
 )]
 pub struct BiQuad {
     #[dsp(id)]
@@ -22,6 +48,32 @@ pub struct BiQuad {
     #[dsp(outputs)]
     outputs: PortStorage,
 
+    #[dsp(select, label = "Type", save, default = "FilterType::Manual")]
+    filter_type: Atomic<FilterType>,
+
+    #[dsp(
+        slider(range = "20.0..=20000.0", logarithmic, suffix = " hz"),
+        save,
+        default = "1000.0"
+    )]
+    frequency: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.1..=20.0", logarithmic),
+        label = "Q",
+        save,
+        default = "0.707"
+    )]
+    q: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "-24.0..=24.0", suffix = " db"),
+        label = "Gain",
+        save,
+        default = "0.0"
+    )]
+    gain_db: Atomic<f32>,
+
     #[dsp(slider(range = "-10.0..=10.0"), default = "1.0", save)]
     a0: Atomic<f32>,
 
@@ -42,6 +94,10 @@ pub struct BiQuad {
 
     #[dsp(default = "BiQuad::initial_filter()")]
     filter: Arc<Mutex<biquad::DirectForm1<f32>>>,
+
+    /// The coefficients last generated from the filter type, if they've since
+    /// been edited the filter switches to manual
+    generated: Mutex<Option<[f32; 6]>>,
 }
 
 impl BiQuad {
@@ -59,7 +115,79 @@ impl BiQuad {
         Arc::new(Mutex::new(filter))
     }
 
+    fn reset(&self) {
+        self.filter.lock().unwrap().reset_state();
+    }
+
+    fn coefficients(&self) -> [f32; 6] {
+        [
+            self.a0.load(atomig::Ordering::Relaxed),
+            self.a1.load(atomig::Ordering::Relaxed),
+            self.a2.load(atomig::Ordering::Relaxed),
+            self.b0.load(atomig::Ordering::Relaxed),
+            self.b1.load(atomig::Ordering::Relaxed),
+            self.b2.load(atomig::Ordering::Relaxed),
+        ]
+    }
+
+    /// Compute the coefficients for the selected filter type and store them in
+    /// the coefficient sliders
+    fn generate_coefficients(&self, filter_type: FilterType) {
+        let gain = self.gain_db.load(atomig::Ordering::Relaxed);
+
+        let ty = match filter_type {
+            FilterType::Manual => return,
+            FilterType::LowPass => biquad::Type::LowPass,
+            FilterType::HighPass => biquad::Type::HighPass,
+            FilterType::BandPass => biquad::Type::BandPass,
+            FilterType::Notch => biquad::Type::Notch,
+            FilterType::Peaking => biquad::Type::PeakingEQ(gain),
+            FilterType::LowShelf => biquad::Type::LowShelf(gain),
+            FilterType::HighShelf => biquad::Type::HighShelf(gain),
+        };
+
+        let coeffs = match biquad::Coefficients::<f32>::from_params(
+            ty,
+            (crate::devices::sample_rate() as f32).hz(),
+            self.frequency.load(atomig::Ordering::Relaxed).hz(),
+            self.q.load(atomig::Ordering::Relaxed),
+        ) {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!("Couldn't compute biquad coefficients: {:?}", e);
+                return;
+            }
+        };
+
+        self.a0.store(1.0, atomig::Ordering::Relaxed);
+        self.a1.store(coeffs.a1, atomig::Ordering::Relaxed);
+        self.a2.store(coeffs.a2, atomig::Ordering::Relaxed);
+        self.b0.store(coeffs.b0, atomig::Ordering::Relaxed);
+        self.b1.store(coeffs.b1, atomig::Ordering::Relaxed);
+        self.b2.store(coeffs.b2, atomig::Ordering::Relaxed);
+
+        *self.generated.lock().unwrap() = Some(self.coefficients());
+    }
+
     fn regenerate_filter(&self) {
+        let filter_type = self.filter_type.load(atomig::Ordering::Relaxed);
+
+        if filter_type == FilterType::Manual {
+            *self.generated.lock().unwrap() = None;
+        } else {
+            let mut generated = self.generated.lock().unwrap();
+
+            if generated.is_some_and(|g| g != self.coefficients()) {
+                // a coefficient was edited by hand
+                *generated = None;
+                self.filter_type
+                    .store(FilterType::Manual, atomig::Ordering::Relaxed);
+            } else {
+                drop(generated);
+                self.generate_coefficients(filter_type);
+            }
+        }
+
         let a0 = self.a0.load(atomig::Ordering::Relaxed);
 
         let coeffs = biquad::Coefficients {
@@ -87,3 +215,35 @@ impl SimpleNode for BiQuad {
         input.iter().map(|x| filter.run(*x)).collect_slice(output);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_restores_declared_defaults() {
+        let node = BiQuad::new(NodeId::generate());
+
+        assert!(node.set_choice("filter_type", "LowPass"));
+        assert!(node.set_param("frequency", 200.0));
+        assert!(node.set_param("q", 4.0));
+        assert!(node.set_param("gain_db", -6.0));
+        assert!(node.set_param("b2", 0.5));
+
+        node.reset_defaults();
+
+        let declared: [(&str, f32); 9] = [
+            ("frequency", 1000.0),
+            ("q", 0.707),
+            ("gain_db", 0.0),
+            ("a0", 1.0),
+            ("a1", -0.24),
+            ("a2", 0.0),
+            ("b0", 0.758),
+            ("b1", 0.0),
+            ("b2", 0.0),
+        ];
+        assert_eq!(node.list_params(), declared);
+        assert_eq!(node.list_choices(), [("filter_type", "Manual")]);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Bitcrusher",
+    cfg_name = "bitcrusher",
+    description = "Lower the bit depth and sample rate of a signal for a lo-fi, aliased sound",
+    reset_state = "Bitcrusher::reset"
+)]
+pub struct Bitcrusher {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(slider(range = "1..=16"), save, default = "8")]
+    bits: Atomic<u32>,
+
+    /// Each sample taken is held for this many samples
+    #[dsp(slider(range = "1..=64"), save, default = "1")]
+    downsample: Atomic<u32>,
+
+    /// The sample being held and how many more samples it's held for, these
+    /// carry over between blocks
+    held: Atomic<f32>,
+    hold_left: Atomic<u32>,
+}
+
+impl Bitcrusher {
+    fn reset(&self) {
+        self.held.store(0.0, std::sync::atomic::Ordering::Relaxed);
+        self.hold_left
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl SimpleNode for Bitcrusher {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let bits = self
+            .bits
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .clamp(1, 16);
+        let downsample = self
+            .downsample
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .max(1);
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        // the levels are spread over -1..=1, with one of them at silence
+        let step = 1.0 / 2.0f32.powi(bits as i32 - 1);
+
+        let mut held = self.held.load(std::sync::atomic::Ordering::Relaxed);
+        let mut hold_left = self.hold_left.load(std::sync::atomic::Ordering::Relaxed);
+
+        for (x, out) in input.iter().zip(output.iter_mut()) {
+            if hold_left == 0 {
+                held = ((x / step).round() * step).clamp(-1.0, 1.0);
+                hold_left = downsample;
+            }
+
+            hold_left -= 1;
+            *out = held;
+        }
+
+        self.held.store(held, std::sync::atomic::Ordering::Relaxed);
+        self.hold_left
+            .store(hold_left, std::sync::atomic::Ordering::Relaxed);
+    }
+}
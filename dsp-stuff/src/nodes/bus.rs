@@ -0,0 +1,133 @@
+use atomig::Atomic;
+use collect_slice::CollectSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::{ids::NodeId, node::*};
+
+/// Name of the port buses are linked through, the output of a send and the
+/// input of a return
+pub const BUS_PORT: &str = "bus";
+
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+)]
+#[repr(u8)]
+enum Bus {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    output = "bus",
+    title = "Send",
+    cfg_name = "bus_send",
+    description = "Pass a signal through, tapping it off into a bus at the send level",
+    bus = "BusSend::bus_end"
+)]
+pub struct BusSend {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(select, save, default = "Bus::A")]
+    bus: Atomic<Bus>,
+
+    #[dsp(slider(range = "0.0..=2.0", as_input), save, default = "0.5")]
+    level: Atomic<f32>,
+}
+
+impl BusSend {
+    fn bus_end(&self) -> Option<BusEnd> {
+        Some(BusEnd::Send(
+            self.bus.load(std::sync::atomic::Ordering::Relaxed) as u8,
+        ))
+    }
+}
+
+impl SimpleNode for BusSend {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut level = [0.0; MAX_BUF_SIZE];
+        self.level_input(&inputs, &mut level);
+        let level = &level[..buf_size()];
+        let input = inputs.get("in").unwrap();
+
+        outputs.get("out").unwrap().copy_from_slice(input);
+
+        input
+            .iter()
+            .zip(level)
+            .map(|(x, level)| x * level)
+            .collect_slice(outputs.get(BUS_PORT).unwrap());
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "bus",
+    output = "out",
+    title = "Return",
+    cfg_name = "bus_return",
+    description = "Output the sum of every send on a bus",
+    bus = "BusReturn::bus_end",
+    sum_inputs
+)]
+pub struct BusReturn {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(select, save, default = "Bus::A")]
+    bus: Atomic<Bus>,
+
+    #[dsp(slider(range = "0.0..=2.0", as_input), save, default = "1.0")]
+    level: Atomic<f32>,
+}
+
+impl BusReturn {
+    fn bus_end(&self) -> Option<BusEnd> {
+        Some(BusEnd::Return(
+            self.bus.load(std::sync::atomic::Ordering::Relaxed) as u8,
+        ))
+    }
+}
+
+impl SimpleNode for BusReturn {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut level = [0.0; MAX_BUF_SIZE];
+        self.level_input(&inputs, &mut level);
+        let level = &level[..buf_size()];
+        let input = inputs.get(BUS_PORT).unwrap();
+        let output = outputs.get("out").unwrap();
+
+        input
+            .iter()
+            .zip(level)
+            .map(|(x, level)| x * level)
+            .collect_slice(output);
+    }
+}
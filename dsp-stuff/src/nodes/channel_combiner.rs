@@ -0,0 +1,45 @@
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "left",
+    input = "right",
+    output = "out",
+    title = "Channel combiner",
+    cfg_name = "channel_combiner",
+    description = "Sum a stereo pair down to mono, for nodes that only take one signal"
+)]
+pub struct ChannelCombiner {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// -1 takes only the left channel, 1 only the right
+    #[dsp(slider(range = "-1.0..=1.0", as_input), save, default = "0.0")]
+    balance: Atomic<f32>,
+}
+
+impl SimpleNode for ChannelCombiner {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut balance = [0.0; MAX_BUF_SIZE];
+        self.balance_input(&inputs, &mut balance);
+        let balance = &balance[..buf_size()];
+        let left = inputs.get("left").unwrap();
+        let right = inputs.get("right").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        for (((out, l), r), balance) in output.iter_mut().zip(left).zip(right).zip(balance) {
+            // equal power, so moving the balance doesn't dip in the middle. A
+            // centred pair of unrelated signals keeps its loudness, but an
+            // identical pair (mono in both) still comes out 3dB above either
+            // side, rather than the 6dB of adding them straight
+            let angle = (balance + 1.0) * std::f32::consts::FRAC_PI_4;
+            *out = l * angle.cos() + r * angle.sin();
+        }
+    }
+}
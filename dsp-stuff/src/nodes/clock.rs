@@ -0,0 +1,79 @@
+use std::sync::atomic::AtomicU64;
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+
+/// Bumped to reset every clock, clocks notice on their next frame
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Restart every clock in the patch from zero
+pub fn reset_all() {
+    EPOCH.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    output = "ramp",
+    output = "time",
+    title = "Clock",
+    cfg_name = "clock",
+    description = "A shared time base, outputs a 0..1 ramp and the seconds since the last resync",
+    reset_state = "Clock::reset"
+)]
+pub struct Clock {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "0.01..=100.0", logarithmic, suffix = " hz"),
+        save,
+        default = "1.0"
+    )]
+    frequency: Atomic<f32>,
+
+    /// Samples since the last reset
+    samples: Atomic<u64>,
+
+    epoch: Atomic<u64>,
+}
+
+impl Clock {
+    fn reset(&self) {
+        self.samples.store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl SimpleNode for Clock {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, _inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let epoch = EPOCH.load(std::sync::atomic::Ordering::Relaxed);
+        if self.epoch.swap(epoch, std::sync::atomic::Ordering::Relaxed) != epoch {
+            self.reset();
+        }
+
+        let frequency = self.frequency.load(std::sync::atomic::Ordering::Relaxed) as f64;
+        let start = self
+            .samples
+            .fetch_add(buf_size() as u64, std::sync::atomic::Ordering::Relaxed);
+
+        // both outputs are derived from the sample count, so clocks at the same
+        // frequency stay in phase with each other. time is kept in f64 and only
+        // narrowed when written out, so the ramp doesn't drift over long sessions
+        let sample_rate = crate::devices::sample_rate() as f64;
+        let time_at = |i: usize| (start + i as u64) as f64 / sample_rate;
+
+        let ramp = outputs.get("ramp").unwrap();
+        for (i, v) in ramp.iter_mut().enumerate() {
+            *v = (time_at(i) * frequency).fract() as f32;
+        }
+
+        let time = outputs.get("time").unwrap();
+        for (i, v) in time.iter_mut().enumerate() {
+            *v = time_at(i) as f32;
+        }
+    }
+}
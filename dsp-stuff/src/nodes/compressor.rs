@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use collect_slice::CollectSlice;
+use eframe::egui;
+use egui::Ui;
+
+/// Gain reduction that fills the meter, in dB
+const METER_RANGE: f32 = 24.0;
+
+/// Coefficient for a one pole smoother reaching ~63% of the way in `ms`
+fn smoothing(ms: f32) -> f32 {
+    if ms <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (ms * crate::devices::sample_rate() as f32 / 1000.0)).exp()
+    }
+}
+
+fn to_db(level: f32) -> f32 {
+    20.0 * level.max(1e-6).log10()
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    output = "gain",
+    title = "Compressor",
+    cfg_name = "compressor",
+    description = "Turn the signal down by the ratio while it's over the threshold, the gain reduction is output for driving other nodes",
+    custom_render = "Compressor::render",
+    reset_state = "Compressor::reset"
+)]
+pub struct Compressor {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "-60.0..=0.0", suffix = " dB"),
+        label = "Threshold",
+        save,
+        default = "-20.0"
+    )]
+    threshold: Atomic<f32>,
+
+    #[dsp(slider(range = "1.0..=20.0", logarithmic), save, default = "4.0")]
+    ratio: Atomic<f32>,
+
+    #[dsp(slider(range = "0.0..=500.0", suffix = " ms"), save, default = "10.0")]
+    attack: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.0..=1000.0", suffix = " ms"),
+        save,
+        default = "100.0"
+    )]
+    release: Atomic<f32>,
+
+    /// Gain added after compressing, to make up for the level taken off
+    #[dsp(slider(range = "0.0..=24.0", suffix = " dB"), save, default = "0.0")]
+    makeup: Atomic<f32>,
+
+    /// The detector's level, following the peaks of the input
+    envelope: Atomic<f32>,
+
+    /// Largest gain reduction (in dB) over the last block, for the meter
+    reduction: Atomic<f32>,
+}
+
+impl Compressor {
+    fn reset(&self) {
+        self.envelope
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
+        self.reduction
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        ui.ctx().request_repaint_after(Duration::from_millis(50));
+
+        let reduction = self.reduction.load(std::sync::atomic::Ordering::Relaxed);
+
+        ui.add(
+            egui::ProgressBar::new(reduction / METER_RANGE)
+                .desired_width(150.0)
+                .text(format!("-{reduction:.1} dB")),
+        )
+        .on_hover_text_at_pointer("Gain reduction");
+    }
+}
+
+impl SimpleNode for Compressor {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let threshold = self.threshold.load(std::sync::atomic::Ordering::Relaxed);
+        let ratio = self
+            .ratio
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .max(1.0);
+        let attack = smoothing(self.attack.load(std::sync::atomic::Ordering::Relaxed));
+        let release = smoothing(self.release.load(std::sync::atomic::Ordering::Relaxed));
+        let makeup = self.makeup.load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+
+        let mut envelope = self.envelope.load(std::sync::atomic::Ordering::Relaxed);
+        let mut max_reduction = 0.0f32;
+        let mut gain = [0.0; MAX_BUF_SIZE];
+        let gain = &mut gain[..input.len()];
+
+        for (g, x) in gain.iter_mut().zip(input) {
+            let level = x.abs();
+            let coef = if level > envelope { attack } else { release };
+            envelope = level + (envelope - level) * coef;
+
+            let over = (to_db(envelope) - threshold).max(0.0);
+            let reduction = over - over / ratio;
+            max_reduction = max_reduction.max(reduction);
+
+            *g = 10.0f32.powf(-reduction / 20.0);
+        }
+
+        let makeup = 10.0f32.powf(makeup / 20.0);
+        input
+            .iter()
+            .zip(gain.iter())
+            .map(|(x, g)| x * g * makeup)
+            .collect_slice(outputs.get("out").unwrap());
+
+        // nothing has to be connected to this, it's there for metering and
+        // ducking other signals, so it leaves out the makeup gain
+        outputs.get("gain").unwrap().copy_from_slice(gain);
+
+        self.envelope
+            .store(envelope, std::sync::atomic::Ordering::Relaxed);
+        self.reduction
+            .store(max_reduction, std::sync::atomic::Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,132 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use atomig::Atomic;
+use eframe::egui;
+use egui::{pos2, vec2, Color32, FontFamily, Rect, RichText, Rounding, Ui};
+
+use crate::{ids::NodeId, node::*};
+
+/// Seconds of signal the correlation is measured over
+const WINDOW: f32 = 0.1;
+
+/// Running sums over the last [`WINDOW`] seconds
+#[derive(Default)]
+struct Window {
+    samples: VecDeque<(f32, f32)>,
+    lr: f64,
+    ll: f64,
+    rr: f64,
+}
+
+impl Window {
+    fn push(&mut self, l: f32, r: f32, len: usize) {
+        while self.samples.len() >= len {
+            let (ol, or) = self.samples.pop_front().unwrap();
+            self.lr -= (ol * or) as f64;
+            self.ll -= (ol * ol) as f64;
+            self.rr -= (or * or) as f64;
+        }
+
+        self.samples.push_back((l, r));
+        self.lr += (l * r) as f64;
+        self.ll += (l * l) as f64;
+        self.rr += (r * r) as f64;
+    }
+
+    fn correlation(&self) -> f32 {
+        let power = (self.ll * self.rr).max(0.0).sqrt();
+
+        // silence (on either side) has no meaningful correlation
+        if power < 1e-9 {
+            0.0
+        } else {
+            (self.lr / power).clamp(-1.0, 1.0) as f32
+        }
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "left",
+    input = "right",
+    title = "Correlation",
+    cfg_name = "correlation",
+    description = "Display the phase correlation between two signals",
+    custom_render = "Correlation::render",
+    reset_state = "Correlation::reset"
+)]
+pub struct Correlation {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    window: Mutex<Window>,
+
+    correlation: Atomic<f32>,
+}
+
+impl Correlation {
+    fn reset(&self) {
+        *self.window.lock().unwrap() = Window::default();
+        self.correlation
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        ui.ctx().request_repaint();
+
+        let correlation = self.correlation.load(std::sync::atomic::Ordering::Relaxed);
+
+        let (_id, rect) = ui.allocate_space(vec2(120.0, 10.0));
+
+        let colour = if correlation < 0.0 {
+            Color32::from_rgb(0xf9, 0x26, 0x72)
+        } else if correlation < 0.5 {
+            Color32::from_rgb(0xe6, 0xdb, 0x74)
+        } else {
+            Color32::from_rgb(0xa6, 0xe2, 0x2e)
+        };
+
+        let mid = rect.center().x;
+        let end = mid + correlation * rect.width() / 2.0;
+
+        let painter = ui.painter();
+        painter.rect_filled(rect, Rounding::ZERO, Color32::from_gray(32));
+        painter.rect_filled(
+            Rect::from_x_y_ranges(mid.min(end)..=mid.max(end), rect.y_range()),
+            Rounding::ZERO,
+            colour,
+        );
+        painter.line_segment(
+            [pos2(mid, rect.top()), pos2(mid, rect.bottom())],
+            (1.0, Color32::from_gray(128)),
+        );
+
+        ui.label(
+            RichText::new(format!("{correlation:>+1.2}"))
+                .family(FontFamily::Monospace)
+                .strong(),
+        );
+    }
+}
+
+impl SimpleNode for Correlation {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, _outputs: ProcessOutput) {
+        let left = inputs.get("left").unwrap();
+        let right = inputs.get("right").unwrap();
+
+        let len = (WINDOW * crate::devices::sample_rate() as f32) as usize;
+        let mut window = self.window.lock().unwrap();
+
+        for (l, r) in left.iter().zip(right) {
+            window.push(*l, *r, len);
+        }
+
+        self.correlation
+            .store(window.correlation(), std::sync::atomic::Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{ids::NodeId, node::*, util::Interpolation};
+use atomig::Atomic;
+use rivulet::{
+    circular_buffer::{Sink, Source},
+    splittable, SplittableView, View, ViewMut,
+};
+
+use super::reverb::{delay_line, delay_line_len, read_delayed};
+
+/// Feedback is kept under this however it's set, so the repeats always die
+/// away
+const MAX_FEEDBACK: f32 = 0.99;
+
+const DEFAULT_TIME: f32 = 500.0;
+
+/// Delay in samples for a delay of `ms`, not necessarily a whole number
+fn delay_samples(ms: f32) -> f32 {
+    ms * crate::devices::sample_rate() as f32 / 1000.0
+}
+
+fn make_buffer() -> Arc<Mutex<(splittable::View<Source<f32>>, Sink<f32>)>> {
+    Arc::new(Mutex::new(delay_line(delay_line_len(delay_samples(
+        DEFAULT_TIME,
+    )))))
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Delay",
+    cfg_name = "delay",
+    description = "Repeat the signal after a delay, feeding the repeats back into the delay",
+    after_settings_change = "Delay::refresh_time",
+    reset_state = "Delay::reset"
+)]
+pub struct Delay {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "0.0..=2000.0", suffix = " ms"),
+        save,
+        default = "DEFAULT_TIME"
+    )]
+    time: Atomic<f32>,
+
+    /// How much of each repeat goes back into the delay
+    #[dsp(slider(range = "0.0..=0.99"), save, default = "0.4")]
+    feedback: Atomic<f32>,
+
+    /// Fraction of delayed signal in the output
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    mix: Atomic<f32>,
+
+    #[dsp(select, save, default = "Interpolation::Linear")]
+    interpolation: Atomic<Interpolation>,
+
+    /// Number of samples held in the delay buffer
+    #[dsp(default = "delay_line_len(delay_samples(DEFAULT_TIME))")]
+    delay_len: Atomic<usize>,
+
+    #[dsp(default = "make_buffer()")]
+    buffer: Arc<Mutex<(splittable::View<Source<f32>>, Sink<f32>)>>,
+}
+
+impl Delay {
+    fn refresh_time(&self) {
+        let num_samples = delay_line_len(delay_samples(
+            self.time.load(std::sync::atomic::Ordering::Relaxed),
+        ));
+
+        // the other settings changing shouldn't cut off the repeats
+        if num_samples != self.delay_len.load(std::sync::atomic::Ordering::Relaxed) {
+            self.rebuild(num_samples);
+        }
+    }
+
+    fn rebuild(&self, num_samples: usize) {
+        *self.buffer.lock().unwrap() = delay_line(num_samples);
+        self.delay_len
+            .store(num_samples, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        // recreating the buffer also zeroes it
+        self.rebuild(delay_line_len(delay_samples(
+            self.time.load(std::sync::atomic::Ordering::Relaxed),
+        )));
+    }
+}
+
+impl SimpleNode for Delay {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let feedback = self
+            .feedback
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .clamp(0.0, MAX_FEEDBACK);
+        let mix = self.mix.load(std::sync::atomic::Ordering::Relaxed);
+        let delay = delay_samples(self.time.load(std::sync::atomic::Ordering::Relaxed));
+        let interpolation = self
+            .interpolation
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        // what goes back into the delay, the input plus the fed back repeats
+        let mut written = [0.0; MAX_BUF_SIZE];
+        let written = &mut written[..input.len()];
+
+        let mut guard = self.buffer.lock().unwrap();
+        let (source, sink) = &mut *guard;
+
+        // the buffer holds exactly one delay line's worth of samples, so take
+        // all of it to be able to read at any delay up to the full length
+        let delay_len = self.delay_len.load(std::sync::atomic::Ordering::Relaxed);
+
+        if delay_len >= input.len() && source.try_grant(delay_len).unwrap_or(false) {
+            let history = &source.view()[..delay_len];
+
+            for (i, (out, x)) in output.iter_mut().zip(input).enumerate() {
+                let d = read_delayed(history, written, i, delay, interpolation);
+
+                *out = x * (1.0 - mix) + d * mix;
+                written[i] = x + d * feedback;
+            }
+
+            source.release(input.len());
+        } else {
+            tracing::trace!("Delay buffer is empty");
+
+            for ((out, x), w) in output.iter_mut().zip(input).zip(written.iter_mut()) {
+                *out = x * (1.0 - mix);
+                *w = *x;
+            }
+        }
+
+        if sink.try_grant(input.len()).unwrap_or(false) {
+            sink.view_mut()[..input.len()].copy_from_slice(written);
+            sink.release(input.len());
+        } else {
+            tracing::trace!("Not copying frame into delay buffer");
+        }
+    }
+}
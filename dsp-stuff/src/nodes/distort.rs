@@ -149,7 +149,8 @@ fn fuzz(input: &[f32], output: &mut [f32], level: &[f32]) {
         .map(|x| x.abs())
         .max_by(f32::total_cmp)
         .unwrap();
-    let mut z = [0.0; BUF_SIZE];
+    let mut z = [0.0; MAX_BUF_SIZE];
+    let z = &mut z[..input.len()];
 
     input
         .iter()
@@ -158,13 +159,14 @@ fn fuzz(input: &[f32], output: &mut [f32], level: &[f32]) {
             let q = clip(x * level) / mx;
             (1.0 - q.copysign(-1.0).exp()).copysign(-1.0)
         })
-        .collect_slice(&mut z);
+        .collect_slice(z);
 
     let mz = z.iter().map(|x| x.abs()).max_by(f32::total_cmp).unwrap();
 
-    let mut y = [0.0; BUF_SIZE];
+    let mut y = [0.0; MAX_BUF_SIZE];
+    let y = &mut y[..input.len()];
 
-    z.iter().map(|x| clip(x * mx) / mz).collect_slice(&mut y);
+    z.iter().map(|x| clip(x * mx) / mz).collect_slice(y);
 
     let my = y.iter().map(|x| x.abs()).max_by(f32::total_cmp).unwrap();
 
@@ -174,8 +176,9 @@ fn fuzz(input: &[f32], output: &mut [f32], level: &[f32]) {
 impl SimpleNode for Distort {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
-        let mut level = [0.0; BUF_SIZE];
+        let mut level = [0.0; MAX_BUF_SIZE];
         self.level_input(&inputs, &mut level);
+        let level = &level[..buf_size()];
         let input = inputs.get("in").unwrap();
         let output = outputs.get("out").unwrap();
 
@@ -12,7 +12,8 @@ use dasp_peak::FullWave;
     output = "out",
     title = "Envelope",
     cfg_name = "envelope",
-    description = "Envelope detection"
+    description = "Envelope detection",
+    reset_state = "Envelope::reset"
 )]
 pub struct Envelope {
     #[dsp(id)]
@@ -31,6 +32,12 @@ pub struct Envelope {
     release: Atomic<f32>,
 }
 
+impl Envelope {
+    fn reset(&self) {
+        *self.detector.lock().unwrap() = Detector::peak(0.0, 0.0);
+    }
+}
+
 impl SimpleNode for Envelope {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
@@ -0,0 +1,150 @@
+use std::sync::{Mutex, MutexGuard};
+
+use atomig::Atomic;
+use eframe::egui;
+use egui::{RichText, Ui};
+
+use crate::{ids::NodeId, node::*};
+
+/// The expression last parsed, and the source it was parsed from
+struct Compiled {
+    source: String,
+    expr: Result<meval::Expr, String>,
+}
+
+impl Default for Compiled {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            expr: Err(String::new()),
+        }
+    }
+}
+
+/// The variables an expression can use, falling back to the builtin functions
+/// and constants
+fn context<'a>(
+    x: f32,
+    t: f64,
+    a: f32,
+    b: f32,
+    builtins: &'a meval::Context<'a>,
+) -> impl meval::ContextProvider + 'a {
+    (
+        ("x", x as f64),
+        (("t", t), (("a", a as f64), (("b", b as f64), builtins))),
+    )
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Expression",
+    cfg_name = "expression",
+    description = "Apply a formula to each sample, x is the input and t the time in seconds",
+    custom_render = "Expression::render",
+    reset_state = "Expression::reset"
+)]
+pub struct Expression {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        text,
+        label = "f(x)",
+        tooltip = "Variables: x (input), t (seconds), a and b. Functions include sin, cos, tanh, \
+                   exp, ln, sqrt, abs, min and max",
+        save,
+        default = "Mutex::new(\"x\".to_owned())"
+    )]
+    expression: Mutex<String>,
+
+    #[dsp(slider(range = "-1.0..=1.0", as_input), save)]
+    a: Atomic<f32>,
+
+    #[dsp(slider(range = "-1.0..=1.0", as_input), save)]
+    b: Atomic<f32>,
+
+    compiled: Mutex<Compiled>,
+
+    /// Samples since the last reset, for `t`
+    samples: Atomic<u64>,
+}
+
+impl Expression {
+    fn reset(&self) {
+        self.samples.store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// The parsed expression, reparsing it if it has been edited
+    fn compiled(&self) -> MutexGuard<'_, Compiled> {
+        let source = self.expression.lock().unwrap();
+        let mut compiled = self.compiled.lock().unwrap();
+
+        if compiled.source != *source {
+            let builtins = meval::Context::new();
+
+            // unknown variables and functions only show up when evaluating
+            let expr = source.parse::<meval::Expr>().and_then(|expr| {
+                expr.eval_with_context(context(0.0, 0.0, 0.0, 0.0, &builtins))?;
+                Ok(expr)
+            });
+
+            compiled.expr = expr.map_err(|e| e.to_string());
+            compiled.source = source.clone();
+        }
+
+        compiled
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        if let Err(e) = &self.compiled().expr {
+            ui.label(RichText::new(e).color(ui.visuals().error_fg_color));
+        }
+    }
+}
+
+impl SimpleNode for Expression {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut a = [0.0; MAX_BUF_SIZE];
+        self.a_input(&inputs, &mut a);
+        let a = &a[..buf_size()];
+        let mut b = [0.0; MAX_BUF_SIZE];
+        self.b_input(&inputs, &mut b);
+        let b = &b[..buf_size()];
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let start = self
+            .samples
+            .fetch_add(input.len() as u64, std::sync::atomic::Ordering::Relaxed);
+
+        let compiled = self.compiled();
+
+        // pass the signal through untouched until the expression is fixed
+        let Ok(expr) = &compiled.expr else {
+            output.copy_from_slice(input);
+            return;
+        };
+
+        let builtins = meval::Context::new();
+        let sample_rate = crate::devices::sample_rate() as f64;
+
+        for (i, (out, x)) in output.iter_mut().zip(input).enumerate() {
+            let t = (start + i as u64) as f64 / sample_rate;
+            let v = expr
+                .eval_with_context(context(*x, t, a[i], b[i], &builtins))
+                .unwrap_or(0.0);
+
+            // don't let a division by zero or similar poison everything downstream
+            *out = if v.is_finite() { v as f32 } else { 0.0 };
+        }
+    }
+}
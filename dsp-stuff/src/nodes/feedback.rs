@@ -0,0 +1,82 @@
+use std::sync::Mutex;
+
+use atomig::Atomic;
+use rivulet::{View, ViewMut};
+
+use crate::{ids::NodeId, node::*};
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Feedback",
+    cfg_name = "feedback",
+    description = "Delay a signal by one block so it can be fed back into an earlier node",
+    breaks_cycles,
+    reset_state = "Feedback::reset"
+)]
+pub struct Feedback {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// Keep this below 1 or the loop will keep getting louder
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    amount: Atomic<f32>,
+
+    /// The last block received, sent out before the next one is waited on so
+    /// there's something for the rest of the loop to run on
+    #[dsp(default = "Mutex::new(Vec::new())")]
+    held: Mutex<Vec<f32>>,
+}
+
+impl Feedback {
+    fn reset(&self) {
+        self.held.lock().unwrap().clear();
+    }
+}
+
+impl Perform for Feedback {
+    async fn perform(&self, inputs: NodeInputs<'_, '_, '_>, outputs: NodeOutputs<'_, '_, '_>) {
+        let buf_size = buf_size();
+
+        let mut held = [0.0; MAX_BUF_SIZE];
+        let held = &mut held[..buf_size];
+        {
+            let last = self.held.lock().unwrap();
+            // nothing's been received yet, or the block size changed since
+            if last.len() == buf_size {
+                held.copy_from_slice(&last);
+            }
+        }
+
+        for output_port in outputs.iter_mut() {
+            for output_pipe in output_port.iter_mut() {
+                output_pipe.grant(buf_size).await.unwrap();
+                output_pipe.view_mut()[..buf_size].copy_from_slice(held);
+                output_pipe.release(buf_size);
+            }
+        }
+
+        let idx = self.inputs.get_idx("in").unwrap();
+        let mut block = [0.0; MAX_BUF_SIZE];
+        let block = &mut block[..buf_size];
+        if collect_and_average(block, inputs[idx], input_mixing()).await {
+            self.inputs.record_level(idx, block);
+        }
+
+        for input_port in inputs.iter_mut() {
+            for input_pipe in input_port.iter_mut() {
+                input_pipe.release(buf_size.min(input_pipe.view().len()));
+            }
+        }
+
+        let amount = self.amount.load(std::sync::atomic::Ordering::Relaxed);
+        let mut last = self.held.lock().unwrap();
+        last.clear();
+        last.extend(block.iter().map(|x| x * amount));
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Mutex;
+
+use atomig::Atomic;
+use eframe::egui;
+use egui::Ui;
+
+use crate::{ids::NodeId, node::*, util::nearest_zero_crossing};
+
+use super::fir::load_mono;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    output = "out",
+    title = "File Player",
+    cfg_name = "file_player",
+    description = "Play an audio file, mixed down to mono",
+    custom_render = "FilePlayer::render",
+    reset_state = "FilePlayer::reset"
+)]
+pub struct FilePlayer {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// The file is loaded again when a saved node is first drawn
+    #[dsp(default = "Mutex::new(None)", save)]
+    file_name: Mutex<Option<String>>,
+
+    #[dsp(checkbox, label = "Loop", save, default = "false")]
+    looping: Atomic<bool>,
+
+    #[dsp(default = "Mutex::new(Vec::new())")]
+    samples: Mutex<Vec<f32>>,
+
+    /// Whether `file_name` has been loaded into `samples` yet
+    loaded: Atomic<bool>,
+
+    playing: Atomic<bool>,
+    cursor: Atomic<usize>,
+
+    /// Where looping jumps back to, and the sample it jumps from, snapped to
+    /// zero crossings so the loop doesn't click
+    loop_start: Atomic<usize>,
+    loop_end: Atomic<usize>,
+}
+
+impl FilePlayer {
+    fn reset(&self) {
+        self.cursor.store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn load(&self, path: &str) {
+        tracing::info!("Loading {:?} to play", path);
+
+        let samples = match load_mono(path.as_ref()) {
+            Ok(samples) => samples.into_iter().map(|x| x as f32).collect(),
+            Err(e) => {
+                tracing::warn!("Couldn't load {:?}: {:?}", path, e);
+                Vec::new()
+            }
+        };
+
+        let start = nearest_zero_crossing(&samples, 0);
+        let end = nearest_zero_crossing(&samples, samples.len().saturating_sub(1)) + 1;
+        let (start, end) = if start < end {
+            (start, end.min(samples.len()))
+        } else {
+            (0, samples.len())
+        };
+
+        let mut current = self.samples.lock().unwrap();
+        *current = samples;
+        self.loop_start
+            .store(start, std::sync::atomic::Ordering::Relaxed);
+        self.loop_end
+            .store(end, std::sync::atomic::Ordering::Relaxed);
+        drop(current);
+
+        self.cursor.store(0, std::sync::atomic::Ordering::Relaxed);
+        self.loaded
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        let file_name = self.file_name.lock().unwrap().clone();
+
+        if let Some(name) = &file_name {
+            if !self.loaded.load(std::sync::atomic::Ordering::Relaxed) {
+                self.load(name);
+            }
+        }
+
+        ui.label(match &file_name {
+            Some(name) => format!("Loaded: {name}"),
+            None => "No file loaded".to_owned(),
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Load").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_title("Load audio")
+                    .add_filter("wave file", &["wav"])
+                    .pick_file()
+                {
+                    let name = path.to_string_lossy().to_string();
+                    self.load(&name);
+                    *self.file_name.lock().unwrap() = Some(name);
+                }
+            }
+
+            let playing = self.playing.load(std::sync::atomic::Ordering::Relaxed);
+            if ui.button(if playing { "Pause" } else { "Play" }).clicked() {
+                self.playing
+                    .store(!playing, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+
+        let sample_rate = crate::devices::sample_rate() as f32;
+        let length = self.samples.lock().unwrap().len();
+        let cursor = self.cursor.load(std::sync::atomic::Ordering::Relaxed);
+
+        ui.label(format!(
+            "{:.1}s / {:.1}s",
+            cursor as f32 / sample_rate,
+            length as f32 / sample_rate
+        ));
+
+        if self.playing.load(std::sync::atomic::Ordering::Relaxed) {
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
+impl SimpleNode for FilePlayer {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, _inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let output = outputs.get("out").unwrap();
+        output.fill(0.0);
+
+        if !self.playing.load(std::sync::atomic::Ordering::Relaxed) {
+            return;
+        }
+
+        let samples = self.samples.lock().unwrap();
+        let looping = self.looping.load(std::sync::atomic::Ordering::Relaxed);
+        let mut cursor = self.cursor.load(std::sync::atomic::Ordering::Relaxed);
+        let end = if looping {
+            self.loop_end
+                .load(std::sync::atomic::Ordering::Relaxed)
+                .min(samples.len())
+        } else {
+            samples.len()
+        };
+
+        let mut written = 0;
+        while written < output.len() {
+            if cursor >= end {
+                if looping && end > 0 {
+                    cursor = self
+                        .loop_start
+                        .load(std::sync::atomic::Ordering::Relaxed)
+                        .min(end - 1);
+                } else {
+                    // stopping rewinds, so pressing play again starts over
+                    cursor = 0;
+                    self.playing
+                        .store(false, std::sync::atomic::Ordering::Relaxed);
+                    break;
+                }
+            }
+
+            let n = (output.len() - written).min(end - cursor);
+            output[written..written + n].copy_from_slice(&samples[cursor..cursor + n]);
+
+            written += n;
+            cursor += n;
+        }
+
+        self.cursor
+            .store(cursor, std::sync::atomic::Ordering::Relaxed);
+    }
+}
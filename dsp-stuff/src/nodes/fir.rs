@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 use eframe::egui;
 use std::iter::zip;
+use std::path::Path;
 use std::sync::Mutex;
 use atomig::Atomic;
 use dasp_interpolate::sinc::Sinc;
@@ -8,6 +9,7 @@ use dasp_signal::Signal;
 use egui::Ui;
 use serde::{Deserialize, Serialize};
 use symphonia_core::audio::SampleBuffer;
+use symphonia_core::errors::Error;
 use symphonia_core::formats::FormatOptions;
 use symphonia_core::io::MediaSourceStream;
 use symphonia_core::meta::MetadataOptions;
@@ -40,7 +42,8 @@ enum Mode {
     title = "FIR Filter",
     cfg_name = "fir",
     description = "Perform a FIR operation",
-    custom_render = "Fir::render"
+    custom_render = "Fir::render",
+    reset_state = "Fir::reset"
 )]
 pub struct Fir {
     #[dsp(id)]
@@ -66,6 +69,10 @@ pub struct Fir {
 }
 
 impl Fir {
+    fn reset(&self) {
+        self.state.lock().unwrap().clear();
+    }
+
     fn render(&self, ui: &mut Ui) {
         let mut file_name = self.file_name.lock().unwrap();
 
@@ -83,97 +90,107 @@ impl Fir {
             {
                 tracing::info!("loading IR from file {:?}", path);
 
-                let f = std::fs::File::open(&path).unwrap();
-
-                let mss = MediaSourceStream::new(Box::new(f), Default::default());
+                let mut taps = match load_mono(&path) {
+                    Ok(samples) => samples,
+                    Err(e) => {
+                        tracing::warn!("Couldn't load IR from {:?}: {:?}", path, e);
+                        return;
+                    }
+                };
 
-                let hint = Hint::new();
+                taps.reverse();
+                *self.taps.lock().unwrap() = taps;
 
-                // Use the default options when reading and decoding.
-                let format_opts: FormatOptions = Default::default();
-                let metadata_opts: MetadataOptions = Default::default();
-                // Probe the media source stream for a format.
-                let probed = symphonia::default::get_probe()
-                    .format(&hint, mss, &format_opts, &metadata_opts)
-                    .unwrap();
+                *file_name = Some(path.to_string_lossy().to_string());
+            }
+        }
+    }
+}
 
-                // Get the format reader yielded by the probe operation.
-                let mut reader = probed.format;
+/// Decode an audio file, mixed down to mono and resampled to the graph's rate
+pub(super) fn load_mono(path: &Path) -> Result<Vec<f64>, Error> {
+    let f = std::fs::File::open(path)?;
 
-                let track = reader.default_track().unwrap().clone();
+    let mss = MediaSourceStream::new(Box::new(f), Default::default());
 
-                let mut decoder = symphonia::default::get_codecs()
-                    .make(&track.codec_params, &Default::default())
-                    .unwrap();
+    let hint = Hint::new();
 
-                let mut samples: Vec<f64> = Vec::new();
-                let sample_rate = track.codec_params.sample_rate.unwrap();
+    // Use the default options when reading and decoding.
+    let format_opts: FormatOptions = Default::default();
+    let metadata_opts: MetadataOptions = Default::default();
+    // Probe the media source stream for a format.
+    let probed =
+        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
 
-                loop {
-                    let packet = match reader.next_packet() {
-                        Ok(packet) => packet,
-                        Err(e) => {
-                            tracing::info!("Bad decode after {} samples: {e:?}", samples.len());
-                            break;
-                        }
-                    };
+    // Get the format reader yielded by the probe operation.
+    let mut reader = probed.format;
 
-                    while !reader.metadata().is_latest() {
-                        reader.metadata().pop();
-                    }
+    let track = reader
+        .default_track()
+        .ok_or(Error::Unsupported("no audio track"))?
+        .clone();
 
-                    if packet.track_id() != track.id {
-                        continue;
-                    }
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &Default::default())?;
 
-                    match decoder.decode(&packet) {
-                        Ok(decoded) => {
-                            let spec = *decoded.spec();
-
-                            // Get the capacity of the decoded buffer. Note: This is capacity, not length!
-                            let duration = decoded.capacity() as u64;
-                            let num_channels = spec.channels.count();
-
-                            let mut buf = SampleBuffer::<f64>::new(duration, spec);
-                            buf.copy_interleaved_ref(decoded);
-
-                            samples.extend(
-                                buf.samples()
-                                    .chunks(num_channels)
-                                    .map(|s| s.iter().sum::<f64>() / num_channels as f64),
-                            )
-                        }
-                        Err(symphonia_core::errors::Error::DecodeError(e)) => {
-                            panic!("Bad decode: {e:?}")
-                        }
-                        Err(_) => break,
-                    }
-                }
+    let mut samples: Vec<f64> = Vec::new();
+    let Some(sample_rate) = track.codec_params.sample_rate else {
+        return Err(Error::Unsupported("unknown sample rate"));
+    };
 
-                *self.taps.lock().unwrap() = if sample_rate != 48_000 {
-                    let sinc = Sinc::new(dasp_ring_buffer::Fixed::from([0.0; 16]));
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(e) => {
+                tracing::info!("Bad decode after {} samples: {e:?}", samples.len());
+                break;
+            }
+        };
 
-                    tracing::info!("Resampling taps from {sample_rate}Hz to 48_000Hz");
+        while !reader.metadata().is_latest() {
+            reader.metadata().pop();
+        }
 
-                    let mut taps = dasp_signal::from_iter(samples)
-                        .from_hz_to_hz(sinc, sample_rate as f64, 48_000.0)
-                        .until_exhausted()
-                        .collect::<Vec<f64>>();
+        if packet.track_id() != track.id {
+            continue;
+        }
 
-                    taps.reverse();
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
 
-                    taps
-                } else {
-                    let mut taps = samples;
-                    taps.reverse();
+                // Get the capacity of the decoded buffer. Note: This is capacity, not length!
+                let duration = decoded.capacity() as u64;
+                let num_channels = spec.channels.count();
 
-                    taps
-                };
+                let mut buf = SampleBuffer::<f64>::new(duration, spec);
+                buf.copy_interleaved_ref(decoded);
 
-                *file_name = Some(path.to_string_lossy().to_string());
+                samples.extend(
+                    buf.samples()
+                        .chunks(num_channels)
+                        .map(|s| s.iter().sum::<f64>() / num_channels as f64),
+                )
             }
+            Err(e @ Error::DecodeError(_)) => return Err(e),
+            Err(_) => break,
         }
     }
+
+    let graph_rate = crate::devices::sample_rate();
+
+    if sample_rate == graph_rate {
+        return Ok(samples);
+    }
+
+    let sinc = Sinc::new(dasp_ring_buffer::Fixed::from([0.0; 16]));
+
+    tracing::info!("Resampling from {sample_rate}Hz to {graph_rate}Hz");
+
+    Ok(dasp_signal::from_iter(samples)
+        .from_hz_to_hz(sinc, sample_rate as f64, graph_rate as f64)
+        .until_exhausted()
+        .collect())
 }
 
 impl SimpleNode for Fir {
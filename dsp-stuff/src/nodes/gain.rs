@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 use crate::{ids::NodeId, node::*};
 use atomig::Atomic;
 use collect_slice::CollectSlice;
+use eframe::egui::Ui;
+
+use super::stereo_meter::PeakHold;
 
 #[derive(dsp_stuff_derive::DspNode)]
 #[dsp(
@@ -8,7 +13,8 @@ use collect_slice::CollectSlice;
     output = "out",
     title = "Gain",
     cfg_name = "gain",
-    description = "Adjust gain of a signal"
+    description = "Adjust gain of a signal",
+    custom_render = "Gain::render"
 )]
 pub struct Gain {
     #[dsp(id)]
@@ -20,13 +26,24 @@ pub struct Gain {
 
     #[dsp(slider(range = "0.0..=10.0", as_input), save, default = "1.0")]
     level: Atomic<f32>,
+
+    peak_hold: PeakHold,
+}
+
+impl Gain {
+    fn render(&self, ui: &mut Ui) {
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+
+        self.peak_hold.render(ui);
+    }
 }
 
 impl SimpleNode for Gain {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
-        let mut level = [0.0; BUF_SIZE];
+        let mut level = [0.0; MAX_BUF_SIZE];
         self.level_input(&inputs, &mut level);
+        let level = &level[..buf_size()];
         let input = inputs.get("in").unwrap();
         let output = outputs.get("out").unwrap();
 
@@ -35,5 +52,7 @@ impl SimpleNode for Gain {
             .zip(level)
             .map(|(x, level)| x * level)
             .collect_slice(output);
+
+        self.peak_hold.update(output);
     }
 }
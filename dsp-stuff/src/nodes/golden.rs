@@ -0,0 +1,187 @@
+//! Golden-file regression tests for the core effect nodes
+//!
+//! Each case feeds an impulse and a log sweep through a freshly made node and
+//! compares what comes out with the output stored under `tests/golden`. Both
+//! stimuli are [`SAMPLES`] long at [`SAMPLE_RATE`] and peak at [`LEVEL`], and
+//! every output sample has to be within [`TOLERANCE`] of the stored one.
+//!
+//! Run the tests with `DSP_STUFF_BLESS=1` to write the stored outputs, after
+//! adding a case or an intended change, a missing one is otherwise a failure.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    biquad::BiQuad, chebyshev::Chebyshev, distort::Distort, gain::Gain, high_pass::HighPass,
+    low_pass::LowPass, reverb::Reverb,
+};
+use crate::{devices, ids::NodeId, node::*};
+
+/// Length of each stimulus, and so of each stored output
+const SAMPLES: usize = 4096;
+
+/// Rate the stored outputs were made at
+const SAMPLE_RATE: u32 = 48000;
+
+/// Peak level of each stimulus, leaving the distortions some headroom
+const LEVEL: f32 = 0.5;
+
+/// How far an output sample may be from the stored one
+const TOLERANCE: f32 = 1e-4;
+
+#[derive(Serialize, Deserialize)]
+struct Golden {
+    impulse: Vec<f32>,
+    sweep: Vec<f32>,
+}
+
+fn impulse() -> Vec<f32> {
+    let mut buf = vec![0.0; SAMPLES];
+    buf[0] = LEVEL;
+    buf
+}
+
+/// A sine sweeping exponentially from 20hz to 20khz
+fn sweep() -> Vec<f32> {
+    let (start, end) = (20.0f64, 20000.0f64);
+    let duration = SAMPLES as f64 / SAMPLE_RATE as f64;
+    let k = (end / start).ln() / duration;
+
+    (0..SAMPLES)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            let phase = std::f64::consts::TAU * start * ((k * t).exp() - 1.0) / k;
+            LEVEL * phase.sin() as f32
+        })
+        .collect()
+}
+
+fn compare(name: &str, stimulus: &str, expected: &[f32], actual: &[f32]) {
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "{name} gave a different length of {stimulus} response"
+    );
+
+    // written so a NaN doesn't count as close
+    let close = |e: f32, a: f32| (e - a).abs() <= TOLERANCE;
+
+    if let Some((i, (e, a))) = expected
+        .iter()
+        .zip(actual)
+        .enumerate()
+        .find(|(_, (e, a))| !close(**e, **a))
+    {
+        panic!(
+            "{name}'s {stimulus} response differs at sample {i}, expected {e} but got {a}. \
+             Rerun with DSP_STUFF_BLESS=1 if the change is intended"
+        );
+    }
+}
+
+/// Check a node, set up by `configure`, against the stored output `name`
+fn check<N: SimpleNode + NodeStatic>(name: &str, configure: impl Fn(&N)) {
+    let actual = devices::with_sample_rate(SAMPLE_RATE, || {
+        let run = |stimulus: &[f32]| {
+            let node = N::new(NodeId::generate());
+            configure(&node);
+            run_block(&node, stimulus)
+        };
+
+        Golden {
+            impulse: run(&impulse()),
+            sweep: run(&sweep()),
+        }
+    });
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.json"));
+
+    if std::env::var_os("DSP_STUFF_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_string(&actual).unwrap()).unwrap();
+        eprintln!("Wrote {}", path.display());
+        return;
+    }
+
+    let stored = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Couldn't read {}: {e}. Run with DSP_STUFF_BLESS=1 to write it",
+            path.display()
+        )
+    });
+    let expected: Golden = serde_json::from_str(&stored).unwrap();
+
+    compare(name, "impulse", &expected.impulse, &actual.impulse);
+    compare(name, "sweep", &expected.sweep, &actual.sweep);
+}
+
+#[test]
+fn gain() {
+    check("gain", |n: &Gain| {
+        assert!(n.set_param("level", 2.0));
+    });
+}
+
+#[test]
+fn distort() {
+    for mode in [
+        "HardClip",
+        "SoftClip",
+        "Tanh",
+        "RecipSoftClip",
+        "Fuzz",
+        "Sin",
+        "Atan",
+        "Square",
+        "Chebyshev4",
+    ] {
+        check(&format!("distort_{mode}"), |n: &Distort| {
+            assert!(n.set_param("level", 4.0));
+            assert!(n.set_choice("mode", mode));
+        });
+    }
+}
+
+#[test]
+fn biquad() {
+    check("biquad_manual", |_: &BiQuad| {});
+
+    for filter_type in ["LowPass", "HighPass", "Peaking"] {
+        check(&format!("biquad_{filter_type}"), |n: &BiQuad| {
+            assert!(n.set_param("frequency", 1000.0));
+            assert!(n.set_param("gain_db", 6.0));
+            assert!(n.set_choice("filter_type", filter_type));
+        });
+    }
+}
+
+#[test]
+fn low_pass() {
+    check("low_pass", |_: &LowPass| {});
+}
+
+#[test]
+fn high_pass() {
+    check("high_pass", |_: &HighPass| {});
+}
+
+#[test]
+fn reverb() {
+    for mode in ["Echo", "Flanger"] {
+        check(&format!("reverb_{mode}"), |n: &Reverb| {
+            assert!(n.set_param("seconds", 0.01));
+            assert!(n.set_choice("mode", mode));
+        });
+    }
+}
+
+#[test]
+fn chebyshev() {
+    check("chebyshev", |n: &Chebyshev| {
+        assert!(n.set_param("level_pos", 5.0));
+        assert!(n.set_param("level_neg", 10.0));
+    });
+}
@@ -0,0 +1,128 @@
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+
+use super::{high_pass::HighPass, low_pass::LowPass, overdrive::Overdrive, reverb::Reverb};
+
+/// Length of the room's echo, short enough to sound like a small room
+const ROOM_SECONDS: f32 = 0.08;
+
+/// The nodes making up the chain, in the order the signal goes through them
+struct Chain {
+    /// Takes out rumble and handling noise below ~80hz before the drive
+    high_pass: HighPass,
+    overdrive: Overdrive,
+    /// The tone control
+    low_pass: LowPass,
+    room: Reverb,
+}
+
+impl Chain {
+    fn new() -> Self {
+        let this = Self {
+            high_pass: HighPass::new(NodeId::generate()),
+            overdrive: Overdrive::new(NodeId::generate()),
+            low_pass: LowPass::new(NodeId::generate()),
+            room: Reverb::new(NodeId::generate()),
+        };
+
+        this.high_pass.set_param("ratio", 0.99);
+        this.overdrive.set_param("boost", 10.0);
+        this.room.set_param("seconds", ROOM_SECONDS.into());
+
+        this
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Guitar chain",
+    cfg_name = "guitar_chain",
+    description = "A ready made guitar chain: high pass, overdrive, tone and a small room",
+    reset_state = "GuitarChain::reset",
+    refresh_settings = "GuitarChain::refresh"
+)]
+pub struct GuitarChain {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.7")]
+    drive: Atomic<f32>,
+
+    /// 0 is darkest, 1 leaves the drive unfiltered
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    tone: Atomic<f32>,
+
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.3")]
+    room: Atomic<f32>,
+
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    level: Atomic<f32>,
+
+    #[dsp(default = "Chain::new()")]
+    chain: Chain,
+
+    /// The room level the reverb was last given, changing it restarts the
+    /// reverb's delay line so it's only passed on when it changes
+    #[dsp(default = "f32::NAN")]
+    room_applied: Atomic<f32>,
+}
+
+impl GuitarChain {
+    fn refresh(&self) {
+        self.chain.high_pass.refresh_settings();
+        self.chain.overdrive.refresh_settings();
+        self.chain.low_pass.refresh_settings();
+        self.chain.room.refresh_settings();
+    }
+
+    fn reset(&self) {
+        self.chain.high_pass.reset_state();
+        self.chain.overdrive.reset_state();
+        self.chain.low_pass.reset_state();
+        self.chain.room.reset_state();
+    }
+}
+
+impl SimpleNode for GuitarChain {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let drive = self.drive.load(std::sync::atomic::Ordering::Relaxed);
+        let tone = self.tone.load(std::sync::atomic::Ordering::Relaxed);
+        let room = self.room.load(std::sync::atomic::Ordering::Relaxed);
+        let level = self.level.load(std::sync::atomic::Ordering::Relaxed);
+
+        let chain = &self.chain;
+
+        chain.overdrive.set_param("drive", drive.into());
+        chain.overdrive.set_param("level", level.into());
+        chain
+            .low_pass
+            .set_param("ratio", 0.9 * (1.0 - f64::from(tone)));
+
+        if self
+            .room_applied
+            .swap(room, std::sync::atomic::Ordering::Relaxed)
+            != room
+        {
+            chain.room.set_param("decay", room.into());
+        }
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let len = input.len();
+        let mut a = [0.0; MAX_BUF_SIZE];
+        let mut b = [0.0; MAX_BUF_SIZE];
+
+        process_nested(&chain.high_pass, ("in", input), ("out", &mut a[..len]));
+        process_nested(&chain.overdrive, ("in", &a[..len]), ("out", &mut b[..len]));
+        process_nested(&chain.low_pass, ("in", &b[..len]), ("out", &mut a[..len]));
+        process_nested(&chain.room, ("in", &a[..len]), ("out", output));
+    }
+}
@@ -7,7 +7,8 @@ use atomig::Atomic;
     output = "out",
     title = "High Pass",
     cfg_name = "high_pass",
-    description = "Attenuates lower frequencies"
+    description = "Attenuates lower frequencies",
+    reset_state = "HighPass::reset"
 )]
 pub struct HighPass {
     #[dsp(id)]
@@ -23,6 +24,12 @@ pub struct HighPass {
     z: Atomic<f32>,
 }
 
+impl HighPass {
+    fn reset(&self) {
+        self.z.store(0.0, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
 impl SimpleNode for HighPass {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
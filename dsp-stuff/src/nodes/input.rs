@@ -1,4 +1,8 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
 use eframe::egui;
 use crate::{
     devices,
@@ -9,16 +13,29 @@ use arc_swap::ArcSwap;
 use rivulet::{circular_buffer::Source, splittable, View, ViewMut};
 use tokio::sync::Mutex;
 
+/// Ports every input has, `out` carries both channels mixed together and the
+/// others a channel each, a mono device's signal goes out of all of them
+const PORTS: [&str; 3] = ["out", "out_l", "out_r"];
+
 pub struct Input {
     id: NodeId,
     inputs: PortStorage,
     outputs: PortStorage,
     source: Arc<Mutex<Option<splittable::View<Source<f32>>>>>,
+    /// Channels interleaved in `source`
+    channels: AtomicUsize,
+    /// Channels the device has, if there's more than two only a pair of them
+    /// starting at `first_channel` are read
+    device_channels: AtomicUsize,
+    first_channel: Arc<AtomicUsize>,
 
     cached_hosts: ArcSwap<Vec<cpal::HostId>>,
     selected_host: ArcSwap<cpal::HostId>,
     cached_devices: ArcSwap<Vec<String>>,
     selected_device: ArcSwap<Option<(String, DeviceId)>>,
+    /// A device that was asked for but couldn't be opened, such as one named
+    /// in a config saved on another machine, kept so it's saved and retried
+    missing_device: ArcSwap<Option<String>>,
 }
 
 impl Drop for Input {
@@ -34,10 +51,23 @@ struct InputConfig {
     id: NodeId,
     selected_host: String,
     selected_device: Option<String>,
+    #[serde(default)]
+    first_channel: usize,
     outputs: HashMap<String, PortId>,
 }
 
 impl Input {
+    /// Open the missing device if it's turned up in the device list
+    fn retry_missing_device(&self) {
+        let Some(missing) = Option::clone(&self.missing_device.load()) else {
+            return;
+        };
+
+        if self.cached_devices.load().contains(&missing) {
+            self.load_device(**self.selected_host.load(), Some(missing));
+        }
+    }
+
     fn load_device(&self, host: cpal::HostId, name: Option<String>) {
         let mut source = self.source.blocking_lock();
 
@@ -52,15 +82,26 @@ impl Input {
             devices::invoke(devices::DeviceCommand::CloseDevice(id));
         }
 
+        self.missing_device.store(Arc::new(None));
+
         if let Some(dev) = name {
-            if let Some((id, new_source)) =
-                devices::invoke(devices::DeviceCommand::OpenInput(host, dev.clone()))
-                    .input_opened()
-                    .unwrap()
+            if let Some((id, new_source, channels, device_channels)) =
+                devices::invoke(devices::DeviceCommand::OpenInput(
+                    host,
+                    dev.clone(),
+                    Arc::clone(&self.first_channel),
+                ))
+                .input_opened()
+                .unwrap()
             {
                 self.selected_device.store(Arc::new(Some((dev, id))));
+                self.channels
+                    .store(channels, std::sync::atomic::Ordering::Relaxed);
+                self.device_channels
+                    .store(device_channels, std::sync::atomic::Ordering::Relaxed);
                 *source = Some(new_source);
             } else {
+                self.missing_device.store(Arc::new(Some(dev)));
                 self.selected_device.store(Arc::new(None));
                 *source = None;
             }
@@ -71,6 +112,34 @@ impl Input {
     }
 }
 
+/// Pick which pair of a device's channels are used, for devices with more than
+/// two
+pub(super) fn channel_pair_select(
+    ui: &mut egui::Ui,
+    id: NodeId,
+    first_channel: &AtomicUsize,
+    device_channels: usize,
+) {
+    let label = |first: usize| format!("{} + {}", first + 1, first + 2);
+
+    let current = first_channel
+        .load(std::sync::atomic::Ordering::Relaxed)
+        .min(device_channels - 2);
+    let mut selected = current;
+
+    egui::ComboBox::new(("channels", id), "Channels")
+        .selected_text(label(current))
+        .show_ui(ui, |ui| {
+            for first in (0..device_channels - 1).step_by(2) {
+                ui.selectable_value(&mut selected, first, label(first));
+            }
+        });
+
+    if selected != current {
+        first_channel.store(selected, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
 impl Node for Input {
     fn title(&self) -> &'static str {
         "Input"
@@ -101,7 +170,11 @@ impl Node for Input {
             id: self.id,
             selected_host: self.selected_host.load().name().to_owned(),
             selected_device: Option::as_ref(&self.selected_device.load())
-                .map(|(n, _)| n.to_owned()),
+                .map(|(n, _)| n.to_owned())
+                .or_else(|| Option::clone(&self.missing_device.load())),
+            first_channel: self
+                .first_channel
+                .load(std::sync::atomic::Ordering::Relaxed),
             outputs: self.outputs.get_all(),
         };
 
@@ -128,9 +201,10 @@ impl Node for Input {
                 .unwrap();
 
             self.cached_devices.store(Arc::new(devices));
+            self.retry_missing_device();
         }
 
-        let (current_device, _current_device_id) = self
+        let (current_device, current_device_id) = self
             .selected_device
             .load()
             .as_ref()
@@ -157,6 +231,51 @@ impl Node for Input {
         if current_device != selected_device {
             self.load_device(selected_host, selected_device);
         }
+
+        if let Some(missing) = self.missing_device.load().as_ref() {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(format!("Device not found: {missing}"))
+                        .color(ui.visuals().error_fg_color),
+                );
+
+                if ui
+                    .button("Retry")
+                    .on_hover_text_at_pointer("Look for the device again")
+                    .clicked()
+                {
+                    let host = **self.selected_host.load();
+                    let devices = devices::invoke(devices::DeviceCommand::ListInputs(host))
+                        .devices()
+                        .unwrap();
+                    self.cached_devices.store(Arc::new(devices));
+                    self.retry_missing_device();
+                }
+            });
+        }
+
+        let device_channels = self
+            .device_channels
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if current_device.is_some() && device_channels > 2 {
+            channel_pair_select(ui, self.id, &self.first_channel, device_channels);
+        }
+
+        if let Some(id) = current_device_id {
+            ui.ctx().request_repaint_after(Duration::from_millis(250));
+
+            if let Some(stats) = devices::invoke(devices::DeviceCommand::GetStats(id))
+                .stats()
+                .unwrap()
+            {
+                ui.label(format!("Overruns: {}", stats.overruns))
+                    .on_hover_text_at_pointer(
+                        "Callbacks from the device that were dropped as the graph hadn't \
+                         taken the previous samples yet. These come from the graph running \
+                         too slowly, not from the device.",
+                    );
+            }
+        }
     }
 }
 
@@ -165,35 +284,47 @@ impl NodeStatic for Input {
         let hosts = devices::invoke(devices::DeviceCommand::ListHosts)
             .hosts()
             .unwrap();
-        let selected_host = *hosts.first().expect("There are no audio hosts available");
+        // with no hosts at all the node is left with nothing to pick from
+        let selected_host = hosts
+            .first()
+            .copied()
+            .unwrap_or_else(|| cpal::default_host().id());
         let devices = devices::invoke(devices::DeviceCommand::ListInputs(selected_host))
             .devices()
             .unwrap();
 
         let outputs = PortStorage::default();
-        outputs.add("out".to_owned());
+        for port in PORTS {
+            outputs.add(port.to_owned());
+        }
 
         Self {
             id,
             inputs: PortStorage::default(),
             outputs,
             source: Arc::new(Mutex::new(None)),
+            channels: AtomicUsize::new(1),
+            device_channels: AtomicUsize::new(1),
+            first_channel: Arc::new(AtomicUsize::new(0)),
 
             cached_hosts: ArcSwap::new(Arc::new(hosts)),
             selected_host: ArcSwap::new(Arc::new(selected_host)),
 
             cached_devices: ArcSwap::new(Arc::new(devices)),
             selected_device: ArcSwap::new(Arc::new(None)),
+            missing_device: ArcSwap::new(Arc::new(None)),
         }
     }
 
-    fn restore(value: serde_json::Value) -> Self
+    fn restore(value: serde_json::Value) -> Result<Self, serde_json::Error>
     where
         Self: Sized,
     {
-        let cfg: InputConfig = serde_json::from_value(value).unwrap();
+        let cfg: InputConfig = serde_json::from_value(value)?;
 
         let mut this = Self::new(cfg.id);
+        this.first_channel
+            .store(cfg.first_channel, std::sync::atomic::Ordering::Relaxed);
 
         if let Some(host) = devices::invoke(devices::DeviceCommand::ListHosts)
             .hosts()
@@ -202,33 +333,69 @@ impl NodeStatic for Input {
             .find(|x| x.name() == cfg.selected_host)
         {
             this.load_device(host, cfg.selected_device);
-        };
+        } else {
+            this.missing_device.store(Arc::new(cfg.selected_device));
+        }
 
         this.outputs = PortStorage::new(cfg.outputs);
+        // saved before inputs were stereo
+        for port in PORTS {
+            if this.outputs.get_id(port).is_none() {
+                this.outputs.add(port.to_owned());
+            }
+        }
 
-        this
+        Ok(this)
     }
 }
 
 impl Perform for Input {
     // #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     async fn perform(&self, _inputs: NodeInputs<'_, '_, '_>, outputs: NodeOutputs<'_, '_, '_>) {
-        let buf_size = 128;
+        let buf_size = buf_size();
 
         let mut source = self.source.lock().await;
 
         if let Some(source) = source.as_mut() {
-            source.grant(buf_size).await.unwrap();
+            let channels = self.channels.load(std::sync::atomic::Ordering::Relaxed);
+            source.grant(buf_size * channels).await.unwrap();
+
+            let mut mixed = [0.0; MAX_BUF_SIZE];
+            let mut left = [0.0; MAX_BUF_SIZE];
+            let mut right = [0.0; MAX_BUF_SIZE];
+            let mixed = &mut mixed[..buf_size];
+            let left = &mut left[..buf_size];
+            let right = &mut right[..buf_size];
+
+            let view = &source.view()[..buf_size * channels];
+            if channels == 2 {
+                for (i, frame) in view.chunks_exact(2).enumerate() {
+                    left[i] = frame[0];
+                    right[i] = frame[1];
+                    mixed[i] = frame[0] + frame[1];
+                }
+            } else {
+                mixed.copy_from_slice(view);
+                left.copy_from_slice(view);
+                right.copy_from_slice(view);
+            }
+
+            for (port, block) in PORTS.iter().zip([&*mixed, &*left, &*right]) {
+                let idx = self.outputs.get_idx(port).unwrap();
+                let output = &mut outputs[idx];
+
+                if !output.is_empty() {
+                    self.outputs.record_level(idx, block);
+                }
 
-            for output in outputs.iter_mut() {
                 for out in output.iter_mut() {
                     out.grant(buf_size).await.unwrap();
-                    out.view_mut()[..buf_size].copy_from_slice(&source.view()[..buf_size]);
+                    out.view_mut()[..buf_size].copy_from_slice(block);
                 }
             }
 
             // tracing::debug!("Releasing source");
-            source.release(buf_size);
+            source.release(buf_size * channels);
 
             // tracing::debug!("Releasing outputs");
             for output_port in outputs.iter_mut() {
@@ -0,0 +1,144 @@
+use std::sync::{atomic::AtomicBool, Mutex};
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use eframe::egui::Ui;
+
+/// The least time (in ms) the fire button opens the gate for, so it's audible
+/// even with no hold
+const FIRE_HOLD: f32 = 100.0;
+
+#[derive(Default)]
+struct GateState {
+    /// Gain currently applied to the signal, ramps between 0 and 1
+    gain: f32,
+    /// Samples left before the gate starts closing
+    held: usize,
+}
+
+/// Coefficient for a one pole smoother reaching ~63% of the way in `ms`
+fn smoothing(ms: f32) -> f32 {
+    if ms <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (ms * crate::devices::sample_rate() as f32 / 1000.0)).exp()
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    input = "key",
+    output = "out",
+    title = "Keyed gate",
+    cfg_name = "keyed_gate",
+    description = "Let the signal through only while the key input is above the threshold",
+    custom_render = "KeyedGate::render",
+    reset_state = "KeyedGate::reset"
+)]
+pub struct KeyedGate {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "-80.0..=0.0", suffix = " dB"),
+        label = "Threshold",
+        save,
+        default = "-30.0"
+    )]
+    threshold_db: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.0..=100.0", suffix = " ms"),
+        label = "Attack",
+        save,
+        default = "1.0"
+    )]
+    attack: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.0..=1000.0", suffix = " ms"),
+        label = "Hold",
+        save,
+        default = "20.0"
+    )]
+    hold: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.0..=1000.0", logarithmic, suffix = " ms"),
+        label = "Release",
+        save,
+        default = "50.0"
+    )]
+    release: Atomic<f32>,
+
+    state: Mutex<GateState>,
+
+    /// Set from the ui to key the gate once, as if the key had crossed the
+    /// threshold
+    fired: AtomicBool,
+}
+
+impl KeyedGate {
+    fn reset(&self) {
+        *self.state.lock().unwrap() = GateState::default();
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        if ui
+            .button("Fire")
+            .on_hover_text_at_pointer("Open the gate once, for trying it out without a key")
+            .clicked()
+        {
+            self.fired.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+impl SimpleNode for KeyedGate {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let threshold_db = self.threshold_db.load(std::sync::atomic::Ordering::Relaxed);
+        let threshold = 10.0f32.powf(threshold_db / 20.0);
+        let attack = smoothing(self.attack.load(std::sync::atomic::Ordering::Relaxed));
+        let release = smoothing(self.release.load(std::sync::atomic::Ordering::Relaxed));
+        let samples_per_ms = crate::devices::sample_rate() as f32 / 1000.0;
+        let hold = (self.hold.load(std::sync::atomic::Ordering::Relaxed) * samples_per_ms) as usize;
+        let fire_hold = (FIRE_HOLD * samples_per_ms) as usize;
+
+        let input = inputs.get("in").unwrap();
+        // with nothing keying it the gate stays shut
+        let key = inputs.get_checked("key");
+        let output = outputs.get("out").unwrap();
+
+        let mut fired = self.fired.swap(false, std::sync::atomic::Ordering::Relaxed);
+
+        let mut state = self.state.lock().unwrap();
+
+        for (i, (out, x)) in output.iter_mut().zip(input).enumerate() {
+            let keyed = key.is_some_and(|key| key[i].abs() >= threshold);
+
+            if std::mem::take(&mut fired) {
+                state.held = hold.max(fire_hold);
+            } else if keyed {
+                state.held = hold;
+            } else {
+                state.held = state.held.saturating_sub(1);
+            }
+
+            let (target, coef) = if keyed || state.held > 0 {
+                (1.0, attack)
+            } else {
+                (0.0, release)
+            };
+
+            state.gain = target + (state.gain - target) * coef;
+
+            *out = x * state.gain;
+        }
+    }
+}
@@ -7,7 +7,8 @@ use atomig::Atomic;
     output = "out",
     title = "Low Pass",
     cfg_name = "high_pass",
-    description = "Attenuates higher frequencies"
+    description = "Attenuates higher frequencies",
+    reset_state = "LowPass::reset"
 )]
 pub struct LowPass {
     #[dsp(id)]
@@ -23,6 +24,12 @@ pub struct LowPass {
     z: Atomic<f32>,
 }
 
+impl LowPass {
+    fn reset(&self) {
+        self.z.store(0.0, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
 impl SimpleNode for LowPass {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
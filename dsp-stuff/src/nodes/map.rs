@@ -0,0 +1,114 @@
+use atomig::Atomic;
+use collect_slice::CollectSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::{ids::NodeId, node::*};
+
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+)]
+#[repr(u8)]
+enum Curve {
+    Linear,
+    /// Slow to start, then rising quickly, for things like frequencies
+    Exp,
+    /// Rising quickly, then levelling off
+    Log,
+}
+
+/// How bent the exponential and logarithmic curves are
+const STEEPNESS: f32 = 4.0;
+
+impl Curve {
+    /// Shape a position in `0..=1`, the ends stay where they are
+    fn shape(self, t: f32) -> f32 {
+        match self {
+            Curve::Linear => t,
+            Curve::Exp => (STEEPNESS * t).exp_m1() / STEEPNESS.exp_m1(),
+            Curve::Log => (t * STEEPNESS.exp_m1()).ln_1p() / STEEPNESS,
+        }
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Map",
+    cfg_name = "map",
+    description = "Scale and offset a control signal from one range to another"
+)]
+pub struct Map {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(slider(range = "-1.0..=1.0"), label = "In min", save, default = "-1.0")]
+    in_min: Atomic<f32>,
+    #[dsp(slider(range = "-1.0..=1.0"), label = "In max", save, default = "1.0")]
+    in_max: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "-20000.0..=20000.0", logarithmic),
+        label = "Out min",
+        save,
+        default = "0.0"
+    )]
+    out_min: Atomic<f32>,
+    #[dsp(
+        slider(range = "-20000.0..=20000.0", logarithmic),
+        label = "Out max",
+        save,
+        default = "1.0"
+    )]
+    out_max: Atomic<f32>,
+
+    #[dsp(select, save, default = "Curve::Linear")]
+    curve: Atomic<Curve>,
+}
+
+impl SimpleNode for Map {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let in_min = self.in_min.load(std::sync::atomic::Ordering::Relaxed);
+        let in_max = self.in_max.load(std::sync::atomic::Ordering::Relaxed);
+        let out_min = self.out_min.load(std::sync::atomic::Ordering::Relaxed);
+        let out_max = self.out_max.load(std::sync::atomic::Ordering::Relaxed);
+        let curve = self.curve.load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let in_range = in_max - in_min;
+
+        input
+            .iter()
+            .map(|x| {
+                let t = if in_range == 0.0 {
+                    0.0
+                } else {
+                    (x - in_min) / in_range
+                };
+
+                // a linear map can carry on past the ends, the curves can't
+                let t = match curve {
+                    Curve::Linear => t,
+                    _ => curve.shape(t.clamp(0.0, 1.0)),
+                };
+
+                out_min + t * (out_max - out_min)
+            })
+            .collect_slice(output);
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::{Arc, Mutex};
+
+use atomig::Atomic;
+use eframe::egui;
+use egui::Ui;
+
+use crate::{
+    ids::{DeviceId, NodeId},
+    midi::{self, MidiState},
+    node::*,
+};
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    output = "frequency",
+    output = "gate",
+    output = "cc_1",
+    output = "cc_2",
+    output = "cc_3",
+    output = "cc_4",
+    title = "MIDI In",
+    cfg_name = "midi_in",
+    description = "Control signals from a MIDI port, the held note's frequency, a gate while \
+                   it's held, and controller values from 0 to 1",
+    custom_render = "MidiIn::render"
+)]
+pub struct MidiIn {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// Controller numbers sent out of each cc port
+    #[dsp(slider(range = "0..=127"), label = "CC 1", save, default = "1")]
+    cc_1: Atomic<u32>,
+    #[dsp(slider(range = "0..=127"), label = "CC 2", save, default = "7")]
+    cc_2: Atomic<u32>,
+    #[dsp(slider(range = "0..=127"), label = "CC 3", save, default = "10")]
+    cc_3: Atomic<u32>,
+    #[dsp(slider(range = "0..=127"), label = "CC 4", save, default = "74")]
+    cc_4: Atomic<u32>,
+
+    /// The port is opened again when a saved node is first drawn
+    #[dsp(default = "Mutex::new(None)", save)]
+    port_name: Mutex<Option<String>>,
+
+    #[dsp(default = "Mutex::new(None)")]
+    connection: Mutex<Option<DeviceId>>,
+    /// Whether `port_name` has been opened yet
+    opened: Atomic<bool>,
+
+    #[dsp(default = "Mutex::new(Vec::new())")]
+    cached_ports: Mutex<Vec<String>>,
+
+    #[dsp(default = "Arc::new(MidiState::default())")]
+    state: Arc<MidiState>,
+}
+
+impl Drop for MidiIn {
+    fn drop(&mut self) {
+        if let Some(id) = self.connection.lock().unwrap().take() {
+            midi::invoke(midi::MidiCommand::Close(id));
+        }
+    }
+}
+
+impl MidiIn {
+    fn refresh_ports(&self) {
+        *self.cached_ports.lock().unwrap() =
+            midi::invoke(midi::MidiCommand::ListPorts).ports().unwrap();
+    }
+
+    fn open_port(&self, name: Option<String>) {
+        let mut connection = self.connection.lock().unwrap();
+
+        if let Some(id) = connection.take() {
+            midi::invoke(midi::MidiCommand::Close(id));
+        }
+
+        if let Some(name) = &name {
+            *connection = midi::invoke(midi::MidiCommand::Open(
+                name.clone(),
+                Arc::clone(&self.state),
+            ))
+            .opened()
+            .unwrap();
+        }
+
+        *self.port_name.lock().unwrap() = name;
+        self.opened
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        if !self.opened.load(std::sync::atomic::Ordering::Relaxed) {
+            self.refresh_ports();
+
+            let name = self.port_name.lock().unwrap().clone();
+            self.open_port(name);
+        }
+
+        let current_port = self.port_name.lock().unwrap().clone();
+        let mut selected_port = current_port.clone();
+
+        ui.horizontal(|ui| {
+            let mut cb = egui::ComboBox::new(("midi_port", self.id), "MIDI port");
+
+            if let Some(p) = &current_port {
+                cb = cb.selected_text(p);
+            }
+
+            cb.show_ui(ui, |ui| {
+                for port in self.cached_ports.lock().unwrap().iter() {
+                    ui.selectable_value(&mut selected_port, Some(port.clone()), port);
+                }
+
+                ui.selectable_value(&mut selected_port, None, "<none>");
+            });
+
+            if ui.button("Refresh").clicked() {
+                self.refresh_ports();
+            }
+        });
+
+        if current_port != selected_port {
+            self.open_port(selected_port);
+        }
+
+        if current_port.is_some() && self.connection.lock().unwrap().is_none() {
+            ui.label(
+                egui::RichText::new("Couldn't open the port").color(ui.visuals().error_fg_color),
+            );
+        }
+    }
+}
+
+impl SimpleNode for MidiIn {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, _inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let frequency = self
+            .state
+            .frequency
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let gate = if self.state.gate.load(std::sync::atomic::Ordering::Relaxed) {
+            1.0
+        } else {
+            0.0
+        };
+
+        outputs.get("frequency").unwrap().fill(frequency);
+        outputs.get("gate").unwrap().fill(gate);
+
+        for (port, cc) in [
+            ("cc_1", &self.cc_1),
+            ("cc_2", &self.cc_2),
+            ("cc_3", &self.cc_3),
+            ("cc_4", &self.cc_4),
+        ] {
+            let controller = cc.load(std::sync::atomic::Ordering::Relaxed) as usize;
+            let value = self
+                .state
+                .controllers
+                .get(controller)
+                .map_or(0.0, |v| v.load(std::sync::atomic::Ordering::Relaxed));
+
+            outputs.get(port).unwrap().fill(value);
+        }
+    }
+}
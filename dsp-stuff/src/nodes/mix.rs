@@ -31,8 +31,9 @@ pub struct Mix {
 impl SimpleNode for Mix {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
-        let mut ratio = [0.0; BUF_SIZE];
+        let mut ratio = [0.0; MAX_BUF_SIZE];
         self.ratio_input(&inputs, &mut ratio);
+        let ratio = &ratio[..buf_size()];
 
         let input_a = inputs.get("a").unwrap();
         let input_b = inputs.get("b").unwrap();
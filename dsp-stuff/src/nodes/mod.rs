@@ -10,28 +10,92 @@ use self::{
     envelope::Envelope, fir::Fir, gain::Gain, high_pass::HighPass, input::Input, low_pass::LowPass,
     mix::Mix, muff::Muff, mux::Mux, output::Output, overdrive::Overdrive, reverb::Reverb,
     signal_gen::SignalGen, spectrogram::Spectrogram, wave_view::WaveView, pitch::Pitch,
+    correlation::Correlation, room_measurement::RoomMeasurement, parallel::Parallel,
+    clock::Clock,
+    expr::Expression,
+    keyed_gate::KeyedGate,
+    map::Map,
+    tempo::Tempo,
+    stereo_reverb::StereoReverb,
+    offset::Offset,
+    bus::{BusReturn, BusSend},
+    reverse::Reverse,
+    stereo_meter::StereoMeter,
+    resonator_bank::ResonatorBank,
+    channel_combiner::ChannelCombiner,
+    guitar_chain::GuitarChain,
+    compressor::Compressor,
+    delay::Delay,
+    pan::Pan,
+    bitcrusher::Bitcrusher,
+    tremolo::Tremolo,
+    peak_eq::PeakEq,
+    bandpass::BandPass,
+    notch::Notch,
+    noise::Noise,
+    adsr::Adsr,
+    recorder::Recorder,
+    file_player::FilePlayer,
+    midi_in::MidiIn,
+    feedback::Feedback,
 };
+#[cfg(feature = "lv2")]
+use self::plugin::Plugin;
 
 pub mod add;
+pub mod adsr;
+pub mod bandpass;
 pub mod biquad;
+pub mod bitcrusher;
+pub mod bus;
+pub mod channel_combiner;
 pub mod chebyshev;
+pub mod clock;
+pub mod compressor;
+pub mod correlation;
+pub mod delay;
 pub mod demux;
 pub mod distort;
 pub mod envelope;
+pub mod expr;
+pub mod feedback;
+pub mod file_player;
 pub mod fir;
 pub mod gain;
+#[cfg(test)]
+mod golden;
+pub mod guitar_chain;
 pub mod high_pass;
 pub mod input;
+pub mod keyed_gate;
 pub mod low_pass;
+pub mod map;
+pub mod midi_in;
 pub mod mix;
 #[cfg(feature = "gpl_effects")]
 pub mod muff;
 pub mod mux;
+pub mod noise;
+pub mod notch;
+pub mod offset;
 pub mod output;
 pub mod overdrive;
+pub mod pan;
+pub mod parallel;
+pub mod peak_eq;
+#[cfg(feature = "lv2")]
+pub mod plugin;
+pub mod recorder;
+pub mod resonator_bank;
 pub mod reverb;
+pub mod reverse;
+pub mod room_measurement;
 pub mod signal_gen;
 pub mod spectrogram;
+pub mod stereo_meter;
+pub mod stereo_reverb;
+pub mod tempo;
+pub mod tremolo;
 pub mod wave_view;
 pub mod pitch;
 
@@ -60,6 +124,39 @@ pub enum Nodes {
     Envelope,
     Fir,
     Pitch,
+    Correlation,
+    RoomMeasurement,
+    Parallel,
+    Clock,
+    Expression,
+    KeyedGate,
+    #[cfg(feature = "lv2")]
+    Plugin,
+    Map,
+    Tempo,
+    StereoReverb,
+    Offset,
+    BusSend,
+    BusReturn,
+    Reverse,
+    StereoMeter,
+    ResonatorBank,
+    ChannelCombiner,
+    GuitarChain,
+    Compressor,
+    Delay,
+    Pan,
+    Bitcrusher,
+    Tremolo,
+    PeakEq,
+    BandPass,
+    Notch,
+    Noise,
+    Adsr,
+    Recorder,
+    FilePlayer,
+    MidiIn,
+    Feedback,
 }
 
 pub static NODES: &[(&str, fn(NodeId) -> Arc<Nodes>)] = &[
@@ -87,37 +184,211 @@ pub static NODES: &[(&str, fn(NodeId) -> Arc<Nodes>)] = &[
     ("Envelope", |id| Arc::new(Nodes::from(Envelope::new(id)))),
     ("FIR", |id| Arc::new(Nodes::from(Fir::new(id)))),
     ("Pitch", |id| Arc::new(Nodes::from(Pitch::new(id)))),
+    ("Correlation", |id| {
+        Arc::new(Nodes::from(Correlation::new(id)))
+    }),
+    ("Room measurement", |id| {
+        Arc::new(Nodes::from(RoomMeasurement::new(id)))
+    }),
+    ("Parallel", |id| Arc::new(Nodes::from(Parallel::new(id)))),
+    ("Clock", |id| Arc::new(Nodes::from(Clock::new(id)))),
+    ("Expression", |id| Arc::new(Nodes::from(Expression::new(id)))),
+    ("Keyed gate", |id| Arc::new(Nodes::from(KeyedGate::new(id)))),
+    #[cfg(feature = "lv2")]
+    ("Plugin", |id| Arc::new(Nodes::from(Plugin::new(id)))),
+    ("Map", |id| Arc::new(Nodes::from(Map::new(id)))),
+    ("Tempo", |id| Arc::new(Nodes::from(Tempo::new(id)))),
+    ("Stereo reverb", |id| {
+        Arc::new(Nodes::from(StereoReverb::new(id)))
+    }),
+    ("Offset", |id| Arc::new(Nodes::from(Offset::new(id)))),
+    ("Send", |id| Arc::new(Nodes::from(BusSend::new(id)))),
+    ("Return", |id| Arc::new(Nodes::from(BusReturn::new(id)))),
+    ("Reverse", |id| Arc::new(Nodes::from(Reverse::new(id)))),
+    ("Stereo meter", |id| Arc::new(Nodes::from(StereoMeter::new(id)))),
+    ("Resonator bank", |id| {
+        Arc::new(Nodes::from(ResonatorBank::new(id)))
+    }),
+    ("Channel combiner", |id| {
+        Arc::new(Nodes::from(ChannelCombiner::new(id)))
+    }),
+    ("Guitar chain", |id| Arc::new(Nodes::from(GuitarChain::new(id)))),
+    ("Compressor", |id| Arc::new(Nodes::from(Compressor::new(id)))),
+    ("Delay", |id| Arc::new(Nodes::from(Delay::new(id)))),
+    ("Pan", |id| Arc::new(Nodes::from(Pan::new(id)))),
+    ("Bitcrusher", |id| Arc::new(Nodes::from(Bitcrusher::new(id)))),
+    ("Tremolo", |id| Arc::new(Nodes::from(Tremolo::new(id)))),
+    ("Peak EQ", |id| Arc::new(Nodes::from(PeakEq::new(id)))),
+    ("Band Pass", |id| Arc::new(Nodes::from(BandPass::new(id)))),
+    ("Notch", |id| Arc::new(Nodes::from(Notch::new(id)))),
+    ("Noise", |id| Arc::new(Nodes::from(Noise::new(id)))),
+    ("ADSR", |id| Arc::new(Nodes::from(Adsr::new(id)))),
+    ("Recorder", |id| Arc::new(Nodes::from(Recorder::new(id)))),
+    ("File Player", |id| Arc::new(Nodes::from(FilePlayer::new(id)))),
+    ("MIDI In", |id| Arc::new(Nodes::from(MidiIn::new(id)))),
+    ("Feedback", |id| Arc::new(Nodes::from(Feedback::new(id)))),
+];
+
+/// Broad groupings of node types, used to colour their title bars
+#[derive(Clone, Copy, PartialEq, Eq, Debug, strum::EnumIter, strum::IntoStaticStr)]
+pub enum Category {
+    /// Nodes that produce a signal
+    Sources,
+    Filters,
+    /// Nodes that act on the level of a signal
+    Dynamics,
+    Effects,
+    /// Routing and plumbing, also the default for anything not listed
+    Utilities,
+    /// Nodes for looking at a signal, rather than changing it
+    Analysis,
+}
+
+/// The category of each node type, keyed by `cfg_name`
+pub static CATEGORIES: &[(&str, Category)] = &[
+    ("input", Category::Sources),
+    ("output", Category::Utilities),
+    ("gain", Category::Dynamics),
+    ("mix", Category::Utilities),
+    ("mux", Category::Utilities),
+    ("demux", Category::Utilities),
+    ("add", Category::Utilities),
+    ("distort", Category::Effects),
+    ("overdrive", Category::Effects),
+    ("biquad", Category::Filters),
+    #[cfg(feature = "gpl_effects")]
+    ("muff", Category::Effects),
+    ("chebyshev", Category::Effects),
+    ("reverb", Category::Effects),
+    ("wave_view", Category::Analysis),
+    ("spectrogram", Category::Analysis),
+    ("signal_gen", Category::Sources),
+    ("low_pass", Category::Filters),
+    ("high_pass", Category::Filters),
+    ("envelope", Category::Dynamics),
+    ("fir", Category::Filters),
+    ("pitch", Category::Analysis),
+    ("correlation", Category::Analysis),
+    ("room_measurement", Category::Analysis),
+    ("parallel", Category::Utilities),
+    ("clock", Category::Sources),
+    ("expression", Category::Effects),
+    ("keyed_gate", Category::Dynamics),
+    #[cfg(feature = "lv2")]
+    ("plugin", Category::Effects),
+    ("map", Category::Utilities),
+    ("tempo", Category::Analysis),
+    ("stereo_reverb", Category::Effects),
+    ("offset", Category::Utilities),
+    ("bus_send", Category::Utilities),
+    ("bus_return", Category::Utilities),
+    ("reverse", Category::Effects),
+    ("stereo_meter", Category::Analysis),
+    ("resonator_bank", Category::Filters),
+    ("channel_combiner", Category::Utilities),
+    ("guitar_chain", Category::Effects),
+    ("compressor", Category::Dynamics),
+    ("delay", Category::Effects),
+    ("pan", Category::Utilities),
+    ("bitcrusher", Category::Effects),
+    ("tremolo", Category::Effects),
+    ("peak_eq", Category::Filters),
+    ("bandpass", Category::Filters),
+    ("notch", Category::Filters),
+    ("noise", Category::Sources),
+    ("adsr", Category::Sources),
+    ("recorder", Category::Utilities),
+    ("file_player", Category::Sources),
+    ("midi_in", Category::Sources),
+    ("feedback", Category::Utilities),
 ];
 
-pub static RESTORE: &[(&str, fn(serde_json::Value) -> Arc<Nodes>)] = &[
-    ("input", |v| Arc::new(Nodes::from(Input::restore(v)))),
-    ("output", |v| Arc::new(Nodes::from(Output::restore(v)))),
-    ("gain", |v| Arc::new(Nodes::from(Gain::restore(v)))),
-    ("mix", |v| Arc::new(Nodes::from(Mix::restore(v)))),
-    ("mux", |v| Arc::new(Nodes::from(Mux::restore(v)))),
-    ("demux", |v| Arc::new(Nodes::from(Demux::restore(v)))),
-    ("add", |v| Arc::new(Nodes::from(Add::restore(v)))),
-    ("distort", |v| Arc::new(Nodes::from(Distort::restore(v)))),
+pub fn category(cfg_name: &str) -> Category {
+    CATEGORIES
+        .iter()
+        .find(|(name, _)| *name == cfg_name)
+        .map_or(Category::Utilities, |(_, category)| *category)
+}
+
+pub static RESTORE: &[(
+    &str,
+    fn(serde_json::Value) -> Result<Arc<Nodes>, serde_json::Error>,
+)] = &[
+    ("input", |v| Ok(Arc::new(Nodes::from(Input::restore(v)?)))),
+    ("output", |v| Ok(Arc::new(Nodes::from(Output::restore(v)?)))),
+    ("gain", |v| Ok(Arc::new(Nodes::from(Gain::restore(v)?)))),
+    ("mix", |v| Ok(Arc::new(Nodes::from(Mix::restore(v)?)))),
+    ("mux", |v| Ok(Arc::new(Nodes::from(Mux::restore(v)?)))),
+    ("demux", |v| Ok(Arc::new(Nodes::from(Demux::restore(v)?)))),
+    ("add", |v| Ok(Arc::new(Nodes::from(Add::restore(v)?)))),
+    ("distort", |v| Ok(Arc::new(Nodes::from(Distort::restore(v)?)))),
     ("overdrive", |v| {
-        Arc::new(Nodes::from(Overdrive::restore(v)))
+        Ok(Arc::new(Nodes::from(Overdrive::restore(v)?)))
     }),
-    ("biquad", |v| Arc::new(Nodes::from(BiQuad::restore(v)))),
+    ("biquad", |v| Ok(Arc::new(Nodes::from(BiQuad::restore(v)?)))),
     #[cfg(feature = "gpl_effects")]
-    ("muff", |v| Arc::new(Nodes::from(Muff::restore(v)))),
+    ("muff", |v| Ok(Arc::new(Nodes::from(Muff::restore(v)?)))),
     ("chebyshev", |v| {
-        Arc::new(Nodes::from(Chebyshev::restore(v)))
+        Ok(Arc::new(Nodes::from(Chebyshev::restore(v)?)))
     }),
-    ("reverb", |v| Arc::new(Nodes::from(Reverb::restore(v)))),
-    ("wave_view", |v| Arc::new(Nodes::from(WaveView::restore(v)))),
+    ("reverb", |v| Ok(Arc::new(Nodes::from(Reverb::restore(v)?)))),
+    ("wave_view", |v| Ok(Arc::new(Nodes::from(WaveView::restore(v)?)))),
     ("spectrogram", |v| {
-        Arc::new(Nodes::from(Spectrogram::restore(v)))
+        Ok(Arc::new(Nodes::from(Spectrogram::restore(v)?)))
     }),
     ("signal_gen", |v| {
-        Arc::new(Nodes::from(SignalGen::restore(v)))
+        Ok(Arc::new(Nodes::from(SignalGen::restore(v)?)))
+    }),
+    ("low_pass", |v| Ok(Arc::new(Nodes::from(LowPass::restore(v)?)))),
+    ("high_pass", |v| Ok(Arc::new(Nodes::from(HighPass::restore(v)?)))),
+    ("envelope", |v| Ok(Arc::new(Nodes::from(Envelope::restore(v)?)))),
+    ("fir", |v| Ok(Arc::new(Nodes::from(Fir::restore(v)?)))),
+    ("pitch", |v| Ok(Arc::new(Nodes::from(Pitch::restore(v)?)))),
+    ("correlation", |v| {
+        Ok(Arc::new(Nodes::from(Correlation::restore(v)?)))
+    }),
+    ("room_measurement", |v| {
+        Ok(Arc::new(Nodes::from(RoomMeasurement::restore(v)?)))
+    }),
+    ("parallel", |v| Ok(Arc::new(Nodes::from(Parallel::restore(v)?)))),
+    ("clock", |v| Ok(Arc::new(Nodes::from(Clock::restore(v)?)))),
+    ("expression", |v| Ok(Arc::new(Nodes::from(Expression::restore(v)?)))),
+    ("keyed_gate", |v| Ok(Arc::new(Nodes::from(KeyedGate::restore(v)?)))),
+    #[cfg(feature = "lv2")]
+    ("plugin", |v| Ok(Arc::new(Nodes::from(Plugin::restore(v)?)))),
+    ("map", |v| Ok(Arc::new(Nodes::from(Map::restore(v)?)))),
+    ("tempo", |v| Ok(Arc::new(Nodes::from(Tempo::restore(v)?)))),
+    ("stereo_reverb", |v| {
+        Ok(Arc::new(Nodes::from(StereoReverb::restore(v)?)))
+    }),
+    ("offset", |v| Ok(Arc::new(Nodes::from(Offset::restore(v)?)))),
+    ("bus_send", |v| Ok(Arc::new(Nodes::from(BusSend::restore(v)?)))),
+    ("bus_return", |v| Ok(Arc::new(Nodes::from(BusReturn::restore(v)?)))),
+    ("reverse", |v| Ok(Arc::new(Nodes::from(Reverse::restore(v)?)))),
+    ("stereo_meter", |v| {
+        Ok(Arc::new(Nodes::from(StereoMeter::restore(v)?)))
+    }),
+    ("resonator_bank", |v| {
+        Ok(Arc::new(Nodes::from(ResonatorBank::restore(v)?)))
+    }),
+    ("channel_combiner", |v| {
+        Ok(Arc::new(Nodes::from(ChannelCombiner::restore(v)?)))
+    }),
+    ("guitar_chain", |v| {
+        Ok(Arc::new(Nodes::from(GuitarChain::restore(v)?)))
     }),
-    ("low_pass", |v| Arc::new(Nodes::from(LowPass::restore(v)))),
-    ("high_pass", |v| Arc::new(Nodes::from(HighPass::restore(v)))),
-    ("envelope", |v| Arc::new(Nodes::from(Envelope::restore(v)))),
-    ("fir", |v| Arc::new(Nodes::from(Fir::restore(v)))),
-    ("pitch", |v| Arc::new(Nodes::from(Pitch::restore(v)))),
+    ("compressor", |v| Ok(Arc::new(Nodes::from(Compressor::restore(v)?)))),
+    ("delay", |v| Ok(Arc::new(Nodes::from(Delay::restore(v)?)))),
+    ("pan", |v| Ok(Arc::new(Nodes::from(Pan::restore(v)?)))),
+    ("bitcrusher", |v| Ok(Arc::new(Nodes::from(Bitcrusher::restore(v)?)))),
+    ("tremolo", |v| Ok(Arc::new(Nodes::from(Tremolo::restore(v)?)))),
+    ("peak_eq", |v| Ok(Arc::new(Nodes::from(PeakEq::restore(v)?)))),
+    ("bandpass", |v| Ok(Arc::new(Nodes::from(BandPass::restore(v)?)))),
+    ("notch", |v| Ok(Arc::new(Nodes::from(Notch::restore(v)?)))),
+    ("noise", |v| Ok(Arc::new(Nodes::from(Noise::restore(v)?)))),
+    ("adsr", |v| Ok(Arc::new(Nodes::from(Adsr::restore(v)?)))),
+    ("recorder", |v| Ok(Arc::new(Nodes::from(Recorder::restore(v)?)))),
+    ("file_player", |v| Ok(Arc::new(Nodes::from(FilePlayer::restore(v)?)))),
+    ("midi_in", |v| Ok(Arc::new(Nodes::from(MidiIn::restore(v)?)))),
+    ("feedback", |v| Ok(Arc::new(Nodes::from(Feedback::restore(v)?)))),
 ];
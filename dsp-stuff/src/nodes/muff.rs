@@ -11,7 +11,8 @@ use dsp_stuff_gpl::muff::*;
     output = "out",
     title = "Muff",
     cfg_name = "muff",
-    description = "Big Muff"
+    description = "Big Muff",
+    reset_state = "Muff::reset"
 )]
 pub struct Muff {
     #[dsp(id)]
@@ -31,6 +32,12 @@ pub struct Muff {
     state: Arc<Mutex<MuffState>>,
 }
 
+impl Muff {
+    fn reset(&self) {
+        *self.state.lock().unwrap() = MuffState::default();
+    }
+}
+
 impl SimpleNode for Muff {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
@@ -0,0 +1,149 @@
+use std::sync::Mutex;
+
+use atomig::Atomic;
+use serde::{Deserialize, Serialize};
+
+use crate::{ids::NodeId, node::*};
+
+use super::signal_gen::xorshift;
+
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+)]
+#[repr(u8)]
+enum Color {
+    /// Equal power at every frequency
+    White,
+    /// Falls off by 3db per octave
+    Pink,
+    /// Falls off by 6db per octave
+    Brown,
+}
+
+/// Number of octaves summed for pink noise
+const PINK_ROWS: usize = 16;
+
+/// How much of the previous brown noise sample is kept, without this the
+/// integrator would wander off
+const BROWN_LEAK: f32 = 0.998;
+const BROWN_STEP: f32 = 0.05;
+
+/// Voss-McCartney pink noise, each row is a white noise source updated half as
+/// often as the one before it
+#[derive(Default)]
+struct Pink {
+    rows: [f32; PINK_ROWS],
+    sum: f32,
+    counter: u32,
+}
+
+impl Pink {
+    fn next(&mut self, state: &mut u32) -> f32 {
+        self.counter = self.counter.wrapping_add(1);
+
+        // the lowest set bit picks which row changes, so row n changes every
+        // 2^(n+1) samples
+        let row = self.counter.trailing_zeros() as usize;
+        if row < PINK_ROWS {
+            let value = white(state);
+            self.sum += value - self.rows[row];
+            self.rows[row] = value;
+        }
+
+        // another white source on top fills in the highest octave
+        (self.sum + white(state)) / (PINK_ROWS + 1) as f32
+    }
+}
+
+fn white(state: &mut u32) -> f32 {
+    *state = xorshift(*state);
+    *state as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    output = "out",
+    title = "Noise",
+    cfg_name = "noise",
+    description = "Generate white, pink or brown noise",
+    reset_state = "Noise::reset"
+)]
+pub struct Noise {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(slider(range = "0.0..=1.0", as_input), save, default = "0.5")]
+    amplitude: Atomic<f32>,
+
+    #[dsp(select, save, default = "Color::White")]
+    color: Atomic<Color>,
+
+    #[dsp(default = "0x2545_f491u32")]
+    noise_state: Atomic<u32>,
+
+    pink: Mutex<Pink>,
+
+    brown: Atomic<f32>,
+}
+
+impl Noise {
+    fn reset(&self) {
+        *self.pink.lock().unwrap() = Pink::default();
+        self.brown.store(0.0, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl SimpleNode for Noise {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut amplitude = [0.0; MAX_BUF_SIZE];
+        self.amplitude_input(&inputs, &mut amplitude);
+        let amplitude = &amplitude[..buf_size()];
+
+        let output = outputs.get("out").unwrap();
+
+        let color = self.color.load(std::sync::atomic::Ordering::Relaxed);
+        let mut state = self.noise_state.load(std::sync::atomic::Ordering::Relaxed);
+
+        match color {
+            Color::White => {
+                for (v, amplitude) in output.iter_mut().zip(amplitude) {
+                    *v = white(&mut state) * amplitude;
+                }
+            }
+            Color::Pink => {
+                let mut pink = self.pink.lock().unwrap();
+
+                for (v, amplitude) in output.iter_mut().zip(amplitude) {
+                    *v = pink.next(&mut state) * amplitude;
+                }
+            }
+            Color::Brown => {
+                let mut brown = self.brown.load(std::sync::atomic::Ordering::Relaxed);
+
+                for (v, amplitude) in output.iter_mut().zip(amplitude) {
+                    brown = (brown * BROWN_LEAK + white(&mut state) * BROWN_STEP).clamp(-1.0, 1.0);
+                    *v = brown * amplitude;
+                }
+
+                self.brown
+                    .store(brown, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        self.noise_state
+            .store(state, std::sync::atomic::Ordering::Relaxed);
+    }
+}
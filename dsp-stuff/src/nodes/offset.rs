@@ -0,0 +1,40 @@
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use collect_slice::CollectSlice;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Offset",
+    cfg_name = "offset",
+    description = "Add a constant to a signal, for biasing modulation into range"
+)]
+pub struct Offset {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(slider(range = "-1.0..=1.0", as_input), save, default = "0.0")]
+    offset: Atomic<f32>,
+}
+
+impl SimpleNode for Offset {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut offset = [0.0; MAX_BUF_SIZE];
+        self.offset_input(&inputs, &mut offset);
+        let offset = &offset[..buf_size()];
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        input
+            .iter()
+            .zip(offset)
+            .map(|(x, offset)| x + offset)
+            .collect_slice(output);
+    }
+}
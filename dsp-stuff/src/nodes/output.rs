@@ -1,24 +1,167 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use eframe::egui;
+use super::{
+    input::channel_pair_select,
+    stereo_meter::{bar_position, level_colour, to_dbfs, PeakHold},
+};
 use crate::{
     devices,
     ids::{DeviceId, NodeId, PortId},
     node::*,
 };
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
+use atomig::Atomic;
 use rivulet::{circular_buffer::Sink, View, ViewMut};
+use strum::IntoEnumIterator;
 use tokio::sync::Mutex;
 
+/// A second of -12dBFS 440hz sine, for checking a device works
+#[derive(Default)]
+struct TestTone {
+    /// Samples left to play
+    remaining: Atomic<usize>,
+    phase: Atomic<f32>,
+
+    /// Set once the node itself is producing audio, at which point it plays
+    /// the rest of the tone alongside whatever arrives from the graph
+    from_graph: AtomicBool,
+}
+
+impl TestTone {
+    /// Length of the tone in seconds
+    const LENGTH: f32 = 1.0;
+    const AMPLITUDE: f32 = 0.251;
+    const FREQUENCY: f32 = 440.0;
+
+    fn start(&self) {
+        self.phase.store(0.0, std::sync::atomic::Ordering::Relaxed);
+        self.from_graph
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        self.remaining.store(
+            (Self::LENGTH * devices::sample_rate() as f32) as usize,
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
+    fn playing(&self) -> bool {
+        self.remaining.load(std::sync::atomic::Ordering::Relaxed) > 0
+    }
+
+    /// Mix the next part of the tone into `buf`
+    fn fill(&self, buf: &mut [f32]) {
+        let remaining = self.remaining.load(std::sync::atomic::Ordering::Relaxed);
+        let len = remaining.min(buf.len());
+        let mut phase = self.phase.load(std::sync::atomic::Ordering::Relaxed);
+        let step = Self::FREQUENCY / devices::sample_rate() as f32;
+
+        for v in &mut buf[..len] {
+            *v += (phase * std::f32::consts::TAU).sin() * Self::AMPLITUDE;
+            phase = (phase + step).fract();
+        }
+
+        self.phase
+            .store(phase, std::sync::atomic::Ordering::Relaxed);
+        self.remaining
+            .store(remaining - len, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// How fast the meter falls back, in dB per second
+const METER_FALL: f32 = 20.0;
+
+/// Seconds the peak marker stays put before falling with the meter
+const PEAK_HOLD: f32 = 1.0;
+
+/// Ports every output has, `in` goes to both channels and the others to a
+/// channel each, a mono device gets them all mixed together
+const PORTS: [&str; 3] = ["in", "in_l", "in_r"];
+
+/// Write a block to a device, interleaving the channels if it's stereo
+///
+/// Returns false if the device has gone away.
+async fn write_block(sink: &mut Sink<f32>, channels: usize, left: &[f32], right: &[f32]) -> bool {
+    let len = left.len() * channels;
+
+    if sink.grant(len).await.is_err() {
+        return false;
+    }
+
+    let view = &mut sink.view_mut()[..len];
+    if channels == 2 {
+        for ((frame, l), r) in view.chunks_exact_mut(2).zip(left).zip(right) {
+            frame[0] = *l;
+            frame[1] = *r;
+        }
+    } else {
+        for ((v, l), r) in view.iter_mut().zip(left).zip(right) {
+            *v = 0.5 * (l + r);
+        }
+    }
+
+    sink.release(len);
+
+    true
+}
+
 pub struct Output {
     id: NodeId,
     inputs: PortStorage,
     outputs: PortStorage,
     sink: Arc<Mutex<Option<Sink<f32>>>>,
+    /// Channels of the open device, interleaved in `sink`
+    channels: Arc<AtomicUsize>,
+    /// Channels the device has, if there's more than two only a pair of them
+    /// starting at the controls' `first_channel` are written to
+    device_channels: AtomicUsize,
+    /// Shared with the device's stream, changes apply without reopening it
+    controls: Arc<devices::OutputControls>,
+    /// Changing this reopens the device, as the stream's resamplers are made
+    /// when it's opened
+    resampler_quality: Atomic<devices::ResamplerQuality>,
 
     cached_hosts: ArcSwap<Vec<cpal::HostId>>,
     selected_host: ArcSwap<cpal::HostId>,
     cached_devices: ArcSwap<Vec<String>>,
     selected_device: ArcSwap<Option<(String, DeviceId)>>,
+    /// A device that was asked for but couldn't be opened, such as one named
+    /// in a config saved on another machine, kept so it's saved and retried
+    missing_device: ArcSwap<Option<String>>,
+
+    stats: ArcSwapOption<devices::OutputStats>,
+    /// Frames received from the graph, compared between renders to tell if
+    /// anything is arriving
+    frames: Atomic<u64>,
+    last_rendered_frames: Atomic<u64>,
+
+    /// Gain applied to the output, ramped towards `gain_target` to fade
+    /// around device switches
+    gain: Atomic<f32>,
+    gain_target: Atomic<f32>,
+    /// A device switch waiting for the old device to fade out
+    pending_switch: std::sync::Mutex<Option<PendingSwitch>>,
+
+    test_tone: Arc<TestTone>,
+
+    /// Write silence instead of the graph's audio
+    muted: Atomic<bool>,
+    /// Whether this is one of the outputs that stay audible while soloing,
+    /// the device thread does the actual muting of the others
+    soloed: Atomic<bool>,
+
+    /// Levels of what's sent to the device, falling at [`METER_FALL`]
+    meter_peak: Atomic<f32>,
+    meter_rms: Atomic<f32>,
+    /// The loudest recent peak, and how many samples until it's let go of
+    held_peak: Atomic<f32>,
+    hold_left: Atomic<usize>,
+    peak_hold: PeakHold,
 }
 
 impl Drop for Output {
@@ -35,10 +178,110 @@ struct OutputConfig {
     selected_host: String,
     selected_device: Option<String>,
     inputs: HashMap<String, PortId>,
+    #[serde(default)]
+    muted: bool,
+    #[serde(default)]
+    first_channel: usize,
+    #[serde(default = "default_target_latency")]
+    target_latency_ms: u32,
+    #[serde(default)]
+    resampler_quality: devices::ResamplerQuality,
+}
+
+fn default_target_latency() -> u32 {
+    devices::DEFAULT_TARGET_LATENCY_MS
+}
+
+/// The device to switch to once the current one has faded out
+struct PendingSwitch {
+    host: cpal::HostId,
+    name: Option<String>,
+    /// When to give up waiting on the fade, the graph might not be running
+    /// (frozen, or nothing connected)
+    deadline: Instant,
+    /// Set once faded, to let the end of the fade that's still queued up for
+    /// the device play
+    drained_at: Option<Instant>,
 }
 
 impl Output {
+    /// Switch device once the fade out started by [`Output::load_device`] has
+    /// been played, called every frame so the ui never waits on it
+    fn poll_pending_switch(&self, ui: &egui::Ui) {
+        let mut pending = self.pending_switch.lock().unwrap();
+        let Some(switch) = pending.as_mut() else {
+            return;
+        };
+
+        let now = Instant::now();
+        let faded = self.gain.load(std::sync::atomic::Ordering::Relaxed) <= 0.0;
+
+        if switch.drained_at.is_none() && (faded || now >= switch.deadline) {
+            let queued = self.stats.load().as_ref().map_or(0, |stats| {
+                stats.queued.load(std::sync::atomic::Ordering::Relaxed)
+            });
+            let queued = queued as f32 / devices::sample_rate() as f32;
+            switch.drained_at = Some(now + Duration::from_secs_f32(queued.min(0.1)));
+        }
+
+        if switch.drained_at.is_some_and(|at| now >= at) {
+            let switch = pending.take().unwrap();
+            drop(pending);
+
+            self.switch_device(switch.host, switch.name);
+        } else {
+            ui.ctx().request_repaint_after(Duration::from_millis(5));
+        }
+    }
+
+    /// Open the missing device if it's turned up in the device list
+    fn retry_missing_device(&self) {
+        let Some(missing) = Option::clone(&self.missing_device.load()) else {
+            return;
+        };
+
+        if self.cached_devices.load().contains(&missing) {
+            self.load_device(**self.selected_host.load(), Some(missing));
+        }
+    }
+
+    /// Switch to a device, fading out the current one first so it isn't cut
+    /// off mid-signal
+    ///
+    /// The fade happens on the audio thread, the switch itself is made by
+    /// [`Output::poll_pending_switch`] once it's done.
     fn load_device(&self, host: cpal::HostId, name: Option<String>) {
+        let fade = devices::switch_fade_samples();
+        if self.selected_device.load().is_none() || fade == 0 {
+            *self.pending_switch.lock().unwrap() = None;
+            self.switch_device(host, name);
+            return;
+        }
+
+        self.gain_target
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
+
+        let mut pending = self.pending_switch.lock().unwrap();
+        let deadline = pending.as_ref().map_or_else(
+            || {
+                Instant::now()
+                    + Duration::from_secs_f32(fade as f32 / devices::sample_rate() as f32)
+                    + Duration::from_millis(100)
+            },
+            |p| p.deadline,
+        );
+        let drained_at = pending.as_ref().and_then(|p| p.drained_at);
+
+        // asking for another device before the switch keeps the same fade
+        *pending = Some(PendingSwitch {
+            host,
+            name,
+            deadline,
+            drained_at,
+        });
+    }
+
+    fn switch_device(&self, host: cpal::HostId, name: Option<String>) {
         let mut sink = self.sink.blocking_lock();
 
         let (_current_device, current_device_id) = self
@@ -52,25 +295,231 @@ impl Output {
             devices::invoke(devices::DeviceCommand::CloseDevice(id));
         }
 
+        self.missing_device.store(Arc::new(None));
+
         if let Some(dev) = name {
-            if let Some((id, new_sink)) =
-                devices::invoke(devices::DeviceCommand::OpenOutput(host, dev.clone()))
-                    .output_opened()
-                    .unwrap()
+            if let Some((id, new_sink, stats, channels, device_channels)) =
+                devices::invoke(devices::DeviceCommand::OpenOutput(
+                    host,
+                    dev.clone(),
+                    Arc::clone(&self.controls),
+                    self.resampler_quality
+                        .load(std::sync::atomic::Ordering::Relaxed),
+                ))
+                .output_opened()
+                .unwrap()
             {
                 self.selected_device.store(Arc::new(Some((dev, id))));
+                self.stats.store(Some(stats));
+
+                // the solo belongs to the node, so it follows it to the new device
+                if self.soloed.load(std::sync::atomic::Ordering::Relaxed) {
+                    devices::invoke(devices::DeviceCommand::SetSolo(id, true));
+                }
+                self.channels
+                    .store(channels, std::sync::atomic::Ordering::Relaxed);
+                self.device_channels
+                    .store(device_channels, std::sync::atomic::Ordering::Relaxed);
                 *sink = Some(new_sink);
             } else {
+                self.missing_device.store(Arc::new(Some(dev)));
                 self.selected_device.store(Arc::new(None));
+                self.stats.store(None);
                 *sink = None;
             }
         } else {
             self.selected_device.store(Arc::new(None));
+            self.stats.store(None);
             *sink = None;
         }
 
+        // fade in on the new device
+        self.gain.store(0.0, std::sync::atomic::Ordering::Relaxed);
+        self.gain_target
+            .store(1.0, std::sync::atomic::Ordering::Relaxed);
+
         devices::invoke(devices::DeviceCommand::TriggerResync);
     }
+
+    fn play_test_tone(&self) {
+        self.test_tone.start();
+
+        // if the graph is feeding this node the tone is mixed in as it goes,
+        // otherwise nothing else is writing to the sink so play it directly
+        let frames = self.frames.load(std::sync::atomic::Ordering::Relaxed);
+        if frames
+            != self
+                .last_rendered_frames
+                .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return;
+        }
+
+        let tone = Arc::clone(&self.test_tone);
+        let sink = Arc::clone(&self.sink);
+        let channels = Arc::clone(&self.channels);
+
+        tokio::spawn(async move {
+            while tone.playing() && !tone.from_graph.load(std::sync::atomic::Ordering::Relaxed) {
+                let mut sink = sink.lock().await;
+                let Some(sink) = sink.as_mut() else {
+                    break;
+                };
+
+                let mut buf = [0.0; MAX_BUF_SIZE];
+                let buf = &mut buf[..buf_size()];
+                tone.fill(buf);
+
+                let channels = channels.load(std::sync::atomic::Ordering::Relaxed);
+                // the device went away
+                if !write_block(sink, channels, buf, buf).await {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn set_solo(&self, solo: bool) {
+        self.soloed
+            .store(solo, std::sync::atomic::Ordering::Relaxed);
+
+        if let Some((_, device)) = self.selected_device.load().as_ref() {
+            devices::invoke(devices::DeviceCommand::SetSolo(*device, solo));
+        }
+    }
+
+    /// Update the meter with a block about to be sent to the device
+    fn meter_block(&self, left: &[f32], right: &[f32]) {
+        let len = left.len() + right.len();
+        let (peak, squares) = left
+            .iter()
+            .chain(right)
+            .fold((0.0f32, 0.0f32), |(p, s), x| (p.max(x.abs()), s + x * x));
+        let rms = (squares / len.max(1) as f32).sqrt();
+
+        self.peak_hold.update(left);
+        self.peak_hold.update(right);
+
+        let sample_rate = devices::sample_rate() as f32;
+        let fall = 10.0f32.powf(-METER_FALL / 20.0 * left.len() as f32 / sample_rate);
+
+        let peak = peak.max(self.meter_peak.load(std::sync::atomic::Ordering::Relaxed) * fall);
+        let rms = rms.max(self.meter_rms.load(std::sync::atomic::Ordering::Relaxed) * fall);
+        self.meter_peak
+            .store(peak, std::sync::atomic::Ordering::Relaxed);
+        self.meter_rms
+            .store(rms, std::sync::atomic::Ordering::Relaxed);
+
+        let held = self.held_peak.load(std::sync::atomic::Ordering::Relaxed);
+        let hold_left = self.hold_left.load(std::sync::atomic::Ordering::Relaxed);
+        if peak >= held || hold_left == 0 {
+            self.held_peak
+                .store(peak, std::sync::atomic::Ordering::Relaxed);
+            self.hold_left.store(
+                (PEAK_HOLD * sample_rate) as usize,
+                std::sync::atomic::Ordering::Relaxed,
+            );
+        } else {
+            self.hold_left.store(
+                hold_left.saturating_sub(left.len()),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+        }
+    }
+
+    fn render_meter(&self, ui: &mut egui::Ui) {
+        ui.ctx().request_repaint_after(Duration::from_millis(30));
+
+        let peak = self.meter_peak.load(std::sync::atomic::Ordering::Relaxed);
+        let rms = self.meter_rms.load(std::sync::atomic::Ordering::Relaxed);
+        let held = self.held_peak.load(std::sync::atomic::Ordering::Relaxed);
+
+        ui.horizontal(|ui| {
+            let (_id, rect) = ui.allocate_space(egui::vec2(16.0, 80.0));
+
+            let height = |level: f32| rect.bottom() - bar_position(level) * rect.height();
+
+            let painter = ui.painter();
+            painter.rect_filled(rect, egui::Rounding::ZERO, egui::Color32::from_gray(32));
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(rect.x_range(), height(peak)..=rect.bottom()),
+                egui::Rounding::ZERO,
+                level_colour(peak).gamma_multiply(0.5),
+            );
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(rect.x_range(), height(rms)..=rect.bottom()),
+                egui::Rounding::ZERO,
+                level_colour(rms),
+            );
+
+            let y = height(held);
+            painter.line_segment(
+                [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                (2.0, level_colour(held)),
+            );
+
+            ui.vertical(|ui| {
+                let db = |level: f32| {
+                    let db = to_dbfs(level);
+                    if db.is_finite() {
+                        format!("{db:>5.1}")
+                    } else {
+                        " -inf".to_owned()
+                    }
+                };
+
+                ui.label(
+                    egui::RichText::new(format!("pk  {}", db(held)))
+                        .family(egui::FontFamily::Monospace)
+                        .color(level_colour(held)),
+                );
+                ui.label(
+                    egui::RichText::new(format!("rms {}", db(rms)))
+                        .family(egui::FontFamily::Monospace),
+                );
+            });
+        });
+
+        self.peak_hold.render(ui);
+    }
+
+    fn render_stats(&self, ui: &mut egui::Ui) {
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+
+        let frames = self.frames.load(std::sync::atomic::Ordering::Relaxed);
+        let receiving = self
+            .last_rendered_frames
+            .swap(frames, std::sync::atomic::Ordering::Relaxed)
+            != frames;
+
+        ui.label(if receiving {
+            "Receiving audio"
+        } else {
+            "Nothing arriving from the graph"
+        });
+
+        let stats = self.stats.load();
+        let Some(stats) = stats.as_ref() else {
+            ui.label("No device open");
+            return;
+        };
+
+        let queued = stats.queued.load(std::sync::atomic::Ordering::Relaxed);
+        ui.label(format!(
+            "Buffered: {queued} samples ({:.1} ms)",
+            queued as f32 * 1000.0 / devices::sample_rate() as f32
+        ));
+
+        ui.label(format!(
+            "Underruns: {}",
+            stats.underruns.load(std::sync::atomic::Ordering::Relaxed)
+        ))
+        .on_hover_text_at_pointer(
+            "Callbacks the device asked for audio the graph hadn't produced yet, silence \
+             was played instead. These come from the graph running too slowly, not from \
+             the device.",
+        );
+    }
 }
 
 impl Node for Output {
@@ -103,8 +552,21 @@ impl Node for Output {
             id: self.id,
             selected_host: self.selected_host.load().name().to_owned(),
             selected_device: Option::as_ref(&self.selected_device.load())
-                .map(|(n, _)| n.to_owned()),
+                .map(|(n, _)| n.to_owned())
+                .or_else(|| Option::clone(&self.missing_device.load())),
             inputs: self.inputs.get_all(),
+            muted: self.muted.load(std::sync::atomic::Ordering::Relaxed),
+            first_channel: self
+                .controls
+                .first_channel
+                .load(std::sync::atomic::Ordering::Relaxed),
+            target_latency_ms: self
+                .controls
+                .target_latency_ms
+                .load(std::sync::atomic::Ordering::Relaxed),
+            resampler_quality: self
+                .resampler_quality
+                .load(std::sync::atomic::Ordering::Relaxed),
         };
 
         serde_json::to_value(cfg).unwrap()
@@ -112,6 +574,8 @@ impl Node for Output {
 
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn render(&self, ui: &mut egui::Ui) {
+        self.poll_pending_switch(ui);
+
         let current_host = **self.selected_host.load();
         let mut selected_host = current_host;
 
@@ -130,6 +594,7 @@ impl Node for Output {
                 .unwrap();
 
             self.cached_devices.store(Arc::new(devices));
+            self.retry_missing_device();
         }
 
         let (current_device, _current_device_id) = self
@@ -159,6 +624,124 @@ impl Node for Output {
         if current_device != selected_device {
             self.load_device(selected_host, selected_device);
         }
+
+        if let Some(missing) = self.missing_device.load().as_ref() {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(format!("Device not found: {missing}"))
+                        .color(ui.visuals().error_fg_color),
+                );
+
+                if ui
+                    .button("Retry")
+                    .on_hover_text_at_pointer("Look for the device again")
+                    .clicked()
+                {
+                    let host = **self.selected_host.load();
+                    let devices = devices::invoke(devices::DeviceCommand::ListOutputs(host))
+                        .devices()
+                        .unwrap();
+                    self.cached_devices.store(Arc::new(devices));
+                    self.retry_missing_device();
+                }
+            });
+        }
+
+        let device_channels = self
+            .device_channels
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if current_device.is_some() && device_channels > 2 {
+            channel_pair_select(ui, self.id, &self.controls.first_channel, device_channels);
+        }
+
+        let current_quality = self
+            .resampler_quality
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let mut quality = current_quality;
+
+        egui::ComboBox::new(("resampler_quality", self.id), "Resampler")
+            .selected_text(<&'static str>::from(quality))
+            .show_ui(ui, |ui| {
+                for q in devices::ResamplerQuality::iter() {
+                    ui.selectable_value(&mut quality, q, <&'static str>::from(q));
+                }
+            })
+            .response
+            .on_hover_text_at_pointer(
+                "How the graph's audio is converted to the device's sample rate. Linear is \
+                 cheapest but lets aliasing through, more sinc taps filter more cleanly at \
+                 a higher cost. Changing it reopens the device.",
+            );
+
+        if quality != current_quality {
+            self.resampler_quality
+                .store(quality, std::sync::atomic::Ordering::Relaxed);
+
+            let device = Option::as_ref(&self.selected_device.load()).map(|(n, _)| n.to_owned());
+            if device.is_some() {
+                self.load_device(**self.selected_host.load(), device);
+            }
+        }
+
+        let mut target_latency = self
+            .controls
+            .target_latency_ms
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if ui
+            .add(
+                egui::Slider::new(&mut target_latency, 1..=500)
+                    .logarithmic(true)
+                    .text("Target latency")
+                    .suffix(" ms"),
+            )
+            .on_hover_text_at_pointer(
+                "How much audio may be left queued for the device after a resync. Lower \
+                 values keep latency down, but can run the device dry and glitch with \
+                 small device buffers.",
+            )
+            .changed()
+        {
+            self.controls
+                .target_latency_ms
+                .store(target_latency, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        let playing = self.test_tone.playing();
+        if ui
+            .add_enabled(!playing, egui::Button::new("Test tone"))
+            .on_hover_text_at_pointer("Play a second of sine to check the device works")
+            .clicked()
+        {
+            self.play_test_tone();
+        }
+
+        if playing {
+            ui.ctx().request_repaint();
+        }
+
+        ui.horizontal(|ui| {
+            let muted = self.muted.load(std::sync::atomic::Ordering::Relaxed);
+            if ui
+                .selectable_label(muted, "Mute")
+                .on_hover_text_at_pointer("Send silence to the device")
+                .clicked()
+            {
+                self.muted
+                    .store(!muted, std::sync::atomic::Ordering::Relaxed);
+            }
+
+            let soloed = self.soloed.load(std::sync::atomic::Ordering::Relaxed);
+            if ui
+                .selectable_label(soloed, "Solo")
+                .on_hover_text_at_pointer("Silence every output that isn't soloed")
+                .clicked()
+            {
+                self.set_solo(!soloed);
+            }
+        });
+
+        self.render_meter(ui);
+        self.render_stats(ui);
     }
 }
 
@@ -167,35 +750,75 @@ impl NodeStatic for Output {
         let hosts = devices::invoke(devices::DeviceCommand::ListHosts)
             .hosts()
             .unwrap();
-        let selected_host = *hosts.first().expect("There are no audio hosts available");
+        // with no hosts at all the node is left with nothing to pick from
+        let selected_host = hosts
+            .first()
+            .copied()
+            .unwrap_or_else(|| cpal::default_host().id());
         let devices = devices::invoke(devices::DeviceCommand::ListOutputs(selected_host))
             .devices()
             .unwrap();
 
         let inputs = PortStorage::default();
-        inputs.add("in".to_owned());
+        for port in PORTS {
+            inputs.add(port.to_owned());
+        }
 
         Self {
             id,
             inputs,
             outputs: Default::default(),
             sink: Arc::new(Mutex::new(None)),
+            channels: Arc::new(AtomicUsize::new(1)),
+            device_channels: AtomicUsize::new(1),
+            controls: Arc::default(),
+            resampler_quality: Atomic::new(devices::ResamplerQuality::default()),
 
             cached_hosts: ArcSwap::new(Arc::new(hosts)),
             selected_host: ArcSwap::new(Arc::new(selected_host)),
 
             cached_devices: ArcSwap::new(Arc::new(devices)),
             selected_device: ArcSwap::new(Arc::new(None)),
+            missing_device: ArcSwap::new(Arc::new(None)),
+
+            stats: ArcSwapOption::empty(),
+            frames: Atomic::new(0),
+            last_rendered_frames: Atomic::new(0),
+
+            gain: Atomic::new(1.0),
+            gain_target: Atomic::new(1.0),
+            pending_switch: std::sync::Mutex::new(None),
+
+            test_tone: Arc::default(),
+
+            muted: Atomic::new(false),
+            soloed: Atomic::new(false),
+
+            meter_peak: Atomic::new(0.0),
+            meter_rms: Atomic::new(0.0),
+            held_peak: Atomic::new(0.0),
+            hold_left: Atomic::new(0),
+            peak_hold: PeakHold::default(),
         }
     }
 
-    fn restore(value: serde_json::Value) -> Self
+    fn restore(value: serde_json::Value) -> Result<Self, serde_json::Error>
     where
         Self: Sized,
     {
-        let cfg: OutputConfig = serde_json::from_value(value).unwrap();
+        let cfg: OutputConfig = serde_json::from_value(value)?;
 
         let mut this = Self::new(cfg.id);
+        this.muted
+            .store(cfg.muted, std::sync::atomic::Ordering::Relaxed);
+        this.controls
+            .first_channel
+            .store(cfg.first_channel, std::sync::atomic::Ordering::Relaxed);
+        this.controls
+            .target_latency_ms
+            .store(cfg.target_latency_ms, std::sync::atomic::Ordering::Relaxed);
+        this.resampler_quality
+            .store(cfg.resampler_quality, std::sync::atomic::Ordering::Relaxed);
 
         if let Some(host) = devices::invoke(devices::DeviceCommand::ListHosts)
             .hosts()
@@ -204,45 +827,103 @@ impl NodeStatic for Output {
             .find(|x| x.name() == cfg.selected_host)
         {
             this.load_device(host, cfg.selected_device);
-        };
+        } else {
+            this.missing_device.store(Arc::new(cfg.selected_device));
+        }
 
         this.inputs = PortStorage::new(cfg.inputs);
+        // saved before outputs were stereo
+        for port in PORTS {
+            if this.inputs.get_id(port).is_none() {
+                this.inputs.add(port.to_owned());
+            }
+        }
 
-        this
+        Ok(this)
     }
 }
 
 impl Perform for Output {
     // #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     async fn perform(&self, inputs: NodeInputs<'_, '_, '_>, _outputs: NodeOutputs<'_, '_, '_>) {
-        const BUF_SIZE: usize = 128;
-        let mut buf = [0.0; BUF_SIZE];
+        let buf_size = buf_size();
+        let mut blocks = [[0.0; MAX_BUF_SIZE]; 3];
+        let mut received = false;
 
-        let collected_inputs = &mut inputs[self.inputs.get_idx("in").unwrap()];
+        for (port, block) in PORTS.iter().zip(blocks.iter_mut()) {
+            let block = &mut block[..buf_size];
+            let idx = self.inputs.get_idx(port).unwrap();
 
-        collect_and_average(&mut buf, collected_inputs).await;
+            if collect_and_average(block, &mut inputs[idx], input_mixing()).await {
+                self.inputs.record_level(idx, block);
+                received = true;
+            }
+        }
 
-        let mut sink = self.sink.lock().await;
+        if received {
+            self.frames
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
 
-        if let Some(sink) = sink.as_mut() {
-            // tracing::debug!(merged = merged.len(), "Done a collection");
+        let [both, mut left, mut right] = blocks;
+        let (left, right) = (&mut left[..buf_size], &mut right[..buf_size]);
+        for ((l, r), x) in left.iter_mut().zip(right.iter_mut()).zip(both) {
+            *l += x;
+            *r += x;
+        }
+
+        if self.muted.load(std::sync::atomic::Ordering::Relaxed) {
+            left.fill(0.0);
+            right.fill(0.0);
+        }
 
-            sink.grant(BUF_SIZE).await.unwrap();
+        if self.test_tone.playing() {
+            self.test_tone
+                .from_graph
+                .store(true, std::sync::atomic::Ordering::Relaxed);
 
-            // tracing::debug!(sink_view = sink.view_mut().len(), "Got a grant");
+            let mut tone = [0.0; MAX_BUF_SIZE];
+            self.test_tone.fill(&mut tone[..buf_size]);
+            for ((l, r), x) in left.iter_mut().zip(right.iter_mut()).zip(tone) {
+                *l += x;
+                *r += x;
+            }
+        }
+
+        self.meter_block(left, right);
+
+        let mut sink = self.sink.lock().await;
 
-            sink.view_mut()[..BUF_SIZE].copy_from_slice(&buf);
+        if let Some(sink) = sink.as_mut() {
+            let target = self.gain_target.load(std::sync::atomic::Ordering::Relaxed);
+            let mut gain = self.gain.load(std::sync::atomic::Ordering::Relaxed);
+
+            if gain != 1.0 || target != 1.0 {
+                let step = 1.0 / devices::switch_fade_samples().max(1) as f32;
+
+                for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                    gain = if gain < target {
+                        (gain + step).min(target)
+                    } else {
+                        (gain - step).max(target)
+                    };
+                    *l *= gain;
+                    *r *= gain;
+                }
+
+                self.gain.store(gain, std::sync::atomic::Ordering::Relaxed);
+            }
 
-            // tracing::debug!("Releasing sink");
-            sink.release(BUF_SIZE);
+            let channels = self.channels.load(std::sync::atomic::Ordering::Relaxed);
+            write_block(sink, channels, left, right).await;
 
             // tracing::debug!("Releasing inputs");
             for input_port in inputs.iter_mut() {
                 for input_pipe in input_port.iter_mut() {
-                    if input_pipe.view().len() < BUF_SIZE {
+                    if input_pipe.view().len() < buf_size {
                         continue;
                     }
-                    input_pipe.release(BUF_SIZE);
+                    input_pipe.release(buf_size);
                 }
             }
         }
@@ -58,9 +58,9 @@ fn apply(
 impl SimpleNode for Overdrive {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
-        let mut boost = [0.0; BUF_SIZE];
-        let mut level = [0.0; BUF_SIZE];
-        let mut drive = [0.0; BUF_SIZE];
+        let mut boost = [0.0; MAX_BUF_SIZE];
+        let mut level = [0.0; MAX_BUF_SIZE];
+        let mut drive = [0.0; MAX_BUF_SIZE];
         self.boost_input(&inputs, &mut boost);
         self.level_input(&inputs, &mut level);
         self.drive_input(&inputs, &mut drive);
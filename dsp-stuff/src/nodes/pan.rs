@@ -0,0 +1,54 @@
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out_l",
+    output = "out_r",
+    title = "Pan",
+    cfg_name = "pan",
+    description = "Place a mono signal between the left and right channels"
+)]
+pub struct Pan {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// -1 is hard left, 1 hard right
+    #[dsp(slider(range = "-1.0..=1.0", as_input), save, default = "0.0")]
+    pan: Atomic<f32>,
+}
+
+impl SimpleNode for Pan {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut pan = [0.0; MAX_BUF_SIZE];
+        self.pan_input(&inputs, &mut pan);
+        let pan = &pan[..buf_size()];
+        let input = inputs.get("in").unwrap();
+
+        let mut left = [0.0; MAX_BUF_SIZE];
+        let mut right = [0.0; MAX_BUF_SIZE];
+
+        for (i, (x, pan)) in input.iter().zip(pan).enumerate() {
+            // equal power, so the signal doesn't dip in loudness as it passes
+            // through the centre
+            let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+            left[i] = x * angle.cos();
+            right[i] = x * angle.sin();
+        }
+
+        outputs
+            .get("out_l")
+            .unwrap()
+            .copy_from_slice(&left[..input.len()]);
+        outputs
+            .get("out_r")
+            .unwrap()
+            .copy_from_slice(&right[..input.len()]);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use collect_slice::CollectSlice;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    input = "return",
+    output = "send",
+    output = "out",
+    title = "Parallel",
+    cfg_name = "parallel",
+    description = "Send a copy of a signal off for processing and blend the return back in with the dry signal"
+)]
+pub struct Parallel {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "0.0..=1.0", as_input),
+        label = "Blend (return)",
+        save,
+        default = "0.5"
+    )]
+    blend: Atomic<f32>,
+}
+
+impl SimpleNode for Parallel {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut blend = [0.0; MAX_BUF_SIZE];
+        self.blend_input(&inputs, &mut blend);
+        let blend = &blend[..buf_size()];
+
+        let input = inputs.get("in").unwrap();
+
+        outputs.get("send").unwrap().copy_from_slice(input);
+
+        let output = outputs.get("out").unwrap();
+
+        // with nothing plugged into the return there's nothing to blend in
+        let Some(ret) = inputs.get_checked("return") else {
+            output.copy_from_slice(input);
+            return;
+        };
+
+        input
+            .iter()
+            .zip(ret)
+            .zip(blend)
+            .map(|((dry, ret), blend)| dry + ret * blend)
+            .collect_slice(output);
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use biquad::{Biquad as _, DirectForm1, ToHertz};
+use collect_slice::CollectSlice;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Peak EQ",
+    cfg_name = "peak_eq",
+    description = "Boost or cut a band of frequencies around a centre frequency",
+    after_settings_change = "PeakEq::regenerate",
+    reset_state = "PeakEq::reset"
+)]
+pub struct PeakEq {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "20.0..=20000.0", logarithmic, suffix = " hz"),
+        save,
+        default = "1000.0"
+    )]
+    frequency: Atomic<f32>,
+
+    #[dsp(slider(range = "-24.0..=24.0", suffix = " db"), save, default = "0.0")]
+    gain: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.1..=10.0", logarithmic),
+        label = "Q",
+        tooltip = "How narrow the band is, higher values affect fewer frequencies",
+        save,
+        default = "0.707"
+    )]
+    q: Atomic<f32>,
+
+    /// Starts out flat, which is what the default 0db gain gives
+    #[dsp(default = "Arc::new(Mutex::new(DirectForm1::new(PeakEq::FLAT)))")]
+    filter: Arc<Mutex<biquad::DirectForm1<f32>>>,
+}
+
+impl PeakEq {
+    const FLAT: biquad::Coefficients<f32> = biquad::Coefficients {
+        a1: 0.0,
+        a2: 0.0,
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+    };
+
+    fn reset(&self) {
+        self.filter.lock().unwrap().reset_state();
+    }
+
+    fn regenerate(&self) {
+        let gain = self.gain.load(atomig::Ordering::Relaxed);
+
+        let coeffs = match biquad::Coefficients::<f32>::from_params(
+            biquad::Type::PeakingEQ(gain),
+            (crate::devices::sample_rate() as f32).hz(),
+            self.frequency.load(atomig::Ordering::Relaxed).hz(),
+            self.q.load(atomig::Ordering::Relaxed),
+        ) {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!("Couldn't compute peak eq coefficients: {:?}", e);
+                return;
+            }
+        };
+
+        // the state is kept so sweeping the controls doesn't click
+        self.filter.lock().unwrap().update_coefficients(coeffs);
+    }
+}
+
+impl SimpleNode for PeakEq {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let mut filter = self.filter.lock().unwrap();
+
+        input.iter().map(|x| filter.run(*x)).collect_slice(output);
+    }
+}
@@ -127,9 +127,13 @@ impl SimpleNode for Pitch {
             let clarity_thresh = self.clarity_thresh.load(atomig::Ordering::Relaxed);
             let pick_thresh = self.pick_thresh.load(atomig::Ordering::Relaxed);
 
-            if let Some(pitch_detection::Pitch { frequency, clarity }) =
-                detector.get_pitch(view, 48_000, power_thresh, clarity_thresh, pick_thresh)
-            {
+            if let Some(pitch_detection::Pitch { frequency, clarity }) = detector.get_pitch(
+                view,
+                crate::devices::sample_rate() as usize,
+                power_thresh,
+                clarity_thresh,
+                pick_thresh,
+            ) {
                 self.pitch.store(frequency, atomig::Ordering::Relaxed);
                 self.clarity.store(clarity, atomig::Ordering::Relaxed);
             }
@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use arc_swap::ArcSwap;
+use atomig::Atomic;
+use eframe::egui;
+use egui::{RichText, Ui};
+use once_cell::sync::Lazy;
+
+use crate::{ids::NodeId, node::*};
+
+/// Every LV2 plugin installed, scanning them is slow so it's only done once
+static WORLD: Lazy<livi::World> = Lazy::new(livi::World::new);
+
+static FEATURES: Lazy<Arc<livi::Features>> = Lazy::new(|| {
+    WORLD.build_features(livi::FeaturesBuilder {
+        min_block_length: MIN_BUF_SIZE,
+        max_block_length: MAX_BUF_SIZE,
+    })
+});
+
+/// Plugins with only audio and control ports, and at most two audio ports
+/// each way, are the ones that fit a node
+fn supported(plugin: &livi::Plugin) -> bool {
+    let counts = plugin.port_counts();
+
+    (1..=2).contains(&counts.audio_inputs)
+        && (1..=2).contains(&counts.audio_outputs)
+        && counts.atom_sequence_inputs == 0
+        && counts.atom_sequence_outputs == 0
+        && counts.cv_inputs == 0
+        && counts.cv_outputs == 0
+}
+
+/// Controls of a loaded plugin, shared between the ui and the audio thread
+struct Controls {
+    ports: Vec<livi::Port>,
+    /// The value of each port, applied to the plugin every block
+    values: Vec<Atomic<f32>>,
+}
+
+struct Host {
+    instance: livi::Instance,
+    controls: Arc<Controls>,
+    audio_inputs: usize,
+    audio_outputs: usize,
+}
+
+impl Host {
+    fn load(uri: &str, values: &HashMap<String, f32>) -> Result<Self, String> {
+        let plugin = WORLD
+            .plugin_by_uri(uri)
+            .ok_or_else(|| format!("{uri} isn't installed"))?;
+
+        if !supported(&plugin) {
+            return Err(format!(
+                "{} has ports this node can't connect",
+                plugin.name()
+            ));
+        }
+
+        let counts = plugin.port_counts();
+
+        let instance = unsafe {
+            plugin.instantiate(Arc::clone(&FEATURES), crate::devices::sample_rate() as f64)
+        }
+        .map_err(|e| e.to_string())?;
+
+        let ports = plugin
+            .ports_with_type(livi::PortType::ControlInput)
+            .collect::<Vec<_>>();
+
+        let values = ports
+            .iter()
+            .map(|port| {
+                let value = values
+                    .get(&port.symbol)
+                    .copied()
+                    .unwrap_or(port.default_value);
+                Atomic::new(value)
+            })
+            .collect();
+
+        Ok(Self {
+            instance,
+            controls: Arc::new(Controls { ports, values }),
+            audio_inputs: counts.audio_inputs,
+            audio_outputs: counts.audio_outputs,
+        })
+    }
+}
+
+/// What the ui shows of the plugin last loaded, and the uri it was loaded from
+struct Shown {
+    uri: Arc<String>,
+    controls: Result<Arc<Controls>, String>,
+}
+
+impl Default for Shown {
+    fn default() -> Self {
+        Self {
+            uri: Arc::default(),
+            controls: Err(String::new()),
+        }
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in_left",
+    input = "in_right",
+    output = "out_left",
+    output = "out_right",
+    title = "Plugin",
+    cfg_name = "plugin",
+    rate_dependent,
+    description = "Run an LV2 plugin, mono plugins only use the left ports",
+    custom_render = "Plugin::render"
+)]
+pub struct Plugin {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    /// Uri of the plugin, empty for none
+    #[dsp(save)]
+    uri: ArcSwap<String>,
+
+    /// Values of the plugin's controls, by port symbol
+    #[dsp(save)]
+    values: Mutex<HashMap<String, f32>>,
+
+    /// Only touched by the ui
+    shown: Mutex<Shown>,
+
+    /// The running plugin, instantiated by the ui and swapped in here, the
+    /// audio thread never waits for it
+    loaded: Mutex<Option<Host>>,
+}
+
+impl Plugin {
+    /// (Re)load the plugin if a different one has been picked
+    fn refresh(&self) -> MutexGuard<'_, Shown> {
+        let uri = self.uri.load_full();
+        let mut shown = self.shown.lock().unwrap();
+
+        if Arc::ptr_eq(&shown.uri, &uri) {
+            return shown;
+        }
+
+        let host = if uri.is_empty() {
+            Err(String::new())
+        } else {
+            tracing::info!(%uri, "Loading plugin");
+            Host::load(&uri, &self.values.lock().unwrap())
+        };
+
+        shown.uri = uri;
+        shown.controls = host
+            .as_ref()
+            .map(|h| Arc::clone(&h.controls))
+            .map_err(Clone::clone);
+
+        // the old plugin is dropped here rather than on the audio thread
+        let old = std::mem::replace(&mut *self.loaded.lock().unwrap(), host.ok());
+        drop(old);
+
+        shown
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        let current = self.uri.load_full();
+        let mut selected = current.as_ref().clone();
+
+        let name = |uri: &str| {
+            WORLD
+                .plugin_by_uri(uri)
+                .map_or_else(|| uri.to_owned(), |p| p.name())
+        };
+
+        egui::ComboBox::new(("plugin", self.id), "Plugin")
+            .selected_text(if selected.is_empty() {
+                "<none>".to_owned()
+            } else {
+                name(&selected)
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, String::new(), "<none>");
+
+                for plugin in WORLD.iter_plugins().filter(supported) {
+                    ui.selectable_value(&mut selected, plugin.uri(), plugin.name());
+                }
+            });
+
+        if selected != *current {
+            // the old plugin's values mean nothing to the new one
+            self.values.lock().unwrap().clear();
+            self.uri.store(Arc::new(selected));
+        }
+
+        let shown = self.refresh();
+
+        let controls = match &shown.controls {
+            Ok(controls) => controls,
+            Err(e) => {
+                if !e.is_empty() {
+                    ui.label(RichText::new(e.as_str()).color(ui.visuals().error_fg_color));
+                }
+                return;
+            }
+        };
+
+        let mut values = self.values.lock().unwrap();
+
+        for (port, value) in controls.ports.iter().zip(&controls.values) {
+            let mut v = value.load(std::sync::atomic::Ordering::Relaxed);
+
+            let min = port.min_value.unwrap_or(0.0);
+            let max = port.max_value.unwrap_or(1.0);
+
+            if ui
+                .add(egui::Slider::new(&mut v, min..=max).text(port.name.as_str()))
+                .changed()
+            {
+                value.store(v, std::sync::atomic::Ordering::Relaxed);
+                values.insert(port.symbol.clone(), v);
+            }
+        }
+    }
+}
+
+impl SimpleNode for Plugin {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let ins = [
+            inputs.get("in_left").unwrap(),
+            inputs.get("in_right").unwrap(),
+        ];
+
+        let len = ins[0].len();
+        let mut out_left = [0.0; MAX_BUF_SIZE];
+        let mut out_right = [0.0; MAX_BUF_SIZE];
+
+        // the ui only holds this while swapping in a newly loaded plugin
+        let mut loaded = self.loaded.try_lock();
+
+        let Some(host) = loaded.as_mut().ok().and_then(|l| l.as_mut()) else {
+            // without a plugin this is just a bit of wire
+            outputs.get("out_left").unwrap().copy_from_slice(ins[0]);
+            outputs.get("out_right").unwrap().copy_from_slice(ins[1]);
+            return;
+        };
+
+        for (port, value) in host.controls.ports.iter().zip(&host.controls.values) {
+            host.instance
+                .set_control_input(port.index, value.load(std::sync::atomic::Ordering::Relaxed));
+        }
+
+        let mut outs = [&mut out_left[..len], &mut out_right[..len]];
+
+        let ports = livi::EmptyPortConnections::new()
+            .with_audio_inputs(ins.iter().take(host.audio_inputs).copied())
+            .with_audio_outputs(outs.iter_mut().take(host.audio_outputs).map(|o| &mut **o));
+
+        if let Err(e) = unsafe { host.instance.run(len, ports) } {
+            tracing::warn!("Plugin failed to run: {:?}", e);
+        }
+
+        let audio_outputs = host.audio_outputs;
+        drop(loaded);
+
+        // mono plugins get copied to both sides
+        if audio_outputs == 1 {
+            out_right = out_left;
+        }
+
+        outputs
+            .get("out_left")
+            .unwrap()
+            .copy_from_slice(&out_left[..len]);
+        outputs
+            .get("out_right")
+            .unwrap()
+            .copy_from_slice(&out_right[..len]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fs::File, io::BufWriter, sync::Mutex};
+
+use eframe::egui;
+use egui::Ui;
+
+use crate::{ids::NodeId, node::*};
+
+type Writer = hound::WavWriter<BufWriter<File>>;
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    input = "in_l",
+    input = "in_r",
+    title = "Recorder",
+    cfg_name = "recorder",
+    rate_dependent,
+    description = "Record the signal to a stereo wave file, mono signals go into \"in\"",
+    custom_render = "Recorder::render"
+)]
+pub struct Recorder {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(default = "Mutex::new(None)")]
+    writer: Mutex<Option<Writer>>,
+
+    /// Name of the file being recorded to
+    #[dsp(default = "Mutex::new(None)")]
+    file_name: Mutex<Option<String>>,
+}
+
+impl Recorder {
+    fn start(&self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Record to")
+            .add_filter("wave file", &["wav"])
+            .set_file_name("recording.wav")
+            .save_file()
+        else {
+            return;
+        };
+
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: crate::devices::sample_rate(),
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+
+        match hound::WavWriter::create(&path, spec) {
+            Ok(writer) => {
+                tracing::info!("Recording to {:?}", path);
+
+                *self.file_name.lock().unwrap() =
+                    path.file_name().map(|n| n.to_string_lossy().into_owned());
+                *self.writer.lock().unwrap() = Some(writer);
+            }
+            Err(e) => {
+                tracing::warn!("Couldn't start recording to {:?}: {:?}", path, e);
+            }
+        }
+    }
+
+    /// Finish off the file being recorded to, if there is one
+    fn stop(&self) {
+        let Some(writer) = self.writer.lock().unwrap().take() else {
+            return;
+        };
+
+        let frames = writer.duration();
+
+        match writer.finalize() {
+            Ok(()) => tracing::info!("Finished recording {} frames", frames),
+            Err(e) => tracing::warn!("Couldn't finish the recording: {:?}", e),
+        }
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        let recorded = self.writer.lock().unwrap().as_ref().map(|w| w.duration());
+
+        // the lock isn't held here, the file dialog blocks
+        if ui
+            .add(egui::SelectableLabel::new(recorded.is_some(), "Record"))
+            .clicked()
+        {
+            if recorded.is_some() {
+                self.stop();
+            } else {
+                self.start();
+            }
+        }
+
+        if let Some(frames) = recorded {
+            ui.ctx().request_repaint();
+
+            let name = self.file_name.lock().unwrap().clone().unwrap_or_default();
+            ui.label(format!(
+                "Recording {name}: {:.1}s",
+                frames as f32 / crate::devices::sample_rate() as f32
+            ));
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // deleting the node mid recording still leaves a playable file
+        self.stop();
+    }
+}
+
+impl SimpleNode for Recorder {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, _outputs: ProcessOutput) {
+        let mut writer = self.writer.lock().unwrap();
+        let Some(w) = writer.as_mut() else {
+            return;
+        };
+
+        let input = inputs.get("in").unwrap();
+        let left = inputs.get("in_l").unwrap();
+        let right = inputs.get("in_r").unwrap();
+
+        for ((x, l), r) in input.iter().zip(left).zip(right) {
+            let written = w.write_sample(x + l).and_then(|_| w.write_sample(x + r));
+
+            if let Err(e) = written {
+                tracing::warn!("Couldn't write to the recording, stopping: {:?}", e);
+                drop(writer.take());
+                return;
+            }
+        }
+    }
+}
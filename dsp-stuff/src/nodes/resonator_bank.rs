@@ -0,0 +1,127 @@
+use std::sync::Mutex;
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+
+/// Partials above this fraction of the sample rate are left out, they'd alias
+const MAX_FREQUENCY: f32 = 0.45;
+
+/// One mode of the bank, a two pole resonator
+#[derive(Default, Clone)]
+struct Resonator {
+    y1: f32,
+    y2: f32,
+}
+
+impl Resonator {
+    fn step(&mut self, x: f32, a1: f32, a2: f32, gain: f32) -> f32 {
+        let y = gain * x + a1 * self.y1 - a2 * self.y2;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Resonator bank",
+    cfg_name = "resonator_bank",
+    description = "Ring a bank of tuned resonators with the input, turning noise and hits into pitched tones",
+    reset_state = "ResonatorBank::reset"
+)]
+pub struct ResonatorBank {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "20.0..=2000.0", logarithmic, as_input, suffix = "hz"),
+        label = "Fundamental",
+        save,
+        default = "220.0"
+    )]
+    frequency: Atomic<f32>,
+
+    #[dsp(slider(range = "1..=32"), save, default = "8")]
+    partials: Atomic<u32>,
+
+    /// Time (in seconds) for the fundamental to fall by 60dB, higher partials
+    /// die away faster
+    #[dsp(
+        slider(range = "0.01..=10.0", logarithmic, suffix = "s"),
+        save,
+        default = "1.5"
+    )]
+    decay: Atomic<f32>,
+
+    /// How far the partials are stretched sharp of a harmonic series, like a
+    /// stiff string or a bell
+    #[dsp(slider(range = "0.0..=0.1"), save, default = "0.0")]
+    inharmonicity: Atomic<f32>,
+
+    resonators: Mutex<Vec<Resonator>>,
+}
+
+impl ResonatorBank {
+    fn reset(&self) {
+        self.resonators.lock().unwrap().clear();
+    }
+}
+
+impl SimpleNode for ResonatorBank {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let mut frequency = [0.0; MAX_BUF_SIZE];
+        self.frequency_input(&inputs, &mut frequency);
+        let frequency = &frequency[..buf_size()];
+        let partials = self
+            .partials
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .max(1) as usize;
+        let decay = self.decay.load(std::sync::atomic::Ordering::Relaxed);
+        let inharmonicity = self
+            .inharmonicity
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+        output.fill(0.0);
+
+        // retuning every mode every sample is a lot of trig, so the tuning
+        // follows the fundamental once a block
+        let fundamental = frequency.iter().sum::<f32>() / frequency.len() as f32;
+        let sample_rate = crate::devices::sample_rate() as f32;
+
+        let mut resonators = self.resonators.lock().unwrap();
+        resonators.resize(partials, Resonator::default());
+
+        for (k, resonator) in (1..).zip(resonators.iter_mut()) {
+            let k = k as f32;
+            let freq = fundamental * k * (1.0 + inharmonicity * k * k).sqrt();
+
+            if freq >= MAX_FREQUENCY * sample_rate {
+                // silence it rather than let it alias
+                *resonator = Resonator::default();
+                continue;
+            }
+
+            let w = std::f32::consts::TAU * freq / sample_rate;
+            let t60 = (decay / k.sqrt()).max(1e-3);
+            let r = 10.0f32.powf(-3.0 / (t60 * sample_rate));
+
+            let a1 = 2.0 * r * w.cos();
+            let a2 = r * r;
+            // unity gain at resonance, falling off for the higher partials
+            let gain = (1.0 - r) * 2.0 * w.sin() / k;
+
+            for (x, out) in input.iter().zip(output.iter_mut()) {
+                *out += resonator.step(*x, a1, a2, gain);
+            }
+        }
+    }
+}
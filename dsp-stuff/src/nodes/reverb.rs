@@ -1,14 +1,36 @@
 use std::sync::Arc;
 
-use crate::{ids::NodeId, node::*};
+use crate::{
+    ids::NodeId,
+    node::*,
+    util::{read_fractional, Interpolation},
+};
 use atomig::Atomic;
-use collect_slice::CollectSlice;
 use rivulet::{
     circular_buffer::{Sink, Source},
     splittable, SplittableView, View, ViewMut,
 };
+use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+)]
+#[repr(u8)]
+enum Mode {
+    Echo,
+    /// Sweep the delay time with an LFO, best used with very short delays
+    Flanger,
+}
+
 #[derive(dsp_stuff_derive::DspNode)]
 #[dsp(
     input = "in",
@@ -16,7 +38,8 @@ use std::sync::Mutex;
     title = "Reverb",
     cfg_name = "reverb",
     description = "Repeat/ echo sounds with a given delay and decay factor",
-    after_settings_change = "Reverb::refresh_seconds"
+    after_settings_change = "Reverb::refresh_seconds",
+    reset_state = "Reverb::reset"
 )]
 pub struct Reverb {
     #[dsp(id)]
@@ -37,76 +60,183 @@ pub struct Reverb {
     #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
     decay: Atomic<f32>,
 
+    #[dsp(select, save, default = "Mode::Echo")]
+    mode: Atomic<Mode>,
+
+    #[dsp(
+        slider(range = "0.01..=5.0", logarithmic, suffix = " hz"),
+        label = "LFO rate",
+        save,
+        default = "0.25"
+    )]
+    rate: Atomic<f32>,
+
+    #[dsp(
+        slider(range = "0.0..=1.0"),
+        label = "LFO depth",
+        save,
+        default = "0.8"
+    )]
+    depth: Atomic<f32>,
+
+    #[dsp(select, save, default = "Interpolation::Linear")]
+    interpolation: Atomic<Interpolation>,
+
+    lfo_phase: Atomic<f32>,
+
+    /// Number of samples held in the delay buffer
+    #[dsp(default = "128")]
+    delay_len: Atomic<usize>,
+
     #[dsp(default = "make_buffer()")]
     buffer: Arc<Mutex<(splittable::View<Source<f32>>, Sink<f32>)>>,
 }
 
 fn make_buffer() -> Arc<Mutex<(splittable::View<Source<f32>>, Sink<f32>)>> {
-    let (mut sink, source) = rivulet::circular_buffer::<f32>(128);
+    Arc::new(Mutex::new(delay_line(128)))
+}
+
+/// A buffer holding `num_samples` of silence, reading a block from it and
+/// writing one back delays a signal by `num_samples`
+pub(super) fn delay_line(num_samples: usize) -> (splittable::View<Source<f32>>, Sink<f32>) {
+    let (mut sink, source) = rivulet::circular_buffer::<f32>(num_samples);
     let source = source.into_view();
-    let _ = sink.try_grant(128);
-    sink.view_mut().fill(0.0);
-    sink.release(sink.view().len());
 
-    Arc::new(Mutex::new((source, sink)))
+    // the buffer may be rounded up past `num_samples`, only that much of it is
+    // filled so the delay is exact
+    let _ = sink.try_grant(num_samples);
+    sink.view_mut()[..num_samples].fill(0.0);
+    sink.release(num_samples);
+
+    (source, sink)
+}
+
+/// Samples a delay line holds past the longest delay read from it, so reads
+/// near that delay still have neighbours either side to interpolate between
+pub(super) const DELAY_MARGIN: usize = 8;
+
+/// Length of delay line needed to read `delay` samples back
+pub(super) fn delay_line_len(delay: f32) -> usize {
+    (delay.ceil() as usize + DELAY_MARGIN).max(buf_size())
+}
+
+/// Read `delay` samples behind sample `i` of the block being produced
+///
+/// `history` is the whole delay line, and `written` what's been written for
+/// earlier samples of the block, which is all that short delays can reach.
+pub(super) fn read_delayed(
+    history: &[f32],
+    written: &[f32],
+    i: usize,
+    delay: f32,
+    interpolation: Interpolation,
+) -> f32 {
+    let len = history.len();
+    let pos = (i + len) as f32 - delay.clamp(1.0, len as f32);
+
+    let sample_at = |k: usize| {
+        let k = k.min(i + len - 1);
+        if k < len {
+            history[k]
+        } else {
+            written[k - len]
+        }
+    };
+
+    read_fractional(sample_at, pos, interpolation)
 }
 
 impl Reverb {
     fn refresh_seconds(&self) {
         let seconds = self.seconds.load(std::sync::atomic::Ordering::Relaxed);
 
-        let num_samples = ((seconds * 48000.0) as usize).max(128);
-
-        let (mut new_sink, new_source) = rivulet::circular_buffer::<f32>(num_samples);
-        let new_source = new_source.into_view();
+        let num_samples = delay_line_len(seconds * crate::devices::sample_rate() as f32);
 
-        let mut guard = self.buffer.lock().unwrap();
+        let line = delay_line(num_samples);
 
-        let _ = new_sink.try_grant(num_samples);
-        new_sink.view_mut().fill(0.0);
-        let num_zeros = new_sink.view().len();
-        new_sink.release(num_zeros);
+        *self.buffer.lock().unwrap() = line;
+        self.delay_len
+            .store(num_samples, std::sync::atomic::Ordering::Relaxed);
+    }
 
-        *guard = (new_source, new_sink);
+    fn reset(&self) {
+        // recreating the buffer also zeroes it
+        self.refresh_seconds();
+        self.lfo_phase
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
     }
-}
 
-impl SimpleNode for Reverb {
-    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
-    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
-        let input = inputs.get("in").unwrap();
-        let output = outputs.get("out").unwrap();
+    /// Run a block through the delay line, in flanger mode the delay is swept
+    /// between its full length and `1 - depth` of it
+    fn echo(
+        &self,
+        buffer: &mut (splittable::View<Source<f32>>, Sink<f32>),
+        input: &[f32],
+        output: &mut [f32],
+    ) {
+        let decay = self.decay.load(std::sync::atomic::Ordering::Relaxed);
+        let flanger = self.mode.load(std::sync::atomic::Ordering::Relaxed) == Mode::Flanger;
+        let rate = self.rate.load(std::sync::atomic::Ordering::Relaxed);
+        let depth = self.depth.load(std::sync::atomic::Ordering::Relaxed);
+        let mut phase = self.lfo_phase.load(std::sync::atomic::Ordering::Relaxed);
+        let sample_rate = crate::devices::sample_rate() as f32;
+        let max_delay = self.seconds.load(std::sync::atomic::Ordering::Relaxed) * sample_rate;
+        let interpolation = self
+            .interpolation
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        let (source, sink) = buffer;
+
+        // the buffer holds exactly one delay line's worth of samples, so take
+        // all of it to be able to read at any delay up to the full length
+        let delay_len = self.delay_len.load(std::sync::atomic::Ordering::Relaxed);
+
+        if delay_len < input.len() || !source.try_grant(delay_len).unwrap_or(false) {
+            tracing::trace!("Reverb buffer is empty");
+            output.copy_from_slice(input);
+        } else {
+            let history = &source.view()[..delay_len];
 
-        let mut guard = self.buffer.lock().unwrap();
+            for (i, x) in input.iter().enumerate() {
+                let delay = if flanger {
+                    let lfo = 0.5 + 0.5 * (phase * std::f32::consts::TAU).sin();
+                    phase = (phase + rate / sample_rate) % 1.0;
 
-        let decay = self.decay.load(std::sync::atomic::Ordering::Relaxed);
+                    max_delay * (1.0 - depth * lfo)
+                } else {
+                    max_delay
+                };
 
-        if guard.0.try_grant(input.len()).unwrap_or(false) {
-            let view = &guard.0.view()[..input.len()];
+                let delayed = read_delayed(history, output, i, delay, interpolation);
 
-            input
-                .iter()
-                .zip(view.iter())
-                .map(|(a, b)| a + b * decay)
-                .collect_slice(output);
+                output[i] = x + delayed * decay;
+            }
 
-            guard.0.release(input.len());
-        } else {
-            tracing::trace!("Reverb buffer is empty");
-            output.copy_from_slice(input);
+            source.release(input.len());
         }
 
-        if guard.1.try_grant(input.len()).unwrap_or(false) {
-            let view = &mut guard.1.view_mut()[..input.len()];
+        self.lfo_phase
+            .store(phase, std::sync::atomic::Ordering::Relaxed);
 
-            view.copy_from_slice(output);
-            guard.1.release(input.len());
+        if sink.try_grant(input.len()).unwrap_or(false) {
+            let view = &mut sink.view_mut()[..input.len()];
 
-            // for v in output.iter_mut() {
-            //     *v = (*v + 0.5).cos();
-            // }
+            view.copy_from_slice(output);
+            sink.release(input.len());
         } else {
             tracing::trace!("Not copying frame into reverb buffer");
         }
     }
 }
+
+impl SimpleNode for Reverb {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let mut guard = self.buffer.lock().unwrap();
+
+        self.echo(&mut guard, input, output);
+    }
+}
@@ -0,0 +1,140 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::{ids::NodeId, node::*, util::nearest_zero_crossing};
+use atomig::Atomic;
+
+/// Length (in ms) of the fade at each end of a reversed window
+const FADE_MS: f32 = 5.0;
+
+/// Level the trigger has to rise past to start a window
+const TRIGGER_THRESHOLD: f32 = 0.5;
+
+#[derive(Default)]
+struct ReverseState {
+    /// The most recent window of input
+    history: VecDeque<f32>,
+    /// Samples captured since the last window started playing
+    captured: usize,
+    /// The window being played, already reversed
+    playing: Vec<f32>,
+    pos: usize,
+    last_trigger: f32,
+}
+
+impl ReverseState {
+    /// Start playing the captured window, trimmed to the zero crossings
+    /// nearest its ends so the window itself doesn't start or end on a jump
+    fn start_window(&mut self) {
+        let history = self.history.make_contiguous();
+        let start = nearest_zero_crossing(history, 0);
+        let end = nearest_zero_crossing(history, history.len().saturating_sub(1));
+        let window = if start < end {
+            &history[start..=end]
+        } else {
+            &history[..]
+        };
+
+        self.playing.clear();
+        self.playing.extend(window.iter().rev());
+        self.pos = 0;
+        self.captured = 0;
+    }
+
+    /// The next reversed sample, faded in and out at the ends of the window so
+    /// that the jumps between windows don't click
+    fn next(&mut self, fade: usize) -> f32 {
+        let Some(&sample) = self.playing.get(self.pos) else {
+            return 0.0;
+        };
+
+        let len = self.playing.len();
+        let fade = fade.min(len / 2).max(1);
+        let from_end = (self.pos + 1).min(len - self.pos);
+        let gain = (from_end as f32 / fade as f32).min(1.0);
+
+        self.pos += 1;
+
+        sample * gain
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    input = "trigger",
+    output = "out",
+    title = "Reverse",
+    cfg_name = "reverse",
+    description = "Play back windows of a signal backwards, when the trigger is connected a window only plays when it rises",
+    reset_state = "Reverse::reset"
+)]
+pub struct Reverse {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "20.0..=4000.0", logarithmic, suffix = " ms"),
+        label = "Window",
+        save,
+        default = "500.0"
+    )]
+    time_ms: Atomic<f32>,
+
+    /// Fraction of reversed signal in the output
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "1.0")]
+    mix: Atomic<f32>,
+
+    state: Mutex<ReverseState>,
+}
+
+impl Reverse {
+    fn reset(&self) {
+        *self.state.lock().unwrap() = ReverseState::default();
+    }
+}
+
+impl SimpleNode for Reverse {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let samples_per_ms = crate::devices::sample_rate() as f32 / 1000.0;
+        let fade = (FADE_MS * samples_per_ms) as usize;
+        let time_ms = self.time_ms.load(std::sync::atomic::Ordering::Relaxed);
+        let window = ((time_ms * samples_per_ms) as usize).max(2 * fade);
+        let mix = self.mix.load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+        let trigger = inputs.get_checked("trigger");
+        let output = outputs.get("out").unwrap();
+
+        let mut state = self.state.lock().unwrap();
+
+        for (i, (x, out)) in input.iter().zip(output.iter_mut()).enumerate() {
+            state.history.push_back(*x);
+            while state.history.len() > window {
+                state.history.pop_front();
+            }
+            state.captured += 1;
+
+            let start = match trigger {
+                Some(trigger) => {
+                    let rising =
+                        state.last_trigger < TRIGGER_THRESHOLD && trigger[i] >= TRIGGER_THRESHOLD;
+                    state.last_trigger = trigger[i];
+                    rising
+                }
+                // untriggered, each window plays as soon as the next is captured
+                None => state.captured >= window,
+            };
+
+            if start {
+                state.start_window();
+            }
+
+            *out = x * (1.0 - mix) + state.next(fade) * mix;
+        }
+    }
+}
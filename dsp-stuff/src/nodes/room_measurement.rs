@@ -0,0 +1,335 @@
+use std::sync::{Arc, Mutex};
+
+use atomig::Atomic;
+use eframe::egui;
+use egui::{emath::RectTransform, epaint::Shape, pos2, vec2, Color32, Frame, Rect, Stroke, Ui};
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::{ids::NodeId, node::*};
+
+/// How long (in seconds) to keep recording after the sweep has finished, to
+/// capture the room's decay and the round trip latency
+const TAIL: f32 = 1.0;
+
+/// Length (in seconds) of the impulse response kept after a measurement
+const IMPULSE_LEN: f32 = 1.0;
+
+fn seconds_to_samples(seconds: f32) -> usize {
+    (seconds * crate::devices::sample_rate() as f32) as usize
+}
+
+/// Bands per octave of the displayed response
+const BANDS_PER_OCTAVE: f32 = 6.0;
+
+const MIN_DB: f32 = -30.0;
+const MAX_DB: f32 = 12.0;
+
+#[derive(Default)]
+enum Stage {
+    #[default]
+    Idle,
+    Measuring {
+        position: usize,
+        sweep: Vec<f32>,
+        recorded: Vec<f32>,
+    },
+    /// The recording has been handed to a worker thread, as the FFTs are far
+    /// too slow to run on the audio task
+    Analysing,
+}
+
+#[derive(Default)]
+struct Measurement {
+    stage: Stage,
+
+    impulse: Vec<f32>,
+
+    /// (frequency, level in dB) pairs, normalised so the midrange sits at 0dB
+    response: Vec<(f32, f32)>,
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "mic",
+    output = "out",
+    title = "Room Measurement",
+    cfg_name = "room_measurement",
+    rate_dependent,
+    description = "Play a sweep, record it back and display the frequency response",
+    custom_render = "RoomMeasurement::render"
+)]
+pub struct RoomMeasurement {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "10.0..=1000.0", logarithmic, suffix = " hz"),
+        label = "Sweep start",
+        save,
+        default = "20.0"
+    )]
+    sweep_start: Atomic<f32>,
+    #[dsp(
+        slider(range = "1000.0..=24000.0", logarithmic, suffix = " hz"),
+        label = "Sweep end",
+        save,
+        default = "20000.0"
+    )]
+    sweep_end: Atomic<f32>,
+    #[dsp(
+        slider(range = "1.0..=30.0", suffix = "s"),
+        label = "Sweep duration",
+        save,
+        default = "5.0"
+    )]
+    sweep_duration: Atomic<f32>,
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    level: Atomic<f32>,
+
+    measurement: Arc<Mutex<Measurement>>,
+}
+
+/// An exponential sine sweep, which excites every frequency band with the same
+/// energy
+fn make_sweep(start: f32, end: f32, duration: f32, level: f32) -> Vec<f32> {
+    let sample_rate = crate::devices::sample_rate() as f32;
+    let len = (duration * sample_rate) as usize;
+    let rate = (end / start).ln();
+
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / sample_rate;
+            let phase = std::f32::consts::TAU * start * duration / rate
+                * ((t / duration * rate).exp() - 1.0);
+            phase.sin() * level
+        })
+        .collect()
+}
+
+/// Deconvolve the recording by the sweep, giving the impulse response and the
+/// (banded) frequency response of whatever the sweep went through
+fn analyse(sweep: &[f32], recorded: &[f32]) -> (Vec<f32>, Vec<(f32, f32)>) {
+    let len = (sweep.len() + recorded.len()).next_power_of_two();
+
+    let mut planner = FftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(len);
+    let ifft = planner.plan_fft_inverse(len);
+
+    let to_complex = |samples: &[f32]| {
+        let mut buf = samples
+            .iter()
+            .map(|x| Complex::new(*x, 0.0))
+            .collect::<Vec<_>>();
+        buf.resize(len, Complex::default());
+        buf
+    };
+
+    let mut x = to_complex(sweep);
+    let mut y = to_complex(recorded);
+    fft.process(&mut x);
+    fft.process(&mut y);
+
+    // regularise so bins the sweep didn't excite don't blow up
+    let peak = x.iter().map(|v| v.norm_sqr()).fold(0.0, f32::max);
+    let epsilon = peak * 1e-6;
+
+    let mut h = x
+        .iter()
+        .zip(&y)
+        .map(|(x, y)| y * x.conj() / (x.norm_sqr() + epsilon))
+        .collect::<Vec<_>>();
+
+    let response = banded_response(&h);
+
+    ifft.process(&mut h);
+    let impulse = h
+        .iter()
+        .take(seconds_to_samples(IMPULSE_LEN))
+        .map(|v| v.re / len as f32)
+        .collect();
+
+    (impulse, response)
+}
+
+fn banded_response(h: &[Complex<f32>]) -> Vec<(f32, f32)> {
+    let bin_width = crate::devices::sample_rate() as f32 / h.len() as f32;
+    let half_band = 2.0f32.powf(0.5 / BANDS_PER_OCTAVE);
+
+    let mut response = Vec::new();
+    let mut freq = 20.0;
+
+    while freq <= 20000.0 {
+        let lo = (freq / half_band / bin_width) as usize;
+        let hi = ((freq * half_band / bin_width) as usize)
+            .max(lo + 1)
+            .min(h.len() / 2);
+
+        if lo < hi {
+            let bins = &h[lo..hi];
+            let power = bins.iter().map(|v| v.norm_sqr()).sum::<f32>() / bins.len() as f32;
+            response.push((freq, 10.0 * power.max(1e-12).log10()));
+        }
+
+        freq *= 2.0f32.powf(1.0 / BANDS_PER_OCTAVE);
+    }
+
+    let midrange = response
+        .iter()
+        .filter(|(f, _)| (200.0..=2000.0).contains(f))
+        .map(|(_, db)| *db)
+        .collect::<Vec<_>>();
+
+    if !midrange.is_empty() {
+        let reference = midrange.iter().sum::<f32>() / midrange.len() as f32;
+
+        for (_, db) in &mut response {
+            *db -= reference;
+        }
+    }
+
+    response
+}
+
+impl RoomMeasurement {
+    fn start_measurement(&self) {
+        let sweep = make_sweep(
+            self.sweep_start.load(std::sync::atomic::Ordering::Relaxed),
+            self.sweep_end.load(std::sync::atomic::Ordering::Relaxed),
+            self.sweep_duration
+                .load(std::sync::atomic::Ordering::Relaxed),
+            self.level.load(std::sync::atomic::Ordering::Relaxed),
+        );
+
+        let recorded = Vec::with_capacity(sweep.len() + seconds_to_samples(TAIL));
+
+        self.measurement.lock().unwrap().stage = Stage::Measuring {
+            position: 0,
+            sweep,
+            recorded,
+        };
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        let measurement = self.measurement.lock().unwrap();
+
+        let progress = match &measurement.stage {
+            Stage::Idle => None,
+            Stage::Analysing => Some(1.0),
+            Stage::Measuring {
+                position, sweep, ..
+            } => Some(*position as f32 / (sweep.len() + seconds_to_samples(TAIL)) as f32),
+        };
+
+        Frame::dark_canvas(ui.style()).show(ui, |ui| {
+            let (_id, rect) = ui.allocate_space(vec2(240.0, 120.0));
+
+            let to_screen = RectTransform::from_to(
+                Rect::from_x_y_ranges(20.0f32.log10()..=20000.0f32.log10(), MAX_DB..=MIN_DB),
+                rect,
+            );
+
+            let reference = Shape::line_segment(
+                [
+                    to_screen * pos2(20.0f32.log10(), 0.0),
+                    to_screen * pos2(20000.0f32.log10(), 0.0),
+                ],
+                Stroke::new(1.0, Color32::from_gray(64)),
+            );
+
+            let points = measurement
+                .response
+                .iter()
+                .map(|(f, db)| to_screen * pos2(f.log10(), db.clamp(MIN_DB, MAX_DB)))
+                .collect::<Vec<_>>();
+
+            let line = Shape::line(
+                points,
+                Stroke::new(1.3, Color32::from_additive_luminance(196)),
+            );
+
+            ui.painter().extend(vec![reference, line]);
+        });
+
+        if let Some((peak, _)) = measurement
+            .impulse
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        {
+            ui.label(format!(
+                "Latency: {:.1} ms",
+                peak as f32 * 1000.0 / crate::devices::sample_rate() as f32
+            ));
+        }
+
+        drop(measurement);
+
+        if let Some(progress) = progress {
+            ui.ctx().request_repaint();
+            let text = if progress < 1.0 {
+                "Measuring"
+            } else {
+                "Analysing"
+            };
+            ui.add(egui::ProgressBar::new(progress).text(text));
+        } else if ui
+            .button("Measure")
+            .on_hover_text_at_pointer(
+                "Play a sweep out of \"out\" and record it back through \"mic\"",
+            )
+            .clicked()
+        {
+            self.start_measurement();
+        }
+    }
+}
+
+impl SimpleNode for RoomMeasurement {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let input = inputs.get("mic").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let mut measurement = self.measurement.lock().unwrap();
+
+        let Stage::Measuring {
+            position,
+            sweep,
+            recorded,
+        } = &mut measurement.stage
+        else {
+            output.fill(0.0);
+            return;
+        };
+
+        for out in output.iter_mut() {
+            *out = sweep.get(*position).copied().unwrap_or(0.0);
+            *position += 1;
+        }
+
+        recorded.extend_from_slice(input);
+
+        if *position >= sweep.len() + seconds_to_samples(TAIL) {
+            let Stage::Measuring {
+                sweep, recorded, ..
+            } = std::mem::replace(&mut measurement.stage, Stage::Analysing)
+            else {
+                unreachable!()
+            };
+
+            let measurement = Arc::clone(&self.measurement);
+            std::thread::spawn(move || {
+                let (impulse, response) = analyse(&sweep, &recorded);
+
+                let mut measurement = measurement.lock().unwrap();
+                measurement.impulse = impulse;
+                measurement.response = response;
+                measurement.stage = Stage::Idle;
+            });
+        }
+    }
+}
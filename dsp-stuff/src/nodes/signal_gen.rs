@@ -17,9 +17,16 @@ use crate::{ids::NodeId, node::*};
 #[repr(u8)]
 enum Mode {
     Sine,
+    /// Before saw was added this was a rising ramp, patches that relied on
+    /// that should switch to saw
     Triangle,
+    /// This used to flip partway through each block rather than each period,
+    /// it now follows the frequency
     Square,
+    Saw,
     Constant,
+    WhiteNoise,
+    Sweep,
 }
 
 #[derive(dsp_stuff_derive::DspNode)]
@@ -50,81 +57,271 @@ pub struct SignalGen {
 
     #[dsp(select, default = "Mode::Sine", save)]
     mode: Atomic<Mode>,
+
+    #[dsp(
+        checkbox,
+        label = "Band-limited",
+        tooltip = "Smooth the corners of the square, saw and triangle waves so they don't \
+                   alias, turn off for the sharp edged waveforms",
+        save,
+        default = "true"
+    )]
+    band_limited: Atomic<bool>,
+
+    #[dsp(
+        slider(range = "0.1..=20000.0", logarithmic, suffix = " hz"),
+        label = "Sweep start",
+        save,
+        default = "20.0"
+    )]
+    sweep_start: Atomic<f32>,
+    #[dsp(
+        slider(range = "0.1..=20000.0", logarithmic, suffix = " hz"),
+        label = "Sweep end",
+        save,
+        default = "20000.0"
+    )]
+    sweep_end: Atomic<f32>,
+    #[dsp(
+        slider(range = "0.1..=60.0", suffix = "s"),
+        label = "Sweep duration",
+        save,
+        default = "10.0"
+    )]
+    sweep_duration: Atomic<f32>,
+
+    /// Seconds into the current sweep
+    sweep_position: Atomic<f32>,
+
+    #[dsp(default = "0x2545_f491u32")]
+    noise_state: Atomic<u32>,
+}
+
+/// Band-limiting correction for a unit step at phase zero, `dt` is the phase
+/// increment per sample
+fn poly_blep(phase: f32, dt: f32) -> f32 {
+    if phase < dt {
+        let x = phase / dt;
+        -(1.0 - x) * (1.0 - x) / 2.0
+    } else if phase > 1.0 - dt {
+        let x = (phase - 1.0) / dt;
+        (1.0 + x) * (1.0 + x) / 2.0
+    } else {
+        0.0
+    }
+}
+
+/// The integral of [`poly_blep`], correcting a unit (per sample) change in
+/// slope at phase zero
+fn poly_blamp(phase: f32, dt: f32) -> f32 {
+    if phase < dt {
+        let x = phase / dt;
+        (1.0 - x).powi(3) / 6.0
+    } else if phase > 1.0 - dt {
+        let x = (phase - 1.0) / dt;
+        (1.0 + x).powi(3) / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn sine(phase: f32, _dt: f32) -> f32 {
+    (phase * std::f32::consts::TAU).sin()
+}
+
+fn triangle(phase: f32, dt: f32) -> f32 {
+    let naive = 1.0 - 4.0 * (phase - 0.5).abs();
+
+    // the slope flips between -4 and 4 at each corner
+    naive + 8.0 * dt * (poly_blamp(phase, dt) - poly_blamp((phase + 0.5) % 1.0, dt))
+}
+
+fn square(phase: f32, dt: f32) -> f32 {
+    let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+
+    naive + 2.0 * (poly_blep(phase, dt) - poly_blep((phase + 0.5) % 1.0, dt))
+}
+
+fn saw(phase: f32, dt: f32) -> f32 {
+    2.0 * phase - 1.0 - 2.0 * poly_blep(phase, dt)
+}
+
+pub(super) fn xorshift(mut x: u32) -> u32 {
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    x
 }
 
 impl SignalGen {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
-    fn do_sine(&self, output: &mut [f32], amplitude: &[f32], frequency: &[f32]) {
-        let clock = self.clock.load(std::sync::atomic::Ordering::Relaxed);
+    fn do_periodic(
+        &self,
+        output: &mut [f32],
+        amplitude: &[f32],
+        frequency: &[f32],
+        wave: fn(f32, f32) -> f32,
+    ) {
+        let mut clock = self.clock.load(std::sync::atomic::Ordering::Relaxed);
+        let band_limited = self.band_limited.load(std::sync::atomic::Ordering::Relaxed);
 
-        let sample_rate = 48000.0;
-        let mut total = 0.0;
+        let sample_rate = crate::devices::sample_rate() as f32;
 
         for ((v, amplitude), frequency) in output.iter_mut().zip(amplitude).zip(frequency) {
-            let step = frequency / sample_rate;
-            total += step;
-            *v = ((clock + total) * std::f32::consts::TAU).sin() * amplitude;
+            let dt = frequency / sample_rate;
+            clock = (clock + dt) % 1.0;
+
+            // the corrections are all zero for a step of zero
+            *v = wave(clock, if band_limited { dt } else { 0.0 }) * amplitude;
         }
 
         self.clock
-            .store((clock + total) % 1.0, std::sync::atomic::Ordering::Relaxed);
+            .store(clock, std::sync::atomic::Ordering::Relaxed);
     }
 
-    fn do_triangle(&self, output: &mut [f32], amplitude: &[f32], frequency: &[f32]) {
-        let clock = self.clock.load(std::sync::atomic::Ordering::Relaxed);
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn do_const(&self, output: &mut [f32], amplitude: &[f32]) {
+        output.copy_from_slice(amplitude);
+    }
 
-        let sample_rate = 48000.0;
-        let mut total = 0.0;
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn do_noise(&self, output: &mut [f32], amplitude: &[f32]) {
+        let mut state = self.noise_state.load(std::sync::atomic::Ordering::Relaxed);
 
-        for ((v, amplitude), frequency) in output.iter_mut().zip(amplitude).zip(frequency) {
-            let step = frequency / sample_rate;
-            total += step;
-            *v = (2.0 * ((clock + total) % 1.0) - 1.0) * amplitude;
+        for (v, amplitude) in output.iter_mut().zip(amplitude) {
+            state = xorshift(state);
+            *v = (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude;
         }
 
-        self.clock
-            .store((clock + total) % 1.0, std::sync::atomic::Ordering::Relaxed);
+        self.noise_state
+            .store(state, std::sync::atomic::Ordering::Relaxed);
     }
 
-    fn do_square(&self, output: &mut [f32], amplitude: &[f32], frequency: &[f32]) {
-        let clock = self.clock.load(std::sync::atomic::Ordering::Relaxed);
+    /// An exponential sine sweep from the start to the end frequency, looping
+    /// once the duration has passed
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn do_sweep(&self, output: &mut [f32], amplitude: &[f32]) {
+        let start = self.sweep_start.load(std::sync::atomic::Ordering::Relaxed);
+        let end = self.sweep_end.load(std::sync::atomic::Ordering::Relaxed);
+        let duration = self
+            .sweep_duration
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .max(0.1);
 
-        let sample_rate = 48000.0;
-        let mut total = 0.0;
+        let mut clock = self.clock.load(std::sync::atomic::Ordering::Relaxed);
+        let mut position = self
+            .sweep_position
+            .load(std::sync::atomic::Ordering::Relaxed);
 
-        for ((v, amplitude), frequency) in output.iter_mut().zip(amplitude).zip(frequency) {
-            let step = frequency / sample_rate;
-            total += step;
-            *v = (if total > 0.5 { 1.0 } else { -1.0 }) * amplitude;
+        let sample_rate = crate::devices::sample_rate() as f32;
+
+        for (v, amplitude) in output.iter_mut().zip(amplitude) {
+            let frequency = start * (end / start).powf(position / duration);
+
+            clock = (clock + frequency / sample_rate) % 1.0;
+            *v = sine(clock, 0.0) * amplitude;
+
+            position += 1.0 / sample_rate;
+            if position >= duration {
+                position = 0.0;
+            }
         }
 
         self.clock
-            .store((clock + total) % 1.0, std::sync::atomic::Ordering::Relaxed);
-    }
-
-    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
-    fn do_const(&self, output: &mut [f32], amplitude: &[f32]) {
-        output.copy_from_slice(amplitude);
+            .store(clock, std::sync::atomic::Ordering::Relaxed);
+        self.sweep_position
+            .store(position, std::sync::atomic::Ordering::Relaxed);
     }
 }
 
 impl SimpleNode for SignalGen {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
-        let mut amplitude = [0.0; BUF_SIZE];
+        let mut amplitude = [0.0; MAX_BUF_SIZE];
         self.amplitude_input(&inputs, &mut amplitude);
-        let mut frequency = [0.0; BUF_SIZE];
+        let amplitude = &amplitude[..buf_size()];
+        let mut frequency = [0.0; MAX_BUF_SIZE];
         self.frequency_input(&inputs, &mut frequency);
+        let frequency = &frequency[..buf_size()];
 
         let output = outputs.get("out").unwrap();
 
         let mode = self.mode.load(std::sync::atomic::Ordering::Relaxed);
 
         match mode {
-            Mode::Sine => self.do_sine(output, &amplitude, &frequency),
+            Mode::Sine => self.do_periodic(output, &amplitude, &frequency, sine),
             Mode::Constant => self.do_const(output, &amplitude),
-            Mode::Triangle => self.do_triangle(output, &amplitude, &frequency),
-            Mode::Square => self.do_square(output, &amplitude, &frequency),
+            Mode::Triangle => self.do_periodic(output, &amplitude, &frequency, triangle),
+            Mode::Square => self.do_periodic(output, &amplitude, &frequency, square),
+            Mode::Saw => self.do_periodic(output, &amplitude, &frequency, saw),
+            Mode::WhiteNoise => self.do_noise(output, &amplitude),
+            Mode::Sweep => self.do_sweep(output, &amplitude),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustfft::{num_complex::Complex, FftPlanner};
+
+    use super::*;
+    use crate::devices;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// 10hz bins, so the fundamental below fits a whole number of periods
+    const SAMPLES: usize = 4800;
+
+    /// High enough for harmonics past the seventh to fold back, and lands them
+    /// between the harmonics rather than on them
+    const FUNDAMENTAL: f32 = 3130.0;
+
+    /// Energy in the bins that aren't harmonics of [`FUNDAMENTAL`], which is
+    /// what folded back past nyquist
+    fn aliased_energy(wave: fn(f32, f32) -> f32, band_limited: bool) -> f32 {
+        let mut output = vec![0.0; SAMPLES];
+
+        devices::with_sample_rate(SAMPLE_RATE, || {
+            let node = SignalGen::new(NodeId::generate());
+            node.band_limited
+                .store(band_limited, std::sync::atomic::Ordering::Relaxed);
+            node.do_periodic(&mut output, &[1.0; SAMPLES], &[FUNDAMENTAL; SAMPLES], wave);
+        });
+
+        let mut spectrum = output
+            .into_iter()
+            .map(|x| Complex::new(x, 0.0))
+            .collect::<Vec<_>>();
+        FftPlanner::new()
+            .plan_fft_forward(SAMPLES)
+            .process(&mut spectrum);
+
+        let harmonic_spacing = (FUNDAMENTAL * SAMPLES as f32 / SAMPLE_RATE as f32) as usize;
+
+        spectrum[1..=SAMPLES / 2]
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i + 1, x))
+            // the bins either side too, for the phase rounding smearing them
+            .filter(|(bin, _)| {
+                let offset = bin % harmonic_spacing;
+                offset > 1 && offset < harmonic_spacing - 1
+            })
+            .map(|(_, x)| x.norm_sqr())
+            .sum()
+    }
+
+    #[test]
+    fn band_limiting_reduces_aliasing() {
+        for (name, wave) in [("saw", saw as fn(f32, f32) -> f32), ("square", square)] {
+            let naive = aliased_energy(wave, false);
+            let band_limited = aliased_energy(wave, true);
+
+            assert!(
+                band_limited < naive / 10.0,
+                "band-limiting should cut the {name}'s aliasing tenfold, got {band_limited} \
+                 against {naive}"
+            );
         }
     }
 }
@@ -1,13 +1,10 @@
 use eframe::egui;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::VecDeque,
     sync::{Arc, Mutex},
 };
 
-use crate::{
-    ids::{NodeId, PortId},
-    node::*,
-};
+use crate::{ids::NodeId, node::*};
 use atomig::Atomic;
 use audioviz::spectrum::{config::ProcessorConfig, processor::Processor, Frequency};
 use egui::{
@@ -17,65 +14,72 @@ use egui::{
 };
 use rivulet::View;
 
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    title = "Spectrogram",
+    cfg_name = "spectrogram",
+    description = "Inspect the volume of individual frequencies over time",
+    custom_render = "Spectrogram::render"
+)]
 pub struct Spectrogram {
+    #[dsp(id)]
     id: NodeId,
-    outputs: PortStorage,
+    #[dsp(inputs)]
     inputs: PortStorage,
-    buffer: Arc<Mutex<VecDeque<Vec<Frequency>>>>,
-    buffer_size: Atomic<usize>,
-    fft_size: Atomic<usize>,
-    upper_bound: Atomic<usize>,
-    lower_bound: Atomic<usize>,
-}
-
-#[derive(serde::Deserialize, serde::Serialize)]
-struct SpectrogramConfig {
-    id: NodeId,
-    inputs: HashMap<String, PortId>,
-    buffer_size: usize,
-    fft_size: usize,
-    upper_bound: usize,
-    lower_bound: usize,
-}
-
-impl Node for Spectrogram {
-    fn title(&self) -> &'static str {
-        "Spectrogram"
-    }
+    #[dsp(outputs)]
+    outputs: PortStorage,
 
-    fn cfg_name(&self) -> &'static str {
-        "spectrogram"
-    }
+    #[dsp(default = "Arc::new(Mutex::new(VecDeque::with_capacity(10)))")]
+    buffer: Arc<Mutex<VecDeque<Vec<Frequency>>>>,
 
-    fn description(&self) -> &'static str {
-        "Inspect the volume of individual frequencies over time"
-    }
+    #[dsp(
+        slider(range = "128..=8192"),
+        label = "FFT size",
+        save,
+        default = "512"
+    )]
+    fft_size: Atomic<usize>,
 
-    fn id(&self) -> NodeId {
-        self.id
-    }
+    /// Each fft overlaps the previous one by all but `1 / overlap` of it
+    #[dsp(save, default = "2")]
+    overlap: Atomic<usize>,
 
-    fn inputs(&self) -> &PortStorage {
-        &self.inputs
-    }
+    #[dsp(
+        slider(range = "10..=1024"),
+        label = "Buffer size",
+        save,
+        default = "250"
+    )]
+    buffer_size: Atomic<usize>,
 
-    fn outputs(&self) -> &PortStorage {
-        &self.outputs
-    }
+    #[dsp(
+        slider(
+            range = "20..=20_000",
+            range_from = "self.lower_bound.load(atomig::Ordering::Relaxed)"
+        ),
+        label = "Upper bound",
+        save,
+        default = "20_000"
+    )]
+    upper_bound: Atomic<usize>,
 
-    fn save(&self) -> serde_json::Value {
-        let cfg = SpectrogramConfig {
-            id: self.id,
-            inputs: self.inputs.get_all(),
-            buffer_size: self.buffer_size.load(atomig::Ordering::Relaxed),
-            fft_size: self.fft_size.load(atomig::Ordering::Relaxed),
-            upper_bound: self.upper_bound.load(atomig::Ordering::Relaxed),
-            lower_bound: self.lower_bound.load(atomig::Ordering::Relaxed),
-        };
+    #[dsp(
+        slider(
+            range = "20..=20_000",
+            range_to = "self.upper_bound.load(atomig::Ordering::Relaxed)"
+        ),
+        label = "Lower bound",
+        save,
+        default = "20"
+    )]
+    lower_bound: Atomic<usize>,
 
-        serde_json::to_value(cfg).unwrap()
-    }
+    /// The latest `fft_size` samples, kept between frames for overlapping
+    window: Mutex<VecDeque<f32>>,
+}
 
+impl Spectrogram {
     #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
     fn render(&self, ui: &mut egui::Ui) {
         let lower_bound = self.lower_bound.load(atomig::Ordering::Relaxed);
@@ -134,108 +138,66 @@ impl Node for Spectrogram {
             ui.painter().add(Shape::mesh(mesh));
         });
 
-        ui.horizontal(|ui| {
-            ui.label("FFT Size");
-
-            let mut s = self.fft_size.load(atomig::Ordering::Relaxed);
+        let label = |overlap: usize| match overlap {
+            1 => "None".to_owned(),
+            n => format!("{}%", 100.0 - 100.0 / n as f32),
+        };
 
-            let r = ui.add(egui::Slider::new(&mut s, 128..=8192));
+        let mut overlap = self.overlap.load(atomig::Ordering::Relaxed);
 
-            if r.changed() {
-                self.fft_size.store(s, atomig::Ordering::Relaxed);
-            }
-        });
+        egui::ComboBox::new(("overlap", self.id), "Overlap")
+            .selected_text(label(overlap))
+            .show_ui(ui, |ui| {
+                for n in [1, 2, 4, 8] {
+                    if ui.selectable_value(&mut overlap, n, label(n)).changed() {
+                        self.overlap.store(overlap, atomig::Ordering::Relaxed);
+                    }
+                }
+            })
+            .response
+            .on_hover_text_at_pointer("More overlap updates the display more often, for more CPU");
+    }
+}
 
-        ui.horizontal(|ui| {
-            ui.label("Buffer Size");
-            let mut s = self.buffer_size.load(atomig::Ordering::Relaxed);
+impl Perform for Spectrogram {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    async fn perform(&self, inputs: NodeInputs<'_, '_, '_>, _outputs: NodeOutputs<'_, '_, '_>) {
+        let fft_size = self.fft_size.load(atomig::Ordering::Relaxed);
+        let overlap = self.overlap.load(atomig::Ordering::Relaxed).max(1);
+        let hop = (fft_size / overlap).max(1);
 
-            let r = ui.add(egui::Slider::new(&mut s, 10..=1024));
+        let mut hop_buf = vec![0.0; hop];
+        let collected_inputs = &mut inputs[self.inputs.get_idx("in").unwrap()];
+        collect_and_average(&mut hop_buf, collected_inputs, input_mixing()).await;
 
-            if r.changed() {
-                self.buffer_size.store(s, atomig::Ordering::Relaxed);
+        for input_port in inputs.iter_mut() {
+            for input_pipe in input_port.iter_mut() {
+                input_pipe.release(hop);
             }
-        });
-
-        ui.horizontal(|ui| {
-            ui.label("Upper bound");
-            let lower_bound = self.lower_bound.load(atomig::Ordering::Relaxed);
-            let mut s = self.upper_bound.load(atomig::Ordering::Relaxed);
+        }
 
-            let r = ui.add(egui::Slider::new(&mut s, lower_bound..=20_000));
+        let fft_buf = {
+            let mut window = self.window.lock().unwrap();
+            window.extend(hop_buf);
 
-            if r.changed() {
-                self.upper_bound.store(s, atomig::Ordering::Relaxed);
+            while window.len() > fft_size {
+                window.pop_front();
             }
-        });
-
-        ui.horizontal(|ui| {
-            ui.label("Lower bound");
-            let upper_bound = self.upper_bound.load(atomig::Ordering::Relaxed);
-            let mut s = self.lower_bound.load(atomig::Ordering::Relaxed);
-
-            let r = ui.add(egui::Slider::new(&mut s, 20..=upper_bound));
 
-            if r.changed() {
-                let upper = self.upper_bound.load(atomig::Ordering::Relaxed);
-                s = s.min(upper);
-                self.lower_bound.store(s, atomig::Ordering::Relaxed);
+            // wait for a full window after starting or growing the fft
+            if window.len() < fft_size {
+                return;
             }
-        });
-    }
-}
 
-impl NodeStatic for Spectrogram {
-    fn new(id: NodeId) -> Self {
-        let inputs = PortStorage::default();
-        inputs.add("in".to_owned());
-
-        Self {
-            id,
-            inputs,
-            outputs: Default::default(),
-            buffer: Arc::new(Mutex::new(VecDeque::with_capacity(10))),
-            buffer_size: Atomic::new(250),
-            fft_size: Atomic::new(512),
-            lower_bound: Atomic::new(20),
-            upper_bound: Atomic::new(20_000),
-        }
-    }
-
-    fn restore(value: serde_json::Value) -> Self
-    where
-        Self: Sized,
-    {
-        let cfg: SpectrogramConfig = serde_json::from_value(value).unwrap();
-
-        let mut this = Self::new(cfg.id);
-        this.inputs = PortStorage::new(cfg.inputs);
-        this.buffer_size
-            .store(cfg.buffer_size, atomig::Ordering::Relaxed);
-        this.fft_size.store(cfg.fft_size, atomig::Ordering::Relaxed);
-        this.upper_bound
-            .store(cfg.upper_bound, atomig::Ordering::Relaxed);
-        this.lower_bound
-            .store(cfg.lower_bound, atomig::Ordering::Relaxed);
-
-        this
-    }
-}
-
-impl Perform for Spectrogram {
-    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
-    async fn perform(&self, inputs: NodeInputs<'_, '_, '_>, _outputs: NodeOutputs<'_, '_, '_>) {
-        let buf_size = self.fft_size.load(atomig::Ordering::Relaxed);
-        let mut fft_buf = vec![0.0; buf_size];
-        let collected_inputs = &mut inputs[self.inputs.get_idx("in").unwrap()];
-        collect_and_average(&mut fft_buf, collected_inputs).await;
+            window.iter().copied().collect::<Vec<_>>()
+        };
 
         let lower_bound = self.lower_bound.load(atomig::Ordering::Relaxed);
         let upper_bound = self.upper_bound.load(atomig::Ordering::Relaxed);
 
         let mut processor = Processor::from_raw_data(
             ProcessorConfig {
-                sampling_rate: 48000,
+                sampling_rate: crate::devices::sample_rate(),
                 frequency_bounds: [lower_bound, upper_bound],
                 resolution: None, //Some(100),
                 volume: 1.0,
@@ -259,11 +221,5 @@ impl Perform for Spectrogram {
                 queue.pop_front();
             }
         }
-
-        for input_port in inputs.iter_mut() {
-            for input_pipe in input_port.iter_mut() {
-                input_pipe.release(buf_size);
-            }
-        }
     }
 }
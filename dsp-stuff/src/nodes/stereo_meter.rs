@@ -0,0 +1,230 @@
+use std::{
+    collections::VecDeque,
+    sync::{atomic::AtomicU32, Mutex},
+    time::Duration,
+};
+
+use atomig::Atomic;
+use eframe::egui;
+use egui::{pos2, vec2, Color32, FontFamily, Rect, RichText, Rounding, Ui};
+
+use crate::{ids::NodeId, node::*};
+
+/// Seconds of signal the RMS level is taken over, about the integration time
+/// of a VU meter
+const RMS_WINDOW: f32 = 0.3;
+
+/// How fast the held peak falls back, in dB per second
+const PEAK_FALL: f32 = 20.0;
+
+/// Bottom of the bars, in dBFS
+const FLOOR: f32 = -60.0;
+
+/// Running mean square over the last [`RMS_WINDOW`] seconds, and the held peak
+#[derive(Default)]
+struct Channel {
+    squares: VecDeque<f32>,
+    sum: f64,
+    peak: f32,
+}
+
+impl Channel {
+    fn push_block(&mut self, block: &[f32]) {
+        let sample_rate = crate::devices::sample_rate() as f32;
+        let window = (RMS_WINDOW * sample_rate) as usize;
+
+        for x in block {
+            while self.squares.len() >= window {
+                self.sum -= self.squares.pop_front().unwrap() as f64;
+            }
+
+            self.squares.push_back(x * x);
+            self.sum += (x * x) as f64;
+        }
+
+        let fall = 10.0f32.powf(-PEAK_FALL / 20.0 * block.len() as f32 / sample_rate);
+        let block_peak = block.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        self.peak = block_peak.max(self.peak * fall);
+    }
+
+    fn rms(&self) -> f32 {
+        if self.squares.is_empty() {
+            0.0
+        } else {
+            (self.sum.max(0.0) / self.squares.len() as f64).sqrt() as f32
+        }
+    }
+}
+
+pub(super) fn to_dbfs(level: f32) -> f32 {
+    if level > 0.0 {
+        20.0 * level.log10()
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// Where a level sits along a bar, from 0 at [`FLOOR`] to 1 at 0dBFS
+pub(super) fn bar_position(level: f32) -> f32 {
+    (1.0 - to_dbfs(level) / FLOOR).clamp(0.0, 1.0)
+}
+
+pub(super) fn level_colour(level: f32) -> Color32 {
+    let db = to_dbfs(level);
+
+    if db >= 0.0 {
+        Color32::from_rgb(0xf9, 0x26, 0x72)
+    } else if db >= -6.0 {
+        Color32::from_rgb(0xe6, 0xdb, 0x74)
+    } else {
+        Color32::from_rgb(0xa6, 0xe2, 0x2e)
+    }
+}
+
+/// Largest magnitude seen since the last reset, shown as a number with a
+/// button to reset it
+///
+/// Stored as the bits of a non-negative f32 so `fetch_max` works on it.
+#[derive(Default)]
+pub(super) struct PeakHold(AtomicU32);
+
+impl PeakHold {
+    pub(super) fn update(&self, block: &[f32]) {
+        let peak = block.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        self.0
+            .fetch_max(peak.to_bits(), std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub(super) fn render(&self, ui: &mut Ui) {
+        let peak = f32::from_bits(self.0.load(std::sync::atomic::Ordering::Relaxed));
+
+        ui.horizontal(|ui| {
+            let text = if peak > 0.0 {
+                format!("Peak: {:.1} dBFS", to_dbfs(peak))
+            } else {
+                "Peak: -inf dBFS".to_owned()
+            };
+
+            // anything over full scale will clip at the output
+            let text = if peak > 1.0 {
+                RichText::new(text).color(level_colour(peak))
+            } else {
+                RichText::new(text)
+            };
+
+            ui.label(text).on_hover_text_at_pointer("Held until reset");
+
+            if ui.button("Reset").clicked() {
+                self.0.store(0, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "left",
+    input = "right",
+    title = "Stereo meter",
+    cfg_name = "stereo_meter",
+    description = "Display the peak and RMS levels of the left and right channels, in dBFS",
+    custom_render = "StereoMeter::render",
+    reset_state = "StereoMeter::reset"
+)]
+pub struct StereoMeter {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    channels: Mutex<[Channel; 2]>,
+
+    rms: [Atomic<f32>; 2],
+    peak: [Atomic<f32>; 2],
+
+    peak_hold: PeakHold,
+}
+
+impl StereoMeter {
+    fn reset(&self) {
+        *self.channels.lock().unwrap() = Default::default();
+
+        for level in self.rms.iter().chain(&self.peak) {
+            level.store(0.0, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        ui.ctx().request_repaint_after(Duration::from_millis(30));
+
+        ui.horizontal(|ui| {
+            for ((name, rms), peak) in ["L", "R"].iter().zip(&self.rms).zip(&self.peak) {
+                let rms = rms.load(std::sync::atomic::Ordering::Relaxed);
+                let peak = peak.load(std::sync::atomic::Ordering::Relaxed);
+
+                ui.vertical(|ui| {
+                    let (_id, rect) = ui.allocate_space(vec2(16.0, 120.0));
+
+                    let height = |level: f32| rect.bottom() - bar_position(level) * rect.height();
+
+                    let painter = ui.painter();
+                    painter.rect_filled(rect, Rounding::ZERO, Color32::from_gray(32));
+                    painter.rect_filled(
+                        Rect::from_x_y_ranges(rect.x_range(), height(rms)..=rect.bottom()),
+                        Rounding::ZERO,
+                        level_colour(rms),
+                    );
+
+                    let y = height(peak);
+                    painter.line_segment(
+                        [pos2(rect.left(), y), pos2(rect.right(), y)],
+                        (2.0, level_colour(peak)),
+                    );
+
+                    ui.label(RichText::new(*name).family(FontFamily::Monospace).strong());
+                });
+
+                ui.vertical(|ui| {
+                    let db = |level: f32| {
+                        let db = to_dbfs(level);
+                        if db.is_finite() {
+                            format!("{db:>5.1}")
+                        } else {
+                            " -inf".to_owned()
+                        }
+                    };
+
+                    ui.label(
+                        RichText::new(format!("pk  {}", db(peak)))
+                            .family(FontFamily::Monospace)
+                            .color(level_colour(peak)),
+                    );
+                    ui.label(
+                        RichText::new(format!("rms {}", db(rms))).family(FontFamily::Monospace),
+                    );
+                });
+            }
+        });
+
+        self.peak_hold.render(ui);
+    }
+}
+
+impl SimpleNode for StereoMeter {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, _outputs: ProcessOutput) {
+        let blocks = [inputs.get("left").unwrap(), inputs.get("right").unwrap()];
+
+        let mut channels = self.channels.lock().unwrap();
+
+        for (i, (channel, block)) in channels.iter_mut().zip(blocks).enumerate() {
+            channel.push_block(block);
+            self.peak_hold.update(block);
+
+            self.rms[i].store(channel.rms(), std::sync::atomic::Ordering::Relaxed);
+            self.peak[i].store(channel.peak, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::Mutex;
+
+use crate::{ids::NodeId, node::*, util::Interpolation};
+use atomig::Atomic;
+use rivulet::{
+    circular_buffer::{Sink, Source},
+    splittable, View, ViewMut,
+};
+
+use super::reverb::{delay_line, delay_line_len, read_delayed};
+
+/// How much longer the right delay line is than the left, lengths that aren't
+/// simple ratios of each other keep the tails from lining up
+const RIGHT_RATIO: f32 = 1.13;
+
+/// Fraction of each side's delayed signal that is fed into the other side
+const CROSS_FEED: f32 = 0.3;
+
+type DelayLine = (splittable::View<Source<f32>>, Sink<f32>);
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "left",
+    input = "right",
+    output = "left",
+    output = "right",
+    title = "Stereo reverb",
+    cfg_name = "stereo_reverb",
+    description = "Repeat/ echo sounds with separate left and right tails",
+    after_settings_change = "StereoReverb::refresh_seconds",
+    reset_state = "StereoReverb::reset"
+)]
+pub struct StereoReverb {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "0.0..=1.0", suffix = "s"),
+        label = "Delay",
+        save,
+        default = "0.5"
+    )]
+    seconds: Atomic<f32>,
+
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    decay: Atomic<f32>,
+
+    /// How far apart the two tails are spread, 0 sums them to mono
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "1.0")]
+    width: Atomic<f32>,
+
+    #[dsp(select, save, default = "Interpolation::Linear")]
+    interpolation: Atomic<Interpolation>,
+
+    /// Length of the left delay line, in samples
+    #[dsp(default = "128")]
+    delay_len: Atomic<usize>,
+
+    /// Length of the right delay line, in samples
+    #[dsp(default = "128")]
+    right_len: Atomic<usize>,
+
+    #[dsp(default = "Mutex::new([delay_line(128), delay_line(128)])")]
+    buffers: Mutex<[DelayLine; 2]>,
+}
+
+impl StereoReverb {
+    /// Delay of each side in samples
+    fn delays(&self) -> [f32; 2] {
+        let seconds = self.seconds.load(std::sync::atomic::Ordering::Relaxed);
+        let left = seconds * crate::devices::sample_rate() as f32;
+
+        [left, left * RIGHT_RATIO]
+    }
+
+    fn refresh_seconds(&self) {
+        let num_samples = delay_line_len(self.delays()[0]);
+
+        // this is called after any setting changes, only start the tails over
+        // if the delay actually changed
+        if self
+            .delay_len
+            .swap(num_samples, std::sync::atomic::Ordering::Relaxed)
+            != num_samples
+        {
+            self.rebuild(num_samples);
+        }
+    }
+
+    fn reset(&self) {
+        self.rebuild(self.delay_len.load(std::sync::atomic::Ordering::Relaxed));
+    }
+
+    fn rebuild(&self, num_samples: usize) {
+        let right_samples = delay_line_len(self.delays()[1]).max(num_samples);
+        let lines = [delay_line(num_samples), delay_line(right_samples)];
+
+        let mut buffers = self.buffers.lock().unwrap();
+        *buffers = lines;
+        self.right_len
+            .store(right_samples, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl SimpleNode for StereoReverb {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let decay = self.decay.load(std::sync::atomic::Ordering::Relaxed);
+        let width = self.width.load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = [inputs.get("left").unwrap(), inputs.get("right").unwrap()];
+        let len = input[0].len();
+
+        let delays = self.delays();
+        let interpolation = self
+            .interpolation
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        let mut buffers = self.buffers.lock().unwrap();
+        let line_lens = [
+            self.delay_len.load(std::sync::atomic::Ordering::Relaxed),
+            self.right_len.load(std::sync::atomic::Ordering::Relaxed),
+        ];
+
+        // each buffer holds exactly one delay line's worth of samples, so take
+        // all of it to be able to read at any delay up to the full length
+        let mut granted = [false; 2];
+        for (((source, _), granted), line_len) in
+            buffers.iter_mut().zip(&mut granted).zip(line_lens)
+        {
+            *granted = line_len >= len && source.try_grant(line_len).unwrap_or(false);
+            if !*granted {
+                tracing::trace!("Reverb buffer is empty");
+            }
+        }
+
+        let mut output = [[0.0; MAX_BUF_SIZE]; 2];
+        for i in 0..len {
+            let [l, r] = [0, 1].map(|side| {
+                if !granted[side] {
+                    return 0.0;
+                }
+
+                let history = &buffers[side].0.view()[..line_lens[side]];
+                read_delayed(history, &output[side], i, delays[side], interpolation)
+            });
+
+            let wet_l = (l * (1.0 - CROSS_FEED) + r * CROSS_FEED) * decay;
+            let wet_r = (r * (1.0 - CROSS_FEED) + l * CROSS_FEED) * decay;
+
+            let mid = (wet_l + wet_r) * 0.5;
+            let side = (wet_l - wet_r) * 0.5 * width;
+
+            output[0][i] = input[0][i] + mid + side;
+            output[1][i] = input[1][i] + mid - side;
+        }
+
+        for (((source, sink), output), granted) in buffers.iter_mut().zip(&output).zip(granted) {
+            if granted {
+                source.release(len);
+            }
+
+            if sink.try_grant(len).unwrap_or(false) {
+                sink.view_mut()[..len].copy_from_slice(&output[..len]);
+                sink.release(len);
+            } else {
+                tracing::trace!("Not copying frame into reverb buffer");
+            }
+        }
+
+        drop(buffers);
+
+        outputs
+            .get("left")
+            .unwrap()
+            .copy_from_slice(&output[0][..len]);
+        outputs
+            .get("right")
+            .unwrap()
+            .copy_from_slice(&output[1][..len]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use crate::{ids::NodeId, node::*};
+use atomig::Atomic;
+use eframe::egui;
+use egui::{Color32, RichText, Ui};
+
+/// Onset frames per second, each block is one frame
+fn frame_rate() -> f32 {
+    crate::devices::sample_rate() as f32 / buf_size() as f32
+}
+
+/// Seconds of onsets the tempo is estimated over
+const WINDOW: f32 = 6.0;
+
+/// Blocks between estimates, about a sixth of a second
+const ESTIMATE_EVERY: usize = 64;
+
+/// Confidence below which the estimate is flagged as unreliable
+const RELIABLE: f32 = 0.3;
+
+#[derive(Default)]
+struct Analysis {
+    last_energy: f32,
+    /// How much the level rose in each recent frame
+    onsets: VecDeque<f32>,
+    blocks: usize,
+    /// Position in the current beat, for the ramp output
+    phase: f32,
+}
+
+impl Analysis {
+    /// Estimate the tempo in `min_bpm..2 * min_bpm` along with how confident
+    /// the estimate is, from 0 to 1
+    ///
+    /// Limiting the range to one octave keeps it from flipping between
+    /// double and half time.
+    fn estimate(&self, min_bpm: f32) -> Option<(f32, f32)> {
+        let n = self.onsets.len();
+        let mean = self.onsets.iter().sum::<f32>() / n as f32;
+        let onsets = self.onsets.iter().map(|x| x - mean).collect::<Vec<_>>();
+
+        // normalised for how much of the window overlaps at each lag
+        let correlation = |lag: usize| {
+            let sum = onsets[..n - lag]
+                .iter()
+                .zip(&onsets[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>();
+            sum * n as f32 / (n - lag) as f32
+        };
+
+        let max_lag = (60.0 * frame_rate() / min_bpm) as usize;
+        let min_lag = (60.0 * frame_rate() / (2.0 * min_bpm)).ceil() as usize;
+
+        // a couple of beats at least are needed to see any periodicity
+        if n < 2 * max_lag {
+            return None;
+        }
+
+        let energy = correlation(0);
+        if energy <= 1e-9 {
+            return None;
+        }
+
+        let scores = (min_lag - 1..=max_lag + 1)
+            .map(correlation)
+            .collect::<Vec<_>>();
+
+        let (best, &score) = scores[1..scores.len() - 1]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))?;
+
+        // fit a parabola through the peak and its neighbours for a fractional lag
+        let (prev, next) = (scores[best], scores[best + 2]);
+        let denom = prev - 2.0 * score + next;
+        let offset = if denom.abs() > 1e-9 {
+            (0.5 * (prev - next) / denom).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        let lag = (min_lag + best) as f32 + offset;
+
+        Some((60.0 * frame_rate() / lag, (score / energy).clamp(0.0, 1.0)))
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "ramp",
+    title = "Tempo",
+    cfg_name = "tempo",
+    description = "Estimate the tempo of a signal, outputs a 0..1 ramp for each beat",
+    custom_render = "Tempo::render",
+    reset_state = "Tempo::reset"
+)]
+pub struct Tempo {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "40.0..=120.0", suffix = " bpm"),
+        label = "Lowest tempo",
+        save,
+        default = "80.0"
+    )]
+    min_bpm: Atomic<f32>,
+
+    analysis: Mutex<Analysis>,
+
+    #[dsp(default = "120.0")]
+    bpm: Atomic<f32>,
+    confidence: Atomic<f32>,
+}
+
+impl Tempo {
+    fn reset(&self) {
+        *self.analysis.lock().unwrap() = Analysis::default();
+        self.confidence
+            .store(0.0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+
+        let bpm = self.bpm.load(std::sync::atomic::Ordering::Relaxed);
+        let confidence = self.confidence.load(std::sync::atomic::Ordering::Relaxed);
+
+        let text = RichText::new(format!("{bpm:.1} bpm")).heading();
+        ui.label(if confidence < RELIABLE {
+            text.color(Color32::from_rgb(0xe6, 0xdb, 0x74))
+        } else {
+            text
+        });
+
+        ui.add(
+            egui::ProgressBar::new(confidence)
+                .desired_width(120.0)
+                .text(if confidence < RELIABLE {
+                    "Unreliable"
+                } else {
+                    "Confidence"
+                }),
+        )
+        .on_hover_text_at_pointer(
+            "How strongly the onsets repeat at this tempo, music without a clear beat \
+             won't get far",
+        );
+    }
+}
+
+impl SimpleNode for Tempo {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let min_bpm = self.min_bpm.load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("ramp").unwrap();
+
+        let mut analysis = self.analysis.lock().unwrap();
+
+        // rises in log energy, so quiet and loud hits count about the same
+        let energy = input.iter().map(|x| x * x).sum::<f32>() / input.len() as f32;
+        let onset = ((energy + 1e-9).ln() - (analysis.last_energy + 1e-9).ln()).max(0.0);
+        analysis.last_energy = energy;
+
+        analysis.onsets.push_back(onset);
+        while analysis.onsets.len() > (WINDOW * frame_rate()) as usize {
+            analysis.onsets.pop_front();
+        }
+
+        analysis.blocks += 1;
+        if analysis.blocks % ESTIMATE_EVERY == 0 {
+            match analysis.estimate(min_bpm) {
+                Some((bpm, confidence)) => {
+                    self.bpm.store(bpm, std::sync::atomic::Ordering::Relaxed);
+                    self.confidence
+                        .store(confidence, std::sync::atomic::Ordering::Relaxed);
+                }
+                None => {
+                    self.confidence
+                        .store(0.0, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+        }
+
+        let step = self.bpm.load(std::sync::atomic::Ordering::Relaxed)
+            / 60.0
+            / crate::devices::sample_rate() as f32;
+
+        for out in output.iter_mut() {
+            *out = analysis.phase;
+            analysis.phase = (analysis.phase + step) % 1.0;
+        }
+    }
+}
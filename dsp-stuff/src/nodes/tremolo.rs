@@ -0,0 +1,97 @@
+use atomig::Atomic;
+use serde::{Deserialize, Serialize};
+
+use crate::{ids::NodeId, node::*};
+
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+)]
+#[repr(u8)]
+enum Shape {
+    Sine,
+    Triangle,
+    Square,
+}
+
+impl Shape {
+    /// The LFO's level at `phase`, between 0 and 1
+    fn level(self, phase: f32) -> f32 {
+        match self {
+            Shape::Sine => 0.5 - 0.5 * (phase * std::f32::consts::TAU).cos(),
+            Shape::Triangle => 1.0 - 2.0 * (phase - 0.5).abs(),
+            Shape::Square => {
+                if phase < 0.5 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(dsp_stuff_derive::DspNode)]
+#[dsp(
+    input = "in",
+    output = "out",
+    title = "Tremolo",
+    cfg_name = "tremolo",
+    description = "Sweep the volume of a signal up and down with an LFO"
+)]
+pub struct Tremolo {
+    #[dsp(id)]
+    id: NodeId,
+    #[dsp(inputs)]
+    inputs: PortStorage,
+    #[dsp(outputs)]
+    outputs: PortStorage,
+
+    #[dsp(
+        slider(range = "0.1..=20.0", logarithmic, suffix = " hz"),
+        save,
+        default = "5.0"
+    )]
+    rate: Atomic<f32>,
+
+    /// How far the volume dips, at 1 it goes all the way to silence
+    #[dsp(slider(range = "0.0..=1.0"), save, default = "0.5")]
+    depth: Atomic<f32>,
+
+    #[dsp(select, save, default = "Shape::Sine")]
+    shape: Atomic<Shape>,
+
+    clock: Atomic<f32>,
+}
+
+impl SimpleNode for Tremolo {
+    #[tracing::instrument(level = "TRACE", skip_all, fields(node_id = self.id.get()))]
+    fn process(&self, inputs: ProcessInput, mut outputs: ProcessOutput) {
+        let rate = self.rate.load(std::sync::atomic::Ordering::Relaxed);
+        let depth = self.depth.load(std::sync::atomic::Ordering::Relaxed);
+        let shape = self.shape.load(std::sync::atomic::Ordering::Relaxed);
+        let mut clock = self.clock.load(std::sync::atomic::Ordering::Relaxed);
+
+        let input = inputs.get("in").unwrap();
+        let output = outputs.get("out").unwrap();
+
+        let dt = rate / crate::devices::sample_rate() as f32;
+
+        for (x, out) in input.iter().zip(output.iter_mut()) {
+            let lfo = shape.level(clock);
+            clock = (clock + dt) % 1.0;
+
+            *out = x * (1.0 - depth + depth * lfo);
+        }
+
+        self.clock
+            .store(clock, std::sync::atomic::Ordering::Relaxed);
+    }
+}
@@ -1,6 +1,8 @@
 use eframe::egui;
 use std::{
     collections::HashMap,
+    io::BufWriter,
+    path::Path,
     sync::{Arc, Mutex},
 };
 
@@ -119,21 +121,100 @@ impl Node for WaveView {
 
         ui.label(format!("Samples per frame: {}", samples_this_render));
 
+        let to_save = ui
+            .button("Save image")
+            .clicked()
+            .then(|| view[..samples_this_render].to_vec());
+
         source.release(samples_this_render);
+        drop(source);
+
+        if let Some(samples) = to_save {
+            if let Some(path) = rfd::FileDialog::new()
+                .set_title("Save waveform")
+                .add_filter("image", &["png"])
+                .set_file_name("waveform.png")
+                .save_file()
+            {
+                tracing::info!("Saving waveform to {:?}", path);
+                if let Err(e) = save_png(&path, &samples) {
+                    tracing::warn!("Couldn't save waveform: {:?}", e);
+                }
+            }
+        }
     }
 }
 
+const IMAGE_WIDTH: usize = 800;
+const IMAGE_HEIGHT: usize = 300;
+
+/// Draw the waveform into an RGBA image, each column covers the range of the
+/// samples that fall into it
+fn rasterize(samples: &[f32]) -> Vec<u8> {
+    let mut pixels = [0x1b, 0x1b, 0x1b, 0xff].repeat(IMAGE_WIDTH * IMAGE_HEIGHT);
+
+    let mut plot = |x: usize, y: usize, luminance: u8| {
+        let idx = (y * IMAGE_WIDTH + x) * 4;
+        pixels[idx..idx + 3].fill(luminance);
+    };
+
+    let to_y = |v: f32| ((1.0 - v.clamp(-1.0, 1.0)) / 2.0 * (IMAGE_HEIGHT - 1) as f32) as usize;
+
+    for x in 0..IMAGE_WIDTH {
+        plot(x, to_y(0.0), 0x40);
+
+        if samples.is_empty() {
+            continue;
+        }
+
+        let lo = x * samples.len() / IMAGE_WIDTH;
+        // include the next column's first sample so the trace stays connected
+        let hi = ((x + 1) * samples.len() / IMAGE_WIDTH + 1)
+            .max(lo + 1)
+            .min(samples.len());
+
+        let (min, max) = samples[lo..hi]
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), v| {
+                (min.min(*v), max.max(*v))
+            });
+
+        for y in to_y(max)..=to_y(min) {
+            plot(x, y, 0xc4);
+        }
+    }
+
+    pixels
+}
+
+fn save_png(path: &Path, samples: &[f32]) -> color_eyre::Result<()> {
+    let file = std::fs::File::create(path)?;
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        IMAGE_WIDTH as u32,
+        IMAGE_HEIGHT as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rasterize(samples))?;
+
+    Ok(())
+}
+
 impl NodeStatic for WaveView {
-    fn restore(value: serde_json::Value) -> Self
+    fn restore(value: serde_json::Value) -> Result<Self, serde_json::Error>
     where
         Self: Sized,
     {
-        let cfg: WaveViewConfig = serde_json::from_value(value).unwrap();
+        let cfg: WaveViewConfig = serde_json::from_value(value)?;
 
         let mut this = Self::new(cfg.id);
         this.inputs = PortStorage::new(cfg.inputs);
 
-        this
+        Ok(this)
     }
 
     fn new(id: NodeId) -> Self {
@@ -0,0 +1,106 @@
+//! Remote control of node parameters over OSC
+//!
+//! A message to `/node/<id>/<param>` with a number sets that parameter of the
+//! node, and one with a string selects that option. Messages are handed to the
+//! ui to apply, so they're applied from the next frame.
+
+use std::{net::UdpSocket, sync::mpsc};
+
+use eframe::egui;
+use rosc::{OscPacket, OscType};
+
+use crate::ids::NodeId;
+
+pub struct OscMessage {
+    pub node: NodeId,
+    pub param: String,
+    pub value: OscValue,
+}
+
+pub enum OscValue {
+    Number(f64),
+    Choice(String),
+}
+
+/// Listen for OSC messages on `port`, waking up the ui as they arrive
+pub fn listen(port: u16, ctx: egui::Context) -> std::io::Result<mpsc::Receiver<OscMessage>> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))?;
+    let (sender, receiver) = mpsc::channel();
+
+    tracing::info!("Listening for OSC on port {port}");
+
+    std::thread::Builder::new()
+        .name("osc-listener".to_owned())
+        .spawn(move || {
+            let mut buf = [0u8; rosc::decoder::MTU];
+
+            loop {
+                let n = match socket.recv_from(&mut buf) {
+                    Ok((n, _)) => n,
+                    Err(e) => {
+                        tracing::warn!("Couldn't receive OSC: {:?}", e);
+                        continue;
+                    }
+                };
+
+                let packet = match rosc::decoder::decode_udp(&buf[..n]) {
+                    Ok((_, packet)) => packet,
+                    Err(e) => {
+                        tracing::debug!("Ignoring malformed OSC packet: {:?}", e);
+                        continue;
+                    }
+                };
+
+                let mut messages = Vec::new();
+                collect_messages(packet, &mut messages);
+
+                for message in messages {
+                    // the ui has gone away
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                }
+
+                ctx.request_repaint();
+            }
+        })?;
+
+    Ok(receiver)
+}
+
+fn collect_messages(packet: OscPacket, out: &mut Vec<OscMessage>) {
+    match packet {
+        OscPacket::Message(msg) => {
+            let Some(message) = parse(&msg) else {
+                tracing::debug!(addr = msg.addr, "Ignoring OSC message");
+                return;
+            };
+
+            out.push(message);
+        }
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                collect_messages(packet, out);
+            }
+        }
+    }
+}
+
+fn parse(msg: &rosc::OscMessage) -> Option<OscMessage> {
+    let mut parts = msg.addr.strip_prefix("/node/")?.splitn(2, '/');
+
+    let node = NodeId::new(parts.next()?.parse().ok()?);
+    let param = parts.next()?.to_owned();
+
+    let value = match msg.args.first()? {
+        OscType::Float(v) => OscValue::Number(f64::from(*v)),
+        OscType::Double(v) => OscValue::Number(*v),
+        OscType::Int(v) => OscValue::Number(f64::from(*v)),
+        OscType::Long(v) => OscValue::Number(*v as f64),
+        OscType::Bool(v) => OscValue::Number(if *v { 1.0 } else { 0.0 }),
+        OscType::String(v) => OscValue::Choice(v.clone()),
+        _ => return None,
+    };
+
+    Some(OscMessage { node, param, value })
+}
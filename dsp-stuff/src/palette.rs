@@ -0,0 +1,149 @@
+use eframe::egui;
+
+use crate::nodes;
+
+/// How many matches are listed at once
+const MAX_MATCHES: usize = 10;
+
+/// How well `query` matches `name`, if every character of the query appears in
+/// the name in order, ignoring case. Higher is a better match
+///
+/// Characters at the start of a word or following on from the last match
+/// score more, so "rev" ranks "Reverb" above "Stereo reverb"
+fn fuzzy_score(query: &str, name: &str) -> Option<u32> {
+    let mut score = 0;
+    let mut name_chars = name.chars().enumerate();
+    let mut prev_matched = None;
+    let mut prev_char = None;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        loop {
+            let (idx, c) = name_chars.next()?;
+            let at_word_start = prev_char.map_or(true, |p: char| !p.is_alphanumeric());
+            prev_char = Some(c);
+
+            if !c.to_lowercase().eq(q.to_lowercase()) {
+                continue;
+            }
+
+            score += 1;
+            if at_word_start {
+                score += 3;
+            }
+            if prev_matched.map_or(idx == 0, |p| p + 1 == idx) {
+                score += 2;
+            }
+            prev_matched = Some(idx);
+            break;
+        }
+    }
+
+    Some(score)
+}
+
+struct State {
+    query: String,
+    /// Where the palette was opened, and where the chosen node is put
+    position: egui::Pos2,
+    /// Index into the current matches
+    selected: usize,
+}
+
+/// A searchable list of node types, opened with ctrl+space
+#[derive(Default)]
+pub struct Palette {
+    state: Option<State>,
+}
+
+impl Palette {
+    pub fn open(&mut self, position: egui::Pos2) {
+        self.state = Some(State {
+            query: String::new(),
+            position,
+            selected: 0,
+        });
+    }
+
+    /// Indices into `nodes::NODES` matching the query, best first
+    fn matches(query: &str) -> Vec<usize> {
+        let mut matches = nodes::NODES
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, (name, _))| Some((idx, fuzzy_score(query, name)?)))
+            .collect::<Vec<_>>();
+
+        // stable, so equally good matches stay in menu order
+        matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        matches.truncate(MAX_MATCHES);
+
+        matches.into_iter().map(|(idx, _)| idx).collect()
+    }
+
+    /// Draw the palette if it's open, returning the index into `nodes::NODES`
+    /// of the chosen node and where to put it
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(usize, egui::Pos2)> {
+        let state = self.state.as_mut()?;
+
+        let matches = Self::matches(&state.query);
+        state.selected = state.selected.min(matches.len().saturating_sub(1));
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+
+        if up {
+            state.selected = state.selected.saturating_sub(1);
+        }
+        if down && state.selected + 1 < matches.len() {
+            state.selected += 1;
+        }
+
+        let mut chosen = if enter {
+            matches.get(state.selected).copied()
+        } else {
+            None
+        };
+
+        let r = egui::Area::new(egui::Id::new("node_palette"))
+            .fixed_pos(state.position)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(200.0);
+
+                    let r =
+                        ui.add(egui::TextEdit::singleline(&mut state.query).hint_text("Add node"));
+                    r.request_focus();
+
+                    // the matches listed are from before the edit
+                    if r.changed() {
+                        state.selected = 0;
+                    }
+
+                    if matches.is_empty() {
+                        ui.weak("No matching nodes");
+                    }
+
+                    for (i, idx) in matches.iter().enumerate() {
+                        let (name, _) = nodes::NODES[*idx];
+                        if ui.selectable_label(i == state.selected, name).clicked() {
+                            chosen = Some(*idx);
+                        }
+                    }
+                });
+            });
+
+        let position = state.position;
+
+        if chosen.is_some() || escape || r.response.clicked_elsewhere() {
+            self.state = None;
+        }
+
+        chosen.map(|idx| (idx, position))
+    }
+}
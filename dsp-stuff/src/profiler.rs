@@ -0,0 +1,132 @@
+use std::{
+    future::Future,
+    sync::atomic::AtomicU64,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+
+use crate::{devices, node::buf_size};
+
+/// Nanoseconds spent running node tasks, summed over every node
+static DSP_BUSY: AtomicU64 = AtomicU64::new(0);
+
+/// Run a future, counting the time spent polling it (not the time spent
+/// waiting on it) as DSP time
+///
+/// The time spent is also returned, in nanoseconds
+pub async fn timed<F: Future>(f: F) -> (F::Output, u64) {
+    let mut f = std::pin::pin!(f);
+    let mut busy = 0;
+
+    let r = std::future::poll_fn(|cx| {
+        let started = Instant::now();
+        let r = f.as_mut().poll(cx);
+        let elapsed = started.elapsed().as_nanos() as u64;
+        DSP_BUSY.fetch_add(elapsed, std::sync::atomic::Ordering::Relaxed);
+        busy += elapsed;
+        r
+    })
+    .await;
+
+    (r, busy)
+}
+
+/// Each block's time makes up 1/this of a node's average, lower reacts faster
+/// but jitters more
+const BLOCK_TIME_SMOOTHING: u64 = 16;
+
+/// Fold the time taken for one block into a node's moving average
+pub fn record_block_time(average: &AtomicU64, nanos: u64) {
+    let old = average.load(std::sync::atomic::Ordering::Relaxed);
+    let new = if old == 0 {
+        nanos
+    } else {
+        old - old / BLOCK_TIME_SMOOTHING + nanos / BLOCK_TIME_SMOOTHING
+    };
+    average.store(new, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// How long a block of samples lasts at the current sample rate, nodes taking
+/// longer than this to process one can't keep up
+pub fn block_budget() -> Duration {
+    Duration::from_secs_f64(buf_size() as f64 / devices::sample_rate() as f64)
+}
+
+/// How often the DSP load is recalculated
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An overlay showing where the time is going, off by default
+pub struct Profiler {
+    visible: bool,
+
+    last_sample: Instant,
+    last_busy: u64,
+
+    /// Fraction of a single core spent in node tasks over the last interval
+    dsp_load: f32,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            last_sample: Instant::now(),
+            last_busy: DSP_BUSY.load(std::sync::atomic::Ordering::Relaxed),
+            dsp_load: 0.0,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    fn sample(&mut self) {
+        let elapsed = self.last_sample.elapsed();
+        if elapsed < SAMPLE_INTERVAL {
+            return;
+        }
+
+        let busy = DSP_BUSY.load(std::sync::atomic::Ordering::Relaxed);
+        self.dsp_load = (busy - self.last_busy) as f32 / elapsed.as_nanos() as f32;
+        self.last_busy = busy;
+        self.last_sample = Instant::now();
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, nodes: usize, running: usize, links: usize) {
+        if !self.visible {
+            return;
+        }
+
+        self.sample();
+        ctx.request_repaint_after(Duration::from_millis(250));
+
+        let outputs = devices::all_output_stats();
+        let callback_load = outputs
+            .iter()
+            .map(|s| s.load.load(std::sync::atomic::Ordering::Relaxed))
+            .fold(0.0, f32::max);
+        let dropouts: u64 = outputs
+            .iter()
+            .map(|s| s.underruns.load(std::sync::atomic::Ordering::Relaxed))
+            .sum();
+
+        egui::Area::new(egui::Id::new("profiler"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(format!("DSP load: {:.1}%", self.dsp_load * 100.0))
+                        .on_hover_text_at_pointer("Percentage of one core");
+                    ui.label(format!(
+                        "Output callback load: {:.1}%",
+                        callback_load * 100.0
+                    ));
+                    ui.label(format!("Nodes: {running} running, {nodes} total"));
+                    ui.label(format!("Links: {links}"));
+                    ui.label(format!("Outputs: {}", outputs.len()));
+                    ui.label(format!("Dropouts: {dropouts}"));
+                });
+            });
+    }
+}
@@ -1,19 +1,28 @@
 use crate::{
+    automation::{AutomationLane, LaneState, Transport},
     devices,
     ids::{LinkId, NodeId, PortId},
-    node::{Node, Perform},
+    morph::{Morph, Snapshot},
+    node::{
+        buf_size, collect_and_average, input_mixing, set_input_mixing, signal_input, BusEnd,
+        InputMixing, Node, NodeInputs, NodeOutputs, Perform, MAX_BUF_SIZE, MIN_BUF_SIZE,
+    },
     nodes::{self, Nodes},
-    theme::{self, Theme},
-    Params,
+    palette::Palette,
+    profiler::{self, Profiler},
+    settings::Settings,
+    theme::{self, Theme, ThemeEditor},
+    Params, APP_NAME,
 };
 use eframe::egui;
 use eframe::CreationContext;
 use eframe::egui::{pos2, UiStackInfo, ViewportCommand, Visuals};
 use egui_nodes::{AttributeFlags, ColorStyle, LinkArgs, NodeArgs, NodeConstructor, PinArgs};
 use itertools::Itertools;
+use once_cell::sync::{Lazy, OnceCell};
 use rivulet::{
     circular_buffer::{Sink, Source},
-    splittable, SplittableView,
+    splittable, SplittableView, View, ViewMut,
 };
 use serde::{Deserialize, Serialize};
 use std::{
@@ -21,38 +30,132 @@ use std::{
     collections::{HashMap, HashSet},
     io::Write,
     ops::DerefMut,
+    path::PathBuf,
     rc::Rc,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64},
+        Arc,
+    },
+};
+use tokio::{
+    runtime::Handle,
+    sync::{Mutex, OwnedMutexGuard},
 };
-use tokio::{runtime::Handle, sync::Mutex};
 
 pub struct UiContext {
     runtime: tokio::runtime::Runtime,
 
-    theme: &'static Theme,
+    theme: Arc<Theme>,
+    /// Themes saved from the theme editor, by name
+    custom_themes: Vec<(String, Theme)>,
+    theme_editor: ThemeEditor,
+
+    settings: Settings,
 
     node_ctx: egui_nodes::Context,
 
     links: HashMap<LinkId, LinkInstance>,
+    /// Links from each send to each return on the same bus, by the send and
+    /// return nodes, these aren't drawn or saved
+    bus_links: HashMap<(NodeId, NodeId), LinkInstance>,
 
     inputs: HashMap<(NodeId, PortId), HashSet<LinkId>>,
     outputs: HashMap<(NodeId, PortId), HashSet<LinkId>>,
 
     nodes: HashMap<NodeId, NodeInstance>,
+
+    transport: Transport,
+    automation: AutomationLane,
+    morph: Morph,
+    input_mixing: InputMixing,
+    profiler: Profiler,
+    palette: Palette,
+
+    /// Runs every node when the graph is scheduled, see
+    /// `Settings::scheduled_graph`
+    scheduler: Option<Scheduler>,
+    /// The graph changed while scheduled, and needs scheduling again at the
+    /// end of the frame
+    reschedule: bool,
+
+    show_parameters: bool,
+    parameter_filter: String,
+
+    /// When the graph was last autosaved, see `Settings::autosave`
+    last_autosave: std::time::Instant,
+
+    /// The sample rate the nodes last worked out their settings at
+    sample_rate: u32,
+
+    /// The graph before each structural edit, most recent last
+    undo: Vec<DSPConfig>,
+    /// Graphs undone since the last edit, most recent last
+    redo: Vec<DSPConfig>,
+
+    /// Nodes copied with ctrl+c, and the links between them
+    copied_nodes: Vec<NodeConfig>,
+    copied_links: Vec<LinkConfig>,
+
+    /// A link that was refused as it would close a loop, and when, it's shown
+    /// in red for a moment so it's clear why nothing was linked
+    rejected_link: Option<(PortId, PortId, std::time::Instant)>,
+
+    /// Where the canvas was right clicked, while the menu of nodes to add there
+    /// is open
+    canvas_menu: Option<egui::Pos2>,
+
+    /// Parameter changes received over OSC, if listening for them
+    #[cfg(feature = "osc")]
+    osc: Option<std::sync::mpsc::Receiver<crate::osc::OscMessage>>,
 }
 
+/// How far pasted nodes are moved from the nodes they're copies of
+const PASTE_OFFSET: egui::Vec2 = egui::vec2(40.0, 40.0);
+
+/// Nodes added from the menu bar are stepped by this from the first free spot
+/// so they don't stack up on each other
+const CASCADE_OFFSET: egui::Vec2 = egui::vec2(30.0, 30.0);
+
+/// How many edits can be undone
+const UNDO_LIMIT: usize = 50;
+
+/// How long a refused link stays on screen
+const REJECTED_LINK_FLASH: std::time::Duration = std::time::Duration::from_secs(1);
+
 #[derive(Serialize, Deserialize)]
 struct DSPConfig {
     nodes: Vec<NodeConfig>,
     links: Vec<LinkConfig>,
+    #[serde(default)]
+    automation: AutomationLane,
+    #[serde(default)]
+    morph: Morph,
+    #[serde(default)]
+    input_mixing: InputMixing,
 }
 
 impl UiContext {
     pub fn new(cc: &CreationContext, params: &Params) -> Self {
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .thread_name("dsp-runtime-worker")
-            .build()
-            .unwrap();
+        // settings are needed before the runtime exists, as they decide the
+        // priority of its threads
+        let settings: Settings = cc
+            .storage
+            .and_then(|s| s.get_string("settings"))
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        settings.apply();
+
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.thread_name("dsp-runtime-worker");
+
+        // with a dedicated pool only its threads are audio threads
+        if settings.dedicated_dsp_threads {
+            start_dsp_runtime();
+        } else {
+            builder.on_thread_start(devices::elevate_current_thread);
+        }
+
+        let runtime = builder.build().unwrap();
 
         let mut node_ctx = egui_nodes::Context::default();
         node_ctx.attribute_flag_push(AttributeFlags::EnableLinkDetachWithDragClick);
@@ -60,71 +163,423 @@ impl UiContext {
         let mut this = Self {
             runtime,
             node_ctx,
-            theme: &theme::MONOKAI,
+            theme: Arc::new(theme::MONOKAI.clone()),
+            custom_themes: cc
+                .storage
+                .and_then(|s| s.get_string("custom_themes"))
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            theme_editor: ThemeEditor::default(),
+            settings,
             links: HashMap::new(),
+            bus_links: HashMap::new(),
             inputs: HashMap::new(),
             outputs: HashMap::new(),
             nodes: HashMap::new(),
+            transport: Transport::new(),
+            automation: AutomationLane::default(),
+            morph: Morph::default(),
+            input_mixing: InputMixing::default(),
+            profiler: Profiler::new(),
+            palette: Palette::default(),
+            scheduler: None,
+            reschedule: false,
+            show_parameters: false,
+            parameter_filter: String::new(),
+            last_autosave: std::time::Instant::now(),
+            sample_rate: devices::sample_rate(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            copied_nodes: Vec::new(),
+            copied_links: Vec::new(),
+            rejected_link: None,
+            canvas_menu: None,
+            #[cfg(feature = "osc")]
+            osc: params.osc_port.and_then(|port| {
+                match crate::osc::listen(port, cc.egui_ctx.clone()) {
+                    Ok(osc) => Some(osc),
+                    Err(e) => {
+                        tracing::error!("Couldn't listen for OSC on port {port}: {:?}", e);
+                        None
+                    }
+                }
+            }),
         };
 
-        this.update_theme(&theme::MONOKAI);
+        let theme = cc
+            .storage
+            .and_then(|s| s.get_string("theme"))
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_else(|| theme::MONOKAI.clone());
+        this.update_theme(Arc::new(theme));
+
+        if !params.clean {
+            let cfg = cc
+                .storage
+                .and_then(|s| s.get_string("graph_state"))
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .or_else(|| {
+                    tracing::info!("Couldn't restore the saved graph, trying the autosave");
+                    read_autosave()
+                });
 
-        if let Some(s) = cc.storage {
-            if !params.clean {
-                if let Some(cfg) = s
-                    .get_string("graph_state")
-                    .and_then(|s| serde_json::from_str(&s).ok())
-                {
-                    let _guard = this.runtime.enter();
-                    this.restore_config(cfg);
-                }
+            if let Some(cfg) = cfg {
+                let _guard = this.runtime.enter();
+                this.restore_config(cfg);
             }
         }
 
         this
     }
 
+    /// Apply the parameter changes received over OSC since the last frame
+    #[cfg(feature = "osc")]
+    fn apply_osc(&self) {
+        let Some(osc) = &self.osc else {
+            return;
+        };
+
+        for message in osc.try_iter() {
+            let Some(node) = self.nodes.get(&message.node) else {
+                tracing::debug!(node = ?message.node, "OSC message for an unknown node");
+                continue;
+            };
+
+            let applied = match &message.value {
+                crate::osc::OscValue::Number(v) => node.instance.set_param(&message.param, *v),
+                crate::osc::OscValue::Choice(v) => node.instance.set_choice(&message.param, v),
+            };
+
+            if !applied {
+                tracing::debug!(
+                    node = ?message.node,
+                    param = message.param,
+                    "OSC message for an unknown parameter"
+                );
+            }
+        }
+    }
+
+    /// Save the graph to the autosave file if it's been long enough since the
+    /// last time
+    fn autosave(&mut self, ctx: &egui::Context) {
+        if !self.settings.autosave {
+            return;
+        }
+
+        let interval =
+            std::time::Duration::from_secs(self.settings.autosave_interval.max(1) as u64);
+        let elapsed = self.last_autosave.elapsed();
+
+        if elapsed < interval {
+            // the ui might otherwise sit idle past the deadline
+            ctx.request_repaint_after(interval - elapsed);
+            return;
+        }
+
+        self.last_autosave = std::time::Instant::now();
+
+        let Some(path) = autosave_path() else {
+            tracing::warn!("Nowhere to autosave to");
+            return;
+        };
+
+        // written alongside then moved over, so crashing mid write leaves the
+        // previous autosave intact
+        let tmp = path.with_extension("json.tmp");
+        let buf = serde_json::to_vec_pretty(&self.save_config()).unwrap();
+
+        if let Err(e) = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&tmp, buf))
+            .and_then(|_| std::fs::rename(&tmp, &path))
+        {
+            tracing::warn!("Couldn't autosave to {:?}: {:?}", path, e);
+        } else {
+            tracing::debug!("Autosaved to {:?}", path);
+        }
+    }
+
+    /// Remember the graph as it is so the edit about to be made can be undone
+    ///
+    /// Only structural edits (nodes and links coming and going) are recorded,
+    /// moving nodes and changing parameters aren't.
+    fn checkpoint(&mut self) {
+        if self.undo.len() >= UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+
+        self.undo.push(self.save_config());
+        self.redo.clear();
+    }
+
+    fn undo(&mut self) {
+        if let Some(cfg) = self.undo.pop() {
+            tracing::info!("Undoing");
+            self.redo.push(self.save_config());
+            self.restore_config(cfg);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(cfg) = self.redo.pop() {
+            tracing::info!("Redoing");
+            self.undo.push(self.save_config());
+            self.restore_config(cfg);
+        }
+    }
+
     fn save_config(&self) -> DSPConfig {
         let nodes = self.nodes.values().map(|n| n.save()).collect();
         let links = self.links.values().map(|l| l.save()).collect();
 
-        DSPConfig { nodes, links }
+        DSPConfig {
+            nodes,
+            links,
+            automation: self.automation.clone(),
+            morph: self.morph.clone(),
+            input_mixing: self.input_mixing,
+        }
+    }
+
+    /// The graph as a Graphviz (dot) diagram, nodes are labelled with their
+    /// ids so the diagram can be matched up with the editor
+    fn diagram(&self) -> String {
+        use std::fmt::Write;
+
+        // these are special inside record labels
+        let escape = |s: &str| {
+            s.chars().fold(String::new(), |mut acc, c| {
+                if "{}|<>\"\\".contains(c) {
+                    acc.push('\\');
+                }
+                acc.push(c);
+                acc
+            })
+        };
+
+        let ports = |storage: &crate::node::PortStorage| {
+            storage
+                .get_all()
+                .into_iter()
+                .sorted()
+                .map(|(name, id)| format!("<p{}> {}", id.get(), escape(&name)))
+                .join("|")
+        };
+
+        let mut out = String::from("digraph patch {\n    rankdir=LR;\n    node [shape=record];\n");
+
+        for node in self.nodes.values().sorted_by_key(|n| n.id.get()) {
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{{{{{}}}|{} ({})|{{{}}}}}\"];",
+                node.id.get(),
+                ports(node.instance.inputs()),
+                escape(node.title()),
+                node.id.get(),
+                ports(node.instance.outputs()),
+            );
+        }
+
+        for link in self.links.values().sorted_by_key(|l| (l.lhs.1, l.rhs.1)) {
+            let _ = writeln!(
+                out,
+                "    n{}:p{} -> n{}:p{};",
+                link.lhs.0.get(),
+                link.lhs.1.get(),
+                link.rhs.0.get(),
+                link.rhs.1.get(),
+            );
+        }
+
+        // bus links aren't drawn in the editor, so they're set apart here too
+        for link in self
+            .bus_links
+            .values()
+            .sorted_by_key(|l| (l.lhs.1, l.rhs.1))
+        {
+            let _ = writeln!(
+                out,
+                "    n{}:p{} -> n{}:p{} [style=dashed];",
+                link.lhs.0.get(),
+                link.lhs.1.get(),
+                link.rhs.0.get(),
+                link.rhs.1.get(),
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn copy_patch(&self) {
+        let cfg = serde_json::to_string_pretty(&self.save_config()).unwrap();
+
+        if let Err(e) = arboard::Clipboard::new().and_then(|mut c| c.set_text(cfg)) {
+            tracing::warn!("Couldn't copy the patch to the clipboard: {:?}", e);
+        }
+    }
+
+    /// Replace the patch with one from the clipboard, leaving the current patch
+    /// alone if the clipboard doesn't hold one
+    fn paste_patch(&mut self) {
+        let text = match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!("Couldn't read the clipboard: {:?}", e);
+                return;
+            }
+        };
+
+        let cfg: DSPConfig = match serde_json::from_str(&text) {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!("The clipboard doesn't contain a patch: {}", e);
+                return;
+            }
+        };
+
+        // an unknown node type means the patch is probably from a newer
+        // version, so leave the current patch alone rather than half
+        // restoring it
+        if let Some(node) = cfg
+            .nodes
+            .iter()
+            .find(|n| !nodes::RESTORE.iter().any(|(name, _)| *name == n.typename))
+        {
+            tracing::warn!(
+                "The pasted patch has an unknown node type: {}",
+                node.typename
+            );
+            return;
+        }
+
+        self.checkpoint();
+        self.restore_config(cfg);
     }
 
     fn restore_config(&mut self, cfg: DSPConfig) {
+        self.stop_scheduler();
+
         for node in self.nodes.values_mut() {
             node.stop()
         }
 
         self.links.clear();
+        self.bus_links.clear();
         self.inputs.clear();
         self.outputs.clear();
         self.nodes.clear();
 
-        for node in cfg.nodes {
-            let restored = NodeInstance::restore(node);
+        self.insert_restored_patch(cfg.nodes, cfg.links);
 
-            for port in restored.instance.inputs().get_all().values() {
-                self.inputs.entry((restored.id, *port)).or_default();
+        self.link_buses();
+
+        self.automation = cfg.automation;
+        self.automation.resume(&mut self.transport);
+
+        self.morph = cfg.morph;
+
+        self.input_mixing = cfg.input_mixing;
+        set_input_mixing(self.input_mixing);
+
+        self.update_all();
+    }
+
+    /// Restore some nodes and the links between them, skipping any node that
+    /// can't be restored and any link to a port that doesn't exist
+    fn insert_restored_patch(&mut self, nodes: Vec<NodeConfig>, links: Vec<LinkConfig>) {
+        for node in nodes {
+            match NodeInstance::restore(node) {
+                Ok(restored) => self.insert_restored(restored),
+                Err(e) => tracing::error!("Skipping a node that couldn't be restored: {}", e),
             }
+        }
 
-            for port in restored.instance.outputs().get_all().values() {
-                self.outputs.entry((restored.id, *port)).or_default();
+        for link in links {
+            if !self.outputs.contains_key(&link.lhs) || !self.inputs.contains_key(&link.rhs) {
+                tracing::error!(
+                    lhs = ?link.lhs,
+                    rhs = ?link.rhs,
+                    "Skipping a link to a port that doesn't exist"
+                );
+                continue;
             }
 
-            self.nodes.insert(restored.id, restored);
+            self.add_link(link.lhs, link.rhs);
         }
+    }
 
-        for link in cfg.links {
-            self.add_link(link.lhs, link.rhs);
+    fn insert_restored(&mut self, restored: NodeInstance) {
+        for port in restored.instance.inputs().get_all().values() {
+            self.inputs.entry((restored.id, *port)).or_default();
         }
 
-        self.update_all();
+        for port in restored.instance.outputs().get_all().values() {
+            self.outputs.entry((restored.id, *port)).or_default();
+        }
+
+        self.nodes.insert(restored.id, restored);
+    }
+
+    /// Remember the selected nodes, and the links between them, for pasting
+    fn copy_selected(&mut self) {
+        let selected = self
+            .node_ctx
+            .get_selected_nodes()
+            .into_iter()
+            .map(NodeId::new)
+            .collect::<HashSet<_>>();
+
+        if selected.is_empty() {
+            return;
+        }
+
+        self.copied_nodes = selected
+            .iter()
+            .filter_map(|id| self.nodes.get(id))
+            .map(|n| n.save())
+            .collect();
+        self.copied_links = self
+            .links
+            .values()
+            .filter(|l| selected.contains(&l.lhs.0) && selected.contains(&l.rhs.0))
+            .map(|l| l.save())
+            .collect();
+
+        tracing::info!("Copied {} nodes", self.copied_nodes.len());
+    }
+
+    /// Add copies of the copied nodes next to the originals
+    fn paste_copied(&mut self) {
+        if self.copied_nodes.is_empty() {
+            return;
+        }
+
+        self.checkpoint();
+
+        let (nodes, links) = duplicate_nodes(&self.copied_nodes, &self.copied_links, PASTE_OFFSET);
+        tracing::info!("Pasting {} nodes", nodes.len());
+
+        let ids = nodes.iter().map(|n| n.id).collect_vec();
+
+        // so pasting again puts the next copies next to these ones
+        self.copied_nodes = nodes.clone();
+        self.copied_links = links.clone();
+
+        self.insert_restored_patch(nodes, links);
+
+        self.link_buses();
+
+        for id in ids.into_iter().filter(|id| self.nodes.contains_key(id)) {
+            self.restart_node(id);
+        }
+
+        devices::invoke(devices::DeviceCommand::TriggerResync);
     }
 
     fn add_link(&mut self, lhs: (NodeId, PortId), rhs: (NodeId, PortId)) {
         let id = LinkId::generate();
-        let inst = LinkInstance::new(id, lhs, rhs);
+        let inst = LinkInstance::new(id, lhs, rhs, self.settings.link_buffer_size);
 
         tracing::info!(link = ?inst, "Adding link");
 
@@ -133,7 +588,146 @@ impl UiContext {
         self.inputs.entry(rhs).or_default().insert(id);
     }
 
+    /// Link two ports, and their stereo partners too if they both have one
+    ///
+    /// The partners are left alone if the partner input is already connected
+    /// to something, as that's presumably deliberate.
+    fn add_link_and_partner(&mut self, lhs: (NodeId, PortId), rhs: (NodeId, PortId)) {
+        self.add_link(lhs, rhs);
+
+        let (Some(lhs_node), Some(rhs_node)) = (self.nodes.get(&lhs.0), self.nodes.get(&rhs.0))
+        else {
+            return;
+        };
+
+        let (Some(lhs_partner), Some(rhs_partner)) = (
+            lhs_node.instance.outputs().stereo_partner(lhs.1),
+            rhs_node.instance.inputs().stereo_partner(rhs.1),
+        ) else {
+            return;
+        };
+
+        let lhs_partner = (lhs.0, lhs_partner);
+        let rhs_partner = (rhs.0, rhs_partner);
+
+        if self
+            .inputs
+            .get(&rhs_partner)
+            .is_some_and(|links| !links.is_empty())
+        {
+            tracing::debug!(
+                ?rhs_partner,
+                "Not linking stereo partner, the input is already connected"
+            );
+            return;
+        }
+
+        self.add_link(lhs_partner, rhs_partner);
+    }
+
+    /// Link every send to every return on the same bus, returning the nodes
+    /// whose bus links changed
+    fn link_buses(&mut self) -> HashSet<NodeId> {
+        let ends = self
+            .nodes
+            .values()
+            .filter_map(|n| Some((n.id, n.instance.bus()?)))
+            .collect_vec();
+
+        let wanted = ends
+            .iter()
+            .filter_map(|(id, end)| match end {
+                BusEnd::Send(bus) => Some((*id, *bus)),
+                BusEnd::Return(_) => None,
+            })
+            .flat_map(|(send, bus)| {
+                ends.iter()
+                    .filter(move |(_, end)| *end == BusEnd::Return(bus))
+                    .map(move |(ret, _)| (send, *ret))
+            })
+            .collect::<HashSet<_>>();
+
+        let mut changed = HashSet::new();
+
+        self.bus_links.retain(|pair, _| {
+            let keep = wanted.contains(pair);
+            if !keep {
+                changed.extend([pair.0, pair.1]);
+            }
+            keep
+        });
+
+        for (send, ret) in wanted {
+            if self.bus_links.contains_key(&(send, ret)) {
+                continue;
+            }
+
+            let (Some(lhs), Some(rhs)) = (
+                self.nodes[&send]
+                    .instance
+                    .outputs()
+                    .get_id(nodes::bus::BUS_PORT),
+                self.nodes[&ret]
+                    .instance
+                    .inputs()
+                    .get_id(nodes::bus::BUS_PORT),
+            ) else {
+                continue;
+            };
+
+            let inst = LinkInstance::new(
+                LinkId::generate(),
+                (send, lhs),
+                (ret, rhs),
+                self.settings.link_buffer_size,
+            );
+
+            tracing::info!(link = ?inst, "Adding bus link");
+
+            self.bus_links.insert((send, ret), inst);
+            changed.extend([send, ret]);
+        }
+
+        changed
+    }
+
+    /// Every link, including the bus links
+    fn all_links(&self) -> impl Iterator<Item = &LinkInstance> {
+        self.links.values().chain(self.bus_links.values())
+    }
+
     fn update_all(&mut self) {
+        self.stop_scheduler();
+
+        let order = if self.settings.scheduled_graph {
+            self.topological_order()
+        } else {
+            None
+        };
+
+        if let Some(order) = order {
+            for node in self.nodes.values_mut() {
+                node.stop();
+            }
+
+            let nodes = order
+                .into_iter()
+                .map(|id| {
+                    let inputs = self.compute_inputs_for(id);
+                    let outputs = self.compute_outputs_for(id);
+                    let runner = self.nodes[&id].block_runner(
+                        inputs.iter().map(|v| v.len()).sum(),
+                        outputs.iter().map(|v| v.len()).sum(),
+                    );
+
+                    (runner, inputs, outputs)
+                })
+                .collect_vec();
+
+            self.scheduler = Some(Scheduler::start(nodes));
+            return;
+        }
+
         let calculated = self
             .nodes
             .values()
@@ -150,12 +744,116 @@ impl UiContext {
         }
     }
 
+    /// Switch every node over to blocks of `size` samples
+    ///
+    /// Nodes assume every block is the same size, so the whole graph is stopped
+    /// while it changes. Delay lines are sized to fit a block, so node state is
+    /// reset as well.
+    fn set_block_size(&mut self, size: usize) {
+        self.stop_scheduler();
+        for node in self.nodes.values_mut() {
+            node.stop();
+        }
+
+        crate::node::set_buf_size(size);
+        tracing::info!(block_size = crate::node::buf_size(), "Changed block size");
+
+        for node in self.nodes.values() {
+            node.instance.reset_state();
+        }
+
+        self.rebuild_links();
+    }
+
+    /// Recreate the buffers of every link, for when the link buffer size changes
+    fn rebuild_links(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.stop();
+        }
+
+        let size = self.settings.link_buffer_size;
+        for link in self.links.values_mut().chain(self.bus_links.values_mut()) {
+            *link = LinkInstance::new(link.id, link.lhs, link.rhs, size);
+        }
+
+        self.update_all();
+        devices::invoke(devices::DeviceCommand::TriggerResync);
+    }
+
+    fn stop_scheduler(&mut self) {
+        if let Some(mut scheduler) = self.scheduler.take() {
+            scheduler.stop();
+        }
+    }
+
+    /// The nodes ordered so that each comes after every node feeding it, or
+    /// `None` if the graph has a cycle
+    fn topological_order(&self) -> Option<Vec<NodeId>> {
+        let mut incoming = self
+            .nodes
+            .keys()
+            .map(|id| (*id, 0usize))
+            .collect::<HashMap<_, _>>();
+
+        for link in self.all_links() {
+            if let Some(n) = incoming.get_mut(&link.rhs.0) {
+                *n += 1;
+            }
+        }
+
+        let mut ready = incoming
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(id, _)| *id)
+            .collect_vec();
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(id) = ready.pop() {
+            order.push(id);
+
+            for link in self.all_links().filter(|l| l.lhs.0 == id) {
+                if let Some(n) = incoming.get_mut(&link.rhs.0) {
+                    *n -= 1;
+                    if *n == 0 {
+                        ready.push(link.rhs.0);
+                    }
+                }
+            }
+        }
+
+        (order.len() == incoming.len()).then_some(order)
+    }
+
     fn restart_node(&mut self, node: NodeId) {
+        // the whole graph is rescheduled once editing is done, so that deleted
+        // nodes and links are gone by then
+        if self.settings.scheduled_graph {
+            self.reschedule = true;
+            return;
+        }
+
         let inputs = self.compute_inputs_for(node);
         let outpus = self.compute_outputs_for(node);
         self.nodes.get_mut(&node).unwrap().restart(inputs, outpus);
     }
 
+    /// Whether a link from `from` to `to` would close a loop of nodes, which
+    /// stalls every node in it as each waits on the others
+    fn closes_cycle(&self, from: NodeId, to: NodeId) -> bool {
+        let links = self.all_links().map(|l| (l.lhs.0, l.rhs.0)).collect_vec();
+
+        closes_cycle(
+            &links,
+            |id| {
+                self.nodes
+                    .get(&id)
+                    .is_some_and(|n| n.instance.breaks_cycles())
+            },
+            from,
+            to,
+        )
+    }
+
     fn compute_inputs_for(
         &self,
         node: NodeId,
@@ -182,6 +880,12 @@ impl UiContext {
             })
             .collect::<HashMap<_, _>>();
 
+        for link in self.bus_links.values().filter(|l| l.rhs.0 == node) {
+            v.entry(link.rhs.1)
+                .or_default()
+                .push(Arc::clone(&link.source));
+        }
+
         storage
             .get_idxs()
             .into_iter()
@@ -214,6 +918,12 @@ impl UiContext {
             })
             .collect::<HashMap<_, _>>();
 
+        for link in self.bus_links.values().filter(|l| l.lhs.0 == node) {
+            v.entry(link.lhs.1)
+                .or_default()
+                .push(Arc::clone(&link.sink));
+        }
+
         storage
             .get_idxs()
             .into_iter()
@@ -231,13 +941,30 @@ impl UiContext {
         }
 
         let nodes_to_delete = Rc::new(RefCell::new(Vec::new()));
+        let ports_to_disconnect = Rc::new(RefCell::new(Vec::new()));
+        let ports_to_bypass = Rc::new(RefCell::new(Vec::new()));
+        let nodes_to_rename = Rc::new(RefCell::new(Vec::new()));
+        let warnings_to_dismiss = Rc::new(RefCell::new(Vec::new()));
+        let theme = Arc::clone(&self.theme);
+        let block_budget = profiler::block_budget();
+        let sample_rate = self.sample_rate;
 
         let nodes: Vec<NodeConstructor> = self
             .nodes
             .values()
             .map(|node| {
                 let nodes_to_delete = Rc::clone(&nodes_to_delete);
-                let mut n = NodeConstructor::new(node.id.get(), NodeArgs::default());
+                let nodes_to_rename = Rc::clone(&nodes_to_rename);
+                let warnings_to_dismiss = Rc::clone(&warnings_to_dismiss);
+                let node_id = node.id;
+                let colors = theme.category(nodes::category(node.instance.cfg_name()));
+                let args = NodeArgs {
+                    titlebar: Some(colors.titlebar),
+                    titlebar_hovered: Some(colors.titlebar_hovered),
+                    titlebar_selected: Some(colors.titlebar_hovered),
+                    ..Default::default()
+                };
+                let mut n = NodeConstructor::new(node.id.get(), args);
                 n.with_title(move |ui| {
                     ui.horizontal(|ui| {
                         let mut inner_ui = egui::Ui::new(
@@ -248,14 +975,143 @@ impl UiContext {
                             ui.clip_rect(),
                             UiStackInfo::new(egui::UiKind::GenericArea)
                         );
-                        ui.label(format!("{} ({})", node.instance.title(), node.id.get()))
-                            .on_hover_text_at_pointer(node.instance.description());
+                        let title = format!("{} ({})", node.title(), node.id.get());
+                        ui.add(egui::Label::new(title).sense(egui::Sense::click()))
+                            .on_hover_text_at_pointer(node.instance.description())
+                            .context_menu(|ui| {
+                                let mut label = node.label.clone();
+                                ui.horizontal(|ui| {
+                                    ui.label("Name");
+                                    let r = ui.add(
+                                        egui::TextEdit::singleline(&mut label)
+                                            .hint_text(node.instance.title()),
+                                    );
+                                    if r.changed() {
+                                        nodes_to_rename.borrow_mut().push((node_id, label));
+                                    }
+                                });
+
+                                let mut sleep_when_idle = node
+                                    .sleep_when_idle
+                                    .load(std::sync::atomic::Ordering::Relaxed);
+                                if ui
+                                    .checkbox(&mut sleep_when_idle, "Sleep when silent")
+                                    .on_hover_text_at_pointer(
+                                        "Skip processing (outputting silence) while the inputs \
+                                         and outputs have been silent for a while",
+                                    )
+                                    .changed()
+                                {
+                                    node.sleep_when_idle.store(
+                                        sleep_when_idle,
+                                        std::sync::atomic::Ordering::Relaxed,
+                                    );
+                                }
+
+                                let gain_match = node.instance.outputs().gain_match();
+                                let mut matched = gain_match
+                                    .enabled
+                                    .load(std::sync::atomic::Ordering::Relaxed);
+                                if ui
+                                    .checkbox(&mut matched, "Match loudness")
+                                    .on_hover_text_at_pointer(
+                                        "Trim the output to be as loud as the input, averaged \
+                                         over 0.4s, so comparing against the dry signal is fair",
+                                    )
+                                    .changed()
+                                {
+                                    gain_match
+                                        .enabled
+                                        .store(matched, std::sync::atomic::Ordering::Relaxed);
+                                }
+
+                                if ui.button("Reset to defaults").clicked() {
+                                    node.instance.reset_defaults();
+                                    ui.close_menu();
+                                }
+                            });
+
+                        if let Some(made_at) = node.rate_warning {
+                            let r = ui
+                                .add(
+                                    egui::Label::new(
+                                        egui::RichText::new("⚠").color(ui.visuals().warn_fg_color),
+                                    )
+                                    .sense(egui::Sense::click()),
+                                )
+                                .on_hover_text_at_pointer(format!(
+                                    "Set up at {made_at}hz, but the graph now runs at \
+                                     {sample_rate}hz. Anything set up before the change is off \
+                                     until it's set up again, click to dismiss"
+                                ));
+                            if r.clicked() {
+                                warnings_to_dismiss.borrow_mut().push(node_id);
+                            }
+                        }
+
+                        let block_time = std::time::Duration::from_nanos(
+                            node.block_time.load(std::sync::atomic::Ordering::Relaxed),
+                        );
+                        if !block_time.is_zero() {
+                            let load = block_time.as_secs_f32() / block_budget.as_secs_f32();
+                            let color = if load > 1.0 {
+                                ui.visuals().error_fg_color
+                            } else if load > 0.5 {
+                                ui.visuals().warn_fg_color
+                            } else {
+                                ui.visuals().weak_text_color()
+                            };
+                            ui.label(
+                                egui::RichText::new(format!("{}µs", block_time.as_micros()))
+                                    .color(color)
+                                    .small(),
+                            )
+                            .on_hover_text_at_pointer(format!(
+                                "Average time to process a block, {:.0}% of the {}µs a block \
+                                 lasts",
+                                load * 100.0,
+                                block_budget.as_micros()
+                            ));
+                        }
+
+                        let can_solo = crate::node::can_wet_solo(
+                            node.instance.inputs(),
+                            node.instance.outputs(),
+                        );
+                        let wet_solo = node.instance.outputs().wet_solo();
+                        let soloed = wet_solo.load(std::sync::atomic::Ordering::Relaxed);
+                        let r = ui
+                            .add_enabled(can_solo, egui::SelectableLabel::new(soloed, "Wet"))
+                            .on_hover_text_at_pointer(
+                                "Solo the wet signal, only what this node adds to its input",
+                            )
+                            .on_disabled_hover_text(
+                                "No signal passes through this node, so there's no dry signal \
+                                 to take away",
+                            );
+                        if r.clicked() {
+                            wet_solo.store(!soloed, std::sync::atomic::Ordering::Relaxed);
+                        }
                         inner_ui.with_layout(
                             egui::Layout::right_to_left(egui::Align::Center),
                             move |ui| {
                                 if ui.add(egui::Button::new("Close")).clicked() {
                                     nodes_to_delete.borrow_mut().push(node.id);
                                 }
+
+                                let bypassed =
+                                    node.bypassed.load(std::sync::atomic::Ordering::Relaxed);
+                                if ui
+                                    .add(egui::SelectableLabel::new(bypassed, "Bypass"))
+                                    .on_hover_text_at_pointer(
+                                        "Pass the input straight through, muting the outputs if \
+                                         they don't match up with the inputs",
+                                    )
+                                    .clicked()
+                                {
+                                    node.bypassed
+                                        .store(!bypassed, std::sync::atomic::Ordering::Relaxed);
+                                }
                             },
                         )
                     })
@@ -264,7 +1120,7 @@ impl UiContext {
                 .with_content(|ui| node.instance.render(ui))
                 .with_origin(node.position);
 
-                for (input, id) in node
+                let input_ports = node
                     .instance
                     .inputs()
                     .0
@@ -274,16 +1130,30 @@ impl UiContext {
                     .iter()
                     .sorted_by_key(|(k, _)| *k)
                     .map(|(k, v)| (k.to_owned(), *v))
-                {
-                    n.with_input_attribute(id.get(), PinArgs::default(), move |ui| {
-                        ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                            ui.label(input)
-                        })
-                        .inner
-                    });
+                    .collect_vec();
+
+                for (input, id) in input_ports {
+                    let level = node.instance.inputs().take_level(id);
+                    let ports_to_disconnect = Rc::clone(&ports_to_disconnect);
+                    n.with_input_attribute(id.get(), pin_args(&theme, level), move |ui| {
+                        let r = ui
+                            .with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                                ui.add(egui::Label::new(input).sense(egui::Sense::click()))
+                            })
+                            .inner;
+
+                        r.context_menu(|ui| {
+                            if ui.button("Disconnect all").clicked() {
+                                ports_to_disconnect.borrow_mut().push((node_id, id));
+                                ui.close_menu();
+                            }
+                        });
+
+                        r
+                    });
                 }
 
-                for (output, id) in node
+                let output_ports = node
                     .instance
                     .outputs()
                     .0
@@ -293,12 +1163,39 @@ impl UiContext {
                     .iter()
                     .sorted_by_key(|(k, _)| *k)
                     .map(|(k, v)| (k.to_owned(), *v))
-                {
-                    n.with_output_attribute(id.get(), PinArgs::default(), move |ui| {
-                        ui.with_layout(egui::Layout::top_down(egui::Align::RIGHT), |ui| {
-                            ui.label(output)
-                        })
-                        .inner
+                    .collect_vec();
+
+                for (output, id) in output_ports {
+                    let level = node.instance.outputs().take_level(id);
+                    let ports_to_disconnect = Rc::clone(&ports_to_disconnect);
+                    let ports_to_bypass = Rc::clone(&ports_to_bypass);
+                    n.with_output_attribute(id.get(), pin_args(&theme, level), move |ui| {
+                        let r = ui
+                            .with_layout(egui::Layout::top_down(egui::Align::RIGHT), |ui| {
+                                ui.add(egui::Label::new(output).sense(egui::Sense::click()))
+                            })
+                            .inner;
+
+                        r.context_menu(|ui| {
+                            if ui.button("Disconnect all").clicked() {
+                                ports_to_disconnect.borrow_mut().push((node_id, id));
+                                ui.close_menu();
+                            }
+
+                            if ui
+                                .button("Bypass all links")
+                                .on_hover_text_at_pointer(
+                                    "Link whatever feeds the matching input straight to \
+                                     everything this output is linked to",
+                                )
+                                .clicked()
+                            {
+                                ports_to_bypass.borrow_mut().push((node_id, id));
+                                ui.close_menu();
+                            }
+                        });
+
+                        r
                     });
                 }
 
@@ -307,17 +1204,87 @@ impl UiContext {
             .sorted_by_key(|n| n.id())
             .collect_vec();
 
-        let links = self
+        let mut links = self
             .links
             .values()
             .enumerate()
             .map(|(idx, link)| (idx, link.lhs.1.get(), link.rhs.1.get(), LinkArgs::default()))
             .collect::<Vec<_>>();
 
+        if let Some((lhs, rhs, at)) = self.rejected_link {
+            if at.elapsed() < REJECTED_LINK_FLASH {
+                let error = self.theme.error;
+                links.push((
+                    links.len(),
+                    lhs.get(),
+                    rhs.get(),
+                    LinkArgs {
+                        base: Some(error),
+                        hovered: Some(error),
+                        selected: Some(error),
+                    },
+                ));
+                ui.ctx()
+                    .request_repaint_after(REJECTED_LINK_FLASH - at.elapsed());
+            } else {
+                self.rejected_link = None;
+            }
+        }
+
         self.node_ctx.show(nodes, links, ui);
 
+        let right_clicked = ui.input(|i| {
+            i.pointer
+                .secondary_clicked()
+                .then(|| i.pointer.interact_pos())
+                .flatten()
+        });
+        let mut menu_opened = false;
+
+        if let Some(pos) = right_clicked {
+            // nodes, links and pins have context menus of their own
+            if ui.max_rect().contains(pos)
+                && self.node_ctx.hovered_node().is_none()
+                && self.node_ctx.hovered_link().is_none()
+                && self.node_ctx.hovered_pin().is_none()
+            {
+                self.canvas_menu = Some(pos);
+                menu_opened = true;
+            }
+        }
+
+        if let Some(pos) = self.canvas_menu {
+            let mut chosen = None;
+
+            let r = egui::Area::new(egui::Id::new("canvas_menu"))
+                .fixed_pos(pos)
+                .order(egui::Order::Foreground)
+                .show(ui.ctx(), |ui| {
+                    egui::Frame::menu(ui.style()).show(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .max_height(300.0)
+                            .show(ui, |ui| {
+                                for (name, ctor) in nodes::NODES {
+                                    if ui.button(*name).clicked() {
+                                        chosen = Some(*ctor);
+                                    }
+                                }
+                            });
+                    });
+                });
+
+            if let Some(ctor) = chosen {
+                self.spawn_node(ctor, pos);
+                self.canvas_menu = None;
+            } else if !menu_opened && r.response.clicked_elsewhere() {
+                self.canvas_menu = None;
+            }
+        }
+
         if let Some(idx) = self.node_ctx.link_destroyed() {
             if let Some(&id) = self.links.keys().nth(idx) {
+                self.checkpoint();
+
                 if let Some(inst) = self.links.remove(&id) {
                     tracing::info!(link = ?inst, "Removing link");
                     self.outputs.get_mut(&inst.lhs).unwrap().remove(&id);
@@ -340,13 +1307,28 @@ impl UiContext {
             let start = (NodeId::new(start_node), PortId::new(start_port));
             let end = (NodeId::new(end_node), PortId::new(end_port));
 
-            if self.inputs.contains_key(&start) && self.outputs.contains_key(&end) {
-                self.add_link(end, start);
+            let (from, to) = if self.inputs.contains_key(&start) && self.outputs.contains_key(&end)
+            {
+                (end, start)
+            } else {
+                (start, end)
+            };
+
+            if self.inputs.contains_key(&to)
+                && self.outputs.contains_key(&from)
+                && self.closes_cycle(from.0, to.0)
+            {
+                tracing::info!(?from, ?to, "Not linking, it would close a loop");
+                self.rejected_link = Some((from.1, to.1, std::time::Instant::now()));
+            } else if self.inputs.contains_key(&start) && self.outputs.contains_key(&end) {
+                self.checkpoint();
+                self.add_link_and_partner(end, start);
                 self.restart_node(end.0);
                 self.restart_node(start.0);
                 devices::invoke(devices::DeviceCommand::TriggerResync);
             } else if self.inputs.contains_key(&end) && self.outputs.contains_key(&start) {
-                self.add_link(start, end);
+                self.checkpoint();
+                self.add_link_and_partner(start, end);
                 self.restart_node(end.0);
                 self.restart_node(start.0);
                 devices::invoke(devices::DeviceCommand::TriggerResync);
@@ -361,6 +1343,33 @@ impl UiContext {
             };
         }
 
+        if !ports_to_disconnect.borrow().is_empty()
+            || !ports_to_bypass.borrow().is_empty()
+            || !nodes_to_delete.borrow().is_empty()
+        {
+            self.checkpoint();
+        }
+
+        for port in ports_to_disconnect.borrow().iter() {
+            self.disconnect_port(*port);
+        }
+
+        for port in ports_to_bypass.borrow().iter() {
+            self.bypass_port(*port);
+        }
+
+        for (id, label) in nodes_to_rename.take() {
+            if let Some(n) = self.nodes.get_mut(&id) {
+                n.label = label;
+            }
+        }
+
+        for id in warnings_to_dismiss.take() {
+            if let Some(n) = self.nodes.get_mut(&id) {
+                n.rate_warning = None;
+            }
+        }
+
         for node_to_delete in nodes_to_delete.borrow().iter() {
             tracing::info!("Deleting node {:?}", node_to_delete);
             if let Some(n) = self.nodes.get_mut(node_to_delete) {
@@ -402,6 +1411,113 @@ impl UiContext {
         }
     }
 
+    /// Remove every link going in to or out of a port
+    fn disconnect_port(&mut self, port: (NodeId, PortId)) {
+        let links_to_remove = self
+            .inputs
+            .get(&port)
+            .into_iter()
+            .chain(self.outputs.get(&port))
+            .flatten()
+            .copied()
+            .collect_vec();
+
+        let mut nodes_to_restart = HashSet::new();
+
+        for link in &links_to_remove {
+            if let Some(inst) = self.links.get(link) {
+                tracing::info!(link = ?inst, "Removing link");
+                self.outputs.get_mut(&inst.lhs).unwrap().remove(link);
+                self.inputs.get_mut(&inst.rhs).unwrap().remove(link);
+
+                nodes_to_restart.insert(inst.lhs.0);
+                nodes_to_restart.insert(inst.rhs.0);
+            }
+        }
+
+        for node_to_restart in nodes_to_restart {
+            self.restart_node(node_to_restart);
+        }
+
+        // remove links here so that the buffers stay alive until the node restarts without them
+        for link in links_to_remove {
+            self.links.remove(&link);
+        }
+
+        devices::invoke(devices::DeviceCommand::TriggerResync);
+    }
+
+    /// Move every link out of an output port over to whatever feeds the input
+    /// carrying its signal, taking the node out of those paths
+    ///
+    /// Inputs are paired with the output by name, see [`signal_input`], or
+    /// if the node has just the one input, that one. Port indexes aren't used
+    /// as they come from a hashmap and change when a node is restored.
+    fn bypass_port(&mut self, port: (NodeId, PortId)) {
+        let Some(node) = self.nodes.get(&port.0) else {
+            return;
+        };
+
+        let inputs = node.instance.inputs();
+        let output = node
+            .instance
+            .outputs()
+            .get_all()
+            .into_iter()
+            .find(|(_, id)| *id == port.1)
+            .map(|(name, _)| name);
+        let only_input = || {
+            let all = inputs.get_all();
+            if all.len() == 1 {
+                all.into_values().next()
+            } else {
+                None
+            }
+        };
+
+        let Some(input) = output
+            .and_then(|name| signal_input(inputs, &name))
+            .or_else(only_input)
+        else {
+            tracing::info!(?port, "Not bypassing, no input matches the output");
+            return;
+        };
+
+        let link_ends = |ids: Option<&HashSet<LinkId>>| {
+            ids.into_iter()
+                .flatten()
+                .filter_map(|id| self.links.get(id))
+                .map(|l| (l.lhs, l.rhs))
+                .collect_vec()
+        };
+
+        let sources = link_ends(self.inputs.get(&(port.0, input)))
+            .into_iter()
+            .map(|(lhs, _)| lhs)
+            .collect_vec();
+        let destinations = link_ends(self.outputs.get(&port))
+            .into_iter()
+            .map(|(_, rhs)| rhs)
+            .collect_vec();
+
+        self.disconnect_port(port);
+
+        for (source, destination) in sources.iter().cartesian_product(&destinations) {
+            self.add_link(*source, *destination);
+        }
+
+        for id in sources
+            .iter()
+            .chain(&destinations)
+            .map(|(n, _)| *n)
+            .unique()
+        {
+            self.restart_node(id);
+        }
+
+        devices::invoke(devices::DeviceCommand::TriggerResync);
+    }
+
     fn add_node(&mut self, id: NodeId, instance: Arc<Nodes>) {
         let inst = NodeInstance::new(id, instance);
         for port in inst.instance.inputs().0.read().unwrap().ports.values() {
@@ -417,8 +1533,407 @@ impl UiContext {
         self.nodes.insert(inst.id, inst);
     }
 
-    fn update_theme(&mut self, theme: &'static Theme) {
-        self.theme = theme;
+    /// The first spot down the cascade from where new nodes start that no node
+    /// is sitting on
+    fn free_position(&self) -> egui::Pos2 {
+        let mut position = pos2(100.0, 100.0);
+
+        while self
+            .nodes
+            .values()
+            .any(|n| n.position.distance(position) < CASCADE_OFFSET.length() / 2.0)
+        {
+            position += CASCADE_OFFSET;
+        }
+
+        position
+    }
+
+    /// Add a new node from the menus at `position`, in screen space
+    fn spawn_node(&mut self, ctor: fn(NodeId) -> Arc<Nodes>, position: egui::Pos2) {
+        self.checkpoint();
+        let id = NodeId::generate();
+        self.add_node(id, ctor(id));
+
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.position = position;
+        }
+    }
+
+    fn update_automation(&mut self, ctx: &egui::Context) {
+        if self.automation.state() == LaneState::Stopped {
+            return;
+        }
+
+        if let Some(node) = self
+            .automation
+            .target
+            .as_ref()
+            .and_then(|(id, _)| self.nodes.get(id))
+        {
+            self.automation.tick(&self.transport, &node.instance);
+        }
+
+        // keep ticking even if nothing else is asking for a repaint
+        ctx.request_repaint();
+    }
+
+    fn morph_menu(&mut self, ui: &mut egui::Ui) {
+        let capture = |nodes: &HashMap<NodeId, NodeInstance>| {
+            Snapshot::capture(nodes.values().map(|n| (n.id, n.instance.as_ref())))
+        };
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Capture A")
+                .on_hover_text_at_pointer("Store the current parameters of every node as A")
+                .clicked()
+            {
+                self.morph.a = Some(capture(&self.nodes));
+            }
+
+            if ui
+                .button("Capture B")
+                .on_hover_text_at_pointer("Store the current parameters of every node as B")
+                .clicked()
+            {
+                self.morph.b = Some(capture(&self.nodes));
+            }
+        });
+
+        let r = ui
+            .add_enabled(
+                self.morph.is_ready(),
+                egui::Slider::new(&mut self.morph.position, 0.0..=1.0).text("A/B"),
+            )
+            .on_hover_text_at_pointer(
+                "Morph the parameters of every node between the A and B snapshots, \
+                 options switch over halfway",
+            );
+
+        if r.changed() {
+            self.morph
+                .apply(|id| self.nodes.get(&id).map(|n| n.instance.as_ref()));
+        }
+    }
+
+    fn automation_menu(&mut self, ui: &mut egui::Ui) {
+        let selected_text = match &self.automation.target {
+            Some((id, param)) => match self.nodes.get(id) {
+                Some(node) => format!("{} ({}): {}", node.title(), id.get(), param),
+                None => format!("<deleted> ({}): {}", id.get(), param),
+            },
+            None => "<none>".to_owned(),
+        };
+
+        let mut target = self.automation.target.clone();
+
+        egui::ComboBox::new("automation_target", "Parameter")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for node in self.nodes.values().sorted_by_key(|n| n.id.get()) {
+                    for (param, _) in node.instance.list_params() {
+                        ui.selectable_value(
+                            &mut target,
+                            Some((node.id, param.to_owned())),
+                            format!("{} ({}): {}", node.title(), node.id.get(), param),
+                        );
+                    }
+                }
+
+                ui.selectable_value(&mut target, None, "<none>");
+            });
+
+        self.automation.set_target(target);
+
+        let has_target = self.automation.target.is_some();
+
+        ui.horizontal(|ui| match self.automation.state() {
+            LaneState::Stopped => {
+                if ui
+                    .add_enabled(has_target, egui::Button::new("Record"))
+                    .on_hover_text_at_pointer("Record movements of the parameter until stopped")
+                    .clicked()
+                {
+                    self.automation.record(&mut self.transport);
+                }
+
+                if ui
+                    .add_enabled(self.automation.has_recording(), egui::Button::new("Play"))
+                    .on_hover_text_at_pointer("Loop the recorded movements")
+                    .clicked()
+                {
+                    self.automation.play(&mut self.transport);
+                }
+            }
+            LaneState::Recording | LaneState::Playing => {
+                if ui.button("Stop").clicked() {
+                    self.automation.stop(&self.transport);
+                }
+
+                ui.label(format!(
+                    "{:?}: {:.1}s",
+                    self.automation.state(),
+                    self.transport.position()
+                ));
+            }
+        });
+    }
+
+    /// A side panel listing the parameters of every node, clicking one makes it
+    /// the automation target
+    fn parameters_panel(&mut self, ctx: &egui::Context) {
+        let mut target = self.automation.target.clone();
+
+        egui::SidePanel::right("parameters_panel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.parameter_filter).hint_text("Search"));
+                ui.separator();
+
+                let filter = self.parameter_filter.to_lowercase();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for node in self.nodes.values().sorted_by_key(|n| n.id.get()) {
+                        let title = format!("{} ({})", node.title(), node.id.get());
+                        let title_matches = title.to_lowercase().contains(&filter);
+
+                        let params = node
+                            .instance
+                            .list_params()
+                            .into_iter()
+                            .filter(|(param, _)| {
+                                title_matches || param.to_lowercase().contains(&filter)
+                            })
+                            .collect::<Vec<_>>();
+
+                        if params.is_empty() {
+                            continue;
+                        }
+
+                        egui::CollapsingHeader::new(title)
+                            .id_source((node.id, "parameters"))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for (param, value) in params {
+                                    let this = Some((node.id, param.to_owned()));
+                                    ui.selectable_value(
+                                        &mut target,
+                                        this,
+                                        format!("{param}: {value:.3}"),
+                                    )
+                                    .on_hover_text_at_pointer("Use as the automation target");
+                                }
+                            });
+                    }
+                });
+            });
+
+        self.automation.set_target(target);
+
+        // values can be changed by automation and morphing, not just the ui
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    }
+
+    fn settings_menu(&mut self, ui: &mut egui::Ui) {
+        let r = ui
+            .add(
+                egui::Slider::new(
+                    &mut self.settings.link_buffer_size,
+                    2 * MAX_BUF_SIZE..=65536,
+                )
+                .logarithmic(true)
+                .text("Link buffer size")
+                .suffix(" samples"),
+            )
+            .on_hover_text_at_pointer(
+                "The most audio each link can hold. Smaller buffers bound the latency that \
+                 can build up inside the graph, larger ones ride out hiccups without \
+                 underruns. Spectrogram FFT sizes larger than this won't work.",
+            );
+
+        if r.drag_stopped() || (r.changed() && !r.dragged()) {
+            self.rebuild_links();
+        }
+
+        let previous_block_size = self.settings.block_size;
+        egui::ComboBox::from_label("Block size")
+            .selected_text(format!("{} samples", self.settings.block_size))
+            .show_ui(ui, |ui| {
+                let sizes = std::iter::successors(Some(MIN_BUF_SIZE), |s| Some(s * 2))
+                    .take_while(|s| *s <= MAX_BUF_SIZE);
+                for size in sizes {
+                    ui.selectable_value(
+                        &mut self.settings.block_size,
+                        size,
+                        format!("{size} samples"),
+                    );
+                }
+            })
+            .response
+            .on_hover_text_at_pointer(
+                "How many samples each node processes at a time. Smaller blocks lower the \
+                 latency, larger ones spend less time passing audio between nodes and suit \
+                 heavy graphs. Keep it at or below the output device's buffer size, so each \
+                 callback is filled by whole blocks.",
+            );
+
+        if self.settings.block_size != previous_block_size {
+            self.set_block_size(self.settings.block_size);
+        }
+
+        let r = ui
+            .add(
+                egui::Slider::new(&mut self.settings.resync_callbacks, 1..=50)
+                    .text("Resync duration")
+                    .suffix(" callbacks"),
+            )
+            .on_hover_text_at_pointer(
+                "How many output callbacks a resync (from editing the graph or pressing \
+                 \"Sync output\") stays active for. Longer resyncs catch up more reliably \
+                 but can drop audio more than once. Each output's target latency is set \
+                 on its node.",
+            );
+        let mut changed = r.changed();
+
+        let label = |factor: u32| match factor {
+            1 => "Off".to_owned(),
+            n => format!("{n}x"),
+        };
+
+        egui::ComboBox::new("output_oversampling", "Output oversampling")
+            .selected_text(label(self.settings.output_oversampling))
+            .show_ui(ui, |ui| {
+                for factor in [1, 2, 4, 8] {
+                    changed |= ui
+                        .selectable_value(
+                            &mut self.settings.output_oversampling,
+                            factor,
+                            label(factor),
+                        )
+                        .changed();
+                }
+            })
+            .response
+            .on_hover_text_at_pointer(
+                "Upsample the output before converting it to the device's sample rate, \
+                 reducing imaging artifacts. Costs CPU roughly in proportion to the factor. \
+                 Applies to outputs opened after changing it.",
+            );
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.settings.device_switch_fade, 0..=500)
+                    .text("Device switch fade")
+                    .suffix(" ms"),
+            )
+            .on_hover_text_at_pointer(
+                "How long an Output node fades out before switching to another device, and \
+                 back in afterwards. 0 switches immediately.",
+            )
+            .changed();
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.settings.start_fade, 0..=100)
+                    .text("Node start fade")
+                    .suffix(" ms"),
+            )
+            .on_hover_text_at_pointer(
+                "How long a node's output fades in for after the node is (re)started, which \
+                 happens whenever its links change. 0 starts at full level.",
+            )
+            .changed();
+
+        changed |= ui
+            .checkbox(
+                &mut self.settings.elevated_priority,
+                "Elevated thread priority",
+            )
+            .on_hover_text_at_pointer(
+                "Run the audio threads at real-time priority where the OS allows it, \
+                 reducing glitches under load. Falls back to normal priority if permission \
+                 is denied. Fully applies after a restart.",
+            )
+            .changed();
+
+        if ui
+            .checkbox(&mut self.settings.scheduled_graph, "Scheduled graph")
+            .on_hover_text_at_pointer(
+                "Process every node in turn on a single task, each one straight after the \
+                 nodes feeding it, so a long chain doesn't build up latency in its links. \
+                 Only used while the graph has no cycles, and nodes don't sleep when silent \
+                 while it's on.",
+            )
+            .changed()
+        {
+            self.update_all();
+            devices::invoke(devices::DeviceCommand::TriggerResync);
+        }
+
+        ui.checkbox(
+            &mut self.settings.dedicated_dsp_threads,
+            "Dedicated DSP threads",
+        )
+        .on_hover_text_at_pointer(
+            "Run node processing on its own pool of threads, kept apart from the rest of \
+             the app. With elevated priority on, only these threads are elevated. Applies \
+             after a restart.",
+        );
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.settings.idle_threshold, -120.0..=-40.0)
+                    .text("Idle threshold")
+                    .suffix(" dB"),
+            )
+            .on_hover_text_at_pointer(
+                "Level below which nodes set to sleep when silent treat their signal as \
+                 silence",
+            )
+            .changed();
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.settings.idle_hold, 0.1..=30.0)
+                    .logarithmic(true)
+                    .text("Idle hold")
+                    .suffix(" s"),
+            )
+            .on_hover_text_at_pointer(
+                "How long a node must be silent before it sleeps, make this longer than any \
+                 reverb or delay tails",
+            )
+            .changed();
+
+        ui.separator();
+
+        let hover = match autosave_path() {
+            Some(path) => format!(
+                "Periodically save the graph to {}, which is loaded at startup if the \
+                 normally saved graph can't be",
+                path.display()
+            ),
+            None => "There's nowhere to autosave to on this system".to_owned(),
+        };
+
+        ui.checkbox(&mut self.settings.autosave, "Autosave")
+            .on_hover_text_at_pointer(hover);
+
+        ui.add_enabled(
+            self.settings.autosave,
+            egui::Slider::new(&mut self.settings.autosave_interval, 5..=600)
+                .logarithmic(true)
+                .text("Autosave interval")
+                .suffix(" s"),
+        );
+
+        if changed {
+            self.settings.apply();
+        }
+    }
+
+    fn update_theme(&mut self, theme: Arc<Theme>) {
         self.node_ctx.style.colors[ColorStyle::Pin as usize] = theme.link;
         self.node_ctx.style.colors[ColorStyle::PinHovered as usize] = theme.link_hovered;
         self.node_ctx.style.colors[ColorStyle::Link as usize] = theme.link;
@@ -433,6 +1948,7 @@ impl UiContext {
         self.node_ctx.style.colors[ColorStyle::NodeBackgroundSelected as usize] =
             theme.node_background_hovered;
         self.node_ctx.style.colors[ColorStyle::GridBackground as usize] = theme.grid_background;
+        self.theme = theme;
     }
 }
 
@@ -440,6 +1956,28 @@ impl eframe::App for UiContext {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         let _guard = self.runtime.enter();
 
+        // nodes restored before the first device was opened worked out their
+        // settings at the default rate
+        let sample_rate = devices::sample_rate();
+        if sample_rate != self.sample_rate {
+            let made_at = std::mem::replace(&mut self.sample_rate, sample_rate);
+
+            for node in self.nodes.values_mut() {
+                node.instance.refresh_settings();
+
+                // what refresh_settings can't retune has to be set up again
+                if node.instance.rate_dependent() {
+                    tracing::warn!(
+                        id = ?node.id,
+                        made_at,
+                        sample_rate,
+                        "The sample rate changed under a node that depends on it"
+                    );
+                    node.rate_warning.get_or_insert(made_at);
+                }
+            }
+        }
+
         let mut visuals = if self.theme.dark {
             Visuals::dark()
         } else {
@@ -452,6 +1990,9 @@ impl eframe::App for UiContext {
         visuals.widgets.hovered.bg_fill = self.theme.node_background_hovered;
         visuals.widgets.open.bg_fill = self.theme.grid_background;
         visuals.widgets.inactive.bg_fill = self.theme.grid_background;
+        // nodes draw their errors with these
+        visuals.error_fg_color = self.theme.error;
+        visuals.warn_fg_color = self.theme.warning;
 
         ctx.set_visuals(visuals);
 
@@ -487,26 +2028,212 @@ impl eframe::App for UiContext {
                             tracing::info!("Restoring from {:?}", path);
                             if let Ok(file) = std::fs::File::open(path) {
                                 let cfg: DSPConfig = serde_json::from_reader(file).unwrap();
+                                self.checkpoint();
                                 self.restore_config(cfg);
                             }
                         }
                     }
+
+                    if ui
+                        .button("Export diagram")
+                        .on_hover_text_at_pointer("Save the graph as a Graphviz (dot) diagram")
+                        .clicked()
+                    {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_title("Export diagram")
+                            .add_filter("graphviz", &["dot", "gv"])
+                            .set_file_name("patch.dot")
+                            .save_file()
+                        {
+                            tracing::info!("Exporting diagram to {:?}", path);
+                            if let Err(e) = std::fs::write(path, self.diagram()) {
+                                tracing::warn!("Couldn't export the diagram: {:?}", e);
+                            }
+                        }
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .button("Copy patch")
+                        .on_hover_text_at_pointer("Copy the whole patch to the clipboard as json")
+                        .clicked()
+                    {
+                        self.copy_patch();
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .button("Paste patch")
+                        .on_hover_text_at_pointer(
+                            "Replace the current patch with one from the clipboard",
+                        )
+                        .clicked()
+                    {
+                        self.paste_patch();
+                        ui.close_menu();
+                    }
+                });
+
+                egui::menu::menu_button(ui, "Edit", |ui| {
+                    if ui
+                        .add_enabled(!self.undo.is_empty(), egui::Button::new("Undo"))
+                        .on_hover_text_at_pointer("Ctrl+Z")
+                        .clicked()
+                    {
+                        self.undo();
+                    }
+
+                    if ui
+                        .add_enabled(!self.redo.is_empty(), egui::Button::new("Redo"))
+                        .on_hover_text_at_pointer("Ctrl+Shift+Z")
+                        .clicked()
+                    {
+                        self.redo();
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .button("Copy nodes")
+                        .on_hover_text_at_pointer("Ctrl+C, copy the selected nodes")
+                        .clicked()
+                    {
+                        self.copy_selected();
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            !self.copied_nodes.is_empty(),
+                            egui::Button::new("Paste nodes"),
+                        )
+                        .on_hover_text_at_pointer("Ctrl+V")
+                        .clicked()
+                    {
+                        self.paste_copied();
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    let mut input_mixing = self.input_mixing;
+                    egui::ComboBox::new("input_mixing", "Input mixing")
+                        .selected_text(format!("{input_mixing:?}"))
+                        .show_ui(ui, |ui| {
+                            for mode in [InputMixing::Average, InputMixing::Sum] {
+                                ui.selectable_value(&mut input_mixing, mode, format!("{mode:?}"));
+                            }
+                        })
+                        .response
+                        .on_hover_text_at_pointer(
+                            "How an input with several links combines them. Averaging keeps \
+                             the level steady as links are added, summing behaves like a \
+                             mixer. Saved with the patch.",
+                        );
+
+                    if input_mixing != self.input_mixing {
+                        self.checkpoint();
+                        self.input_mixing = input_mixing;
+                        set_input_mixing(input_mixing);
+                    }
                 });
 
                 egui::menu::menu_button(ui, "Effects", |ui| {
                     for (name, ctor) in nodes::NODES {
                         if ui.button(*name).clicked() {
-                            let id = NodeId::generate();
-                            self.add_node(id, ctor(id));
+                            let position = self.free_position();
+                            self.spawn_node(*ctor, position);
                         }
                     }
                 });
 
+                egui::menu::menu_button(ui, "Automation", |ui| {
+                    self.automation_menu(ui);
+                });
+
+                ui.toggle_value(&mut self.show_parameters, "Parameters")
+                    .on_hover_text_at_pointer("List the parameters of every node");
+
+                egui::menu::menu_button(ui, "Morph", |ui| {
+                    self.morph_menu(ui);
+                });
+
+                egui::menu::menu_button(ui, "Settings", |ui| {
+                    self.settings_menu(ui);
+                });
+
                 egui::menu::menu_button(ui, "Theme", |ui| {
                     for (name, theme) in theme::THEMES {
                         if ui.button(*name).clicked() {
-                            self.update_theme(theme);
+                            self.update_theme(Arc::new((*theme).clone()));
+                        }
+                    }
+
+                    let mut chosen = None;
+                    for (name, theme) in &self.custom_themes {
+                        if ui.button(name).clicked() {
+                            chosen = Some(theme.clone());
+                        }
+                    }
+                    if let Some(theme) = chosen {
+                        self.update_theme(Arc::new(theme));
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .button("Load theme")
+                        .on_hover_text_at_pointer("Add a theme saved as json to this menu")
+                        .clicked()
+                    {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_title("Load theme")
+                            .add_filter("theme", &["json"])
+                            .pick_file()
+                        {
+                            tracing::info!("Loading theme from {:?}", path);
+                            match Theme::from_file(&path) {
+                                Ok(theme) => {
+                                    let name = path.file_stem().map_or_else(
+                                        || "Loaded".to_owned(),
+                                        |s| s.to_string_lossy().into_owned(),
+                                    );
+                                    self.custom_themes.retain(|(n, _)| *n != name);
+                                    self.custom_themes.push((name, theme.clone()));
+                                    self.update_theme(Arc::new(theme));
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Couldn't load the theme {:?}: {:?}", path, e);
+                                }
+                            }
                         }
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .button("Save theme")
+                        .on_hover_text_at_pointer("Save the current theme as json, to share it")
+                        .clicked()
+                    {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_title("Save theme")
+                            .add_filter("theme", &["json"])
+                            .set_file_name("theme.json")
+                            .save_file()
+                        {
+                            tracing::info!("Saving theme to {:?}", path);
+                            let buf = serde_json::to_vec_pretty(&*self.theme).unwrap();
+                            if let Err(e) = std::fs::write(path, buf) {
+                                tracing::warn!("Couldn't save the theme: {:?}", e);
+                            }
+                        }
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Edit theme").clicked() {
+                        self.theme_editor.open(&self.theme);
+                        ui.close_menu();
                     }
                 });
 
@@ -517,22 +2244,147 @@ impl eframe::App for UiContext {
                 {
                     devices::invoke(devices::DeviceCommand::TriggerResync);
 
+                    self.stop_scheduler();
                     for node in self.nodes.values_mut() {
                         node.stop();
                     }
 
-                    for link in self.links.values() {
+                    for link in self.all_links() {
                         link.resync();
                     }
 
+                    nodes::clock::reset_all();
+
                     self.update_all();
                 }
-            });
-        });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            self.update_nodes(ui);
-        });
+
+                let mut frozen = *FROZEN.borrow();
+                if ui
+                    .toggle_value(&mut frozen, "Freeze")
+                    .on_hover_text_at_pointer(
+                        "Pause all processing, holding the state of every node until unfrozen",
+                    )
+                    .changed()
+                {
+                    FROZEN.send_replace(frozen);
+                }
+
+                if ui
+                    .button("Reset DSP state")
+                    .on_hover_text_at_pointer(
+                        "Clear delay lines, filters and envelopes of every node, \
+                         parameters are left alone",
+                    )
+                    .clicked()
+                {
+                    for node in self.nodes.values() {
+                        node.instance.reset_state();
+                    }
+                }
+
+                ui.separator();
+
+                let r = ui.add(
+                    egui::Slider::new(&mut self.settings.master_gain, 0.0..=2.0)
+                        .text("Master gain"),
+                );
+                let mut changed = r.changed();
+                changed |= ui
+                    .toggle_value(&mut self.settings.muted, "Mute")
+                    .on_hover_text_at_pointer("Silence all outputs")
+                    .changed();
+
+                if changed {
+                    self.settings.apply();
+                }
+            });
+        });
+
+        if self.show_parameters {
+            self.parameters_panel(ctx);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.update_nodes(ui);
+        });
+
+        // a send or return may have been added, removed or moved to another bus
+        for node in self.link_buses() {
+            if self.nodes.contains_key(&node) {
+                self.restart_node(node);
+            }
+        }
+
+        if std::mem::take(&mut self.reschedule) {
+            self.update_all();
+        }
+
+        self.update_automation(ctx);
+
+        #[cfg(feature = "osc")]
+        self.apply_osc();
+
+        self.autosave(ctx);
+
+        // text fields have undo of their own
+        if !ctx.wants_keyboard_input() {
+            // checked first, as ctrl+z also matches with shift held
+            if ctx.input_mut(|i| {
+                i.consume_key(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::Z,
+                )
+            }) {
+                self.redo();
+            } else if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z)) {
+                self.undo();
+            }
+
+            // the integration usually turns these shortcuts into copy and paste
+            // events rather than key presses
+            let (copy, paste) = ctx.input_mut(|i| {
+                let copy = i.events.iter().any(|e| matches!(e, egui::Event::Copy))
+                    || i.consume_key(egui::Modifiers::COMMAND, egui::Key::C);
+                let paste = i.events.iter().any(|e| matches!(e, egui::Event::Paste(_)))
+                    || i.consume_key(egui::Modifiers::COMMAND, egui::Key::V);
+                (copy, paste)
+            });
+
+            if copy {
+                self.copy_selected();
+            }
+
+            if paste {
+                self.paste_copied();
+            }
+
+            if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Space)) {
+                let position = ctx
+                    .input(|i| i.pointer.latest_pos())
+                    .unwrap_or_else(|| ctx.screen_rect().center());
+                self.palette.open(position);
+            }
+        }
+
+        if let Some(theme) = self.theme_editor.show(ctx, &mut self.custom_themes) {
+            self.update_theme(Arc::new(theme));
+        }
+
+        if let Some((idx, position)) = self.palette.show(ctx) {
+            let (_, ctor) = nodes::NODES[idx];
+            self.spawn_node(ctor, position);
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::F12)) {
+            self.profiler.toggle();
+        }
+
+        let running = match &self.scheduler {
+            Some(scheduler) => scheduler.nodes,
+            None => self.nodes.values().filter(|n| n.task.is_some()).count(),
+        };
+        self.profiler
+            .show(ctx, self.nodes.len(), running, self.links.len());
 
         // ctx.send_viewport_cmd(ViewportCommand::InnerSize(ctx.used_size()));
     }
@@ -540,6 +2392,150 @@ impl eframe::App for UiContext {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         let cfg = serde_json::to_string(&self.save_config()).unwrap();
         storage.set_string("graph_state", cfg);
+
+        let settings = serde_json::to_string(&self.settings).unwrap();
+        storage.set_string("settings", settings);
+
+        let theme = serde_json::to_string(&*self.theme).unwrap();
+        storage.set_string("theme", theme);
+
+        let custom_themes = serde_json::to_string(&self.custom_themes).unwrap();
+        storage.set_string("custom_themes", custom_themes);
+    }
+}
+
+/// Whether a link from `from` to `to` would close a loop through `links`
+///
+/// Loops going through a node that breaks cycles are fine, that node doesn't
+/// wait on the rest of the loop.
+fn closes_cycle(
+    links: &[(NodeId, NodeId)],
+    breaks_cycles: impl Fn(NodeId) -> bool,
+    from: NodeId,
+    to: NodeId,
+) -> bool {
+    if breaks_cycles(from) || breaks_cycles(to) {
+        return false;
+    }
+
+    // the link closes a loop if `from` can already be reached from `to`
+    let mut seen = HashSet::new();
+    let mut queue = vec![to];
+
+    while let Some(id) = queue.pop() {
+        if id == from {
+            return true;
+        }
+
+        for (_, rhs) in links.iter().filter(|(lhs, _)| *lhs == id) {
+            if !breaks_cycles(*rhs) && seen.insert(*rhs) {
+                queue.push(*rhs);
+            }
+        }
+    }
+
+    false
+}
+
+/// Copies of `nodes` with fresh node and port ids, moved by `offset`, along with
+/// the `links` between them pointed at the copies
+///
+/// Links to nodes that aren't in `nodes` are dropped.
+fn duplicate_nodes(
+    nodes: &[NodeConfig],
+    links: &[LinkConfig],
+    offset: egui::Vec2,
+) -> (Vec<NodeConfig>, Vec<LinkConfig>) {
+    let mut node_ids = HashMap::new();
+    let mut port_ids = HashMap::new();
+
+    let nodes = nodes
+        .iter()
+        .map(|node| {
+            let id = NodeId::generate();
+            node_ids.insert(node.id, id);
+
+            // every node saves its id and ports under the same names
+            let mut cfg = node.cfg.clone();
+            cfg["id"] = serde_json::to_value(id).unwrap();
+
+            for key in ["inputs", "outputs"] {
+                let Some(ports) = cfg.get_mut(key).and_then(|p| p.as_object_mut()) else {
+                    continue;
+                };
+
+                for port in ports.values_mut() {
+                    let Ok(old) = serde_json::from_value::<PortId>(port.clone()) else {
+                        continue;
+                    };
+
+                    let new = PortId::generate();
+                    port_ids.insert(old, new);
+                    *port = serde_json::to_value(new).unwrap();
+                }
+            }
+
+            NodeConfig {
+                id,
+                position: (node.position.0 + offset.x, node.position.1 + offset.y),
+                cfg,
+                ..node.clone()
+            }
+        })
+        .collect_vec();
+
+    let links = links
+        .iter()
+        .filter_map(|link| {
+            let remap = |(node, port): (NodeId, PortId)| {
+                Some((*node_ids.get(&node)?, *port_ids.get(&port)?))
+            };
+
+            Some(LinkConfig {
+                lhs: remap(link.lhs)?,
+                rhs: remap(link.rhs)?,
+            })
+        })
+        .collect_vec();
+
+    (nodes, links)
+}
+
+/// Where the graph is autosaved, next to eframe's own storage
+fn autosave_path() -> Option<PathBuf> {
+    eframe::storage_dir(APP_NAME).map(|d| d.join("autosave.json"))
+}
+
+fn read_autosave() -> Option<DSPConfig> {
+    let path = autosave_path()?;
+    let file = std::fs::File::open(&path).ok()?;
+
+    match serde_json::from_reader(file) {
+        Ok(cfg) => {
+            tracing::info!("Restoring from the autosave at {:?}", path);
+            Some(cfg)
+        }
+        Err(e) => {
+            tracing::warn!("Couldn't read the autosave at {:?}: {:?}", path, e);
+            None
+        }
+    }
+}
+
+/// Peak level above which a port is considered to be carrying signal (-80dB)
+const SIGNAL_THRESHOLD: f32 = 1e-4;
+
+fn pin_args(theme: &Theme, level: f32) -> PinArgs {
+    let background = if level > SIGNAL_THRESHOLD {
+        theme.pin_active
+    } else {
+        theme.link.linear_multiply(0.4)
+    };
+
+    PinArgs {
+        background: Some(background),
+        hovered: Some(theme.link_hovered),
+        ..Default::default()
     }
 }
 
@@ -557,15 +2553,16 @@ struct LinkInstance {
     source: Arc<Mutex<splittable::View<Source<f32>>>>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct LinkConfig {
     lhs: (NodeId, PortId),
     rhs: (NodeId, PortId),
 }
 
 impl LinkInstance {
-    fn new(id: LinkId, lhs: (NodeId, PortId), rhs: (NodeId, PortId)) -> Self {
-        let (sink, source) = rivulet::circular_buffer::<f32>(8192);
+    fn new(id: LinkId, lhs: (NodeId, PortId), rhs: (NodeId, PortId), buffer_size: usize) -> Self {
+        // the link has to fit at least a block, and one being written as well
+        let (sink, source) = rivulet::circular_buffer::<f32>(buffer_size.max(2 * buf_size()));
         let source = source.into_view();
 
         Self {
@@ -593,21 +2590,391 @@ impl LinkInstance {
     }
 }
 
+/// Runtime that nodes are spawned on if dedicated DSP threads are enabled,
+/// otherwise they share the main runtime
+static DSP_RUNTIME: OnceCell<tokio::runtime::Runtime> = OnceCell::new();
+
+/// Spawn nodes started from now on onto [`DSP_RUNTIME`]
+fn start_dsp_runtime() {
+    DSP_RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .thread_name("dsp-node-worker")
+            .on_thread_start(devices::elevate_current_thread)
+            .build()
+            .unwrap()
+    });
+}
+
+/// Level (the bits of a linear f32) below which idle nodes count as silent
+static IDLE_THRESHOLD: AtomicU32 = AtomicU32::new(3.162_277_7e-5f32.to_bits()); // -90dB
+
+/// Seconds of silence (the bits of an f32) after which a node set to sleep
+/// when idle stops processing
+static IDLE_HOLD: AtomicU32 = AtomicU32::new(5.0f32.to_bits());
+
+pub fn set_idle_sleep(threshold_db: f32, hold_secs: f32) {
+    IDLE_THRESHOLD.store(
+        10.0f32.powf(threshold_db / 20.0).to_bits(),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    IDLE_HOLD.store(hold_secs.to_bits(), std::sync::atomic::Ordering::Relaxed);
+}
+
+fn idle_hold_samples() -> usize {
+    let hold_secs = f32::from_bits(IDLE_HOLD.load(std::sync::atomic::Ordering::Relaxed));
+    (hold_secs * crate::devices::sample_rate() as f32) as usize
+}
+
+fn idle_threshold() -> f32 {
+    f32::from_bits(IDLE_THRESHOLD.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Wait for a block on every input, and check if they're all silent
+///
+/// Nothing is released, so the block is still there for the node to process.
+async fn inputs_silent(inputs: NodeInputs<'_, '_, '_>) -> bool {
+    let buf_size = buf_size();
+    let threshold = idle_threshold();
+    let mut silent = true;
+
+    for input_port in inputs.iter_mut() {
+        for input_pipe in input_port.iter_mut() {
+            input_pipe.grant(buf_size).await.unwrap();
+
+            if let Some(block) = input_pipe.view().get(..buf_size) {
+                silent &= block.iter().all(|x| x.abs() < threshold);
+            }
+        }
+    }
+
+    silent
+}
+
+/// Stand in for a sleeping node, dropping a block from each input and writing
+/// silence to each output
+async fn skip_block(inputs: NodeInputs<'_, '_, '_>, outputs: NodeOutputs<'_, '_, '_>) {
+    let buf_size = buf_size();
+
+    for output_port in outputs.iter_mut() {
+        for output_pipe in output_port.iter_mut() {
+            output_pipe.grant(buf_size).await.unwrap();
+            output_pipe.view_mut()[..buf_size].fill(0.0);
+        }
+    }
+
+    for input_port in inputs.iter_mut() {
+        for input_pipe in input_port.iter_mut() {
+            input_pipe.release(buf_size.min(input_pipe.view().len()));
+        }
+    }
+
+    for output_port in outputs.iter_mut() {
+        for output_pipe in output_port.iter_mut() {
+            output_pipe.release(buf_size);
+        }
+    }
+}
+
+/// Stand in for a bypassed node, passing its first input straight through to
+/// its first output
+///
+/// Any other inputs (sidechains, modulation) are dropped and any other outputs
+/// are silent, and a node missing either an input or an output is muted.
+async fn bypass_block(inputs: NodeInputs<'_, '_, '_>, outputs: NodeOutputs<'_, '_, '_>) {
+    if inputs.is_empty() || outputs.is_empty() {
+        skip_block(inputs, outputs).await;
+        return;
+    }
+
+    let buf_size = buf_size();
+    let mut buf = [0.0; MAX_BUF_SIZE];
+    let buf = &mut buf[..buf_size];
+    collect_and_average(buf, &mut inputs[0], input_mixing()).await;
+
+    for (idx, output_port) in outputs.iter_mut().enumerate() {
+        for output_pipe in output_port.iter_mut() {
+            output_pipe.grant(buf_size).await.unwrap();
+            if idx == 0 {
+                output_pipe.view_mut()[..buf_size].copy_from_slice(buf);
+            } else {
+                output_pipe.view_mut()[..buf_size].fill(0.0);
+            }
+        }
+    }
+
+    // inputs other than the first still need a block waited on and dropped
+    for input_port in inputs.iter_mut().skip(1) {
+        for input_pipe in input_port.iter_mut() {
+            input_pipe.grant(buf_size).await.unwrap();
+        }
+    }
+
+    for input_port in inputs.iter_mut() {
+        for input_pipe in input_port.iter_mut() {
+            input_pipe.release(buf_size.min(input_pipe.view().len()));
+        }
+    }
+
+    for output_port in outputs.iter_mut() {
+        for output_pipe in output_port.iter_mut() {
+            output_pipe.release(buf_size);
+        }
+    }
+}
+
+/// Whether processing is paused, devices keep running (playing silence) while
+/// the graph is frozen
+static FROZEN: Lazy<tokio::sync::watch::Sender<bool>> =
+    Lazy::new(|| tokio::sync::watch::channel(false).0);
+
+/// Lock every pipe of a node, the guards are held for as long as the node
+/// runs
+async fn lock_pipes(
+    inputs: &[Vec<Arc<Mutex<splittable::View<Source<f32>>>>>],
+    outputs: &[Vec<Arc<Mutex<Sink<f32>>>>],
+) -> (
+    Vec<Vec<OwnedMutexGuard<splittable::View<Source<f32>>>>>,
+    Vec<Vec<OwnedMutexGuard<Sink<f32>>>>,
+) {
+    // this is horrible
+    // should be fine though since we only do this if the graph is edited
+
+    let mut input_guards = Vec::with_capacity(inputs.len());
+    for input_port in inputs {
+        let mut guards = Vec::with_capacity(input_port.len());
+
+        for input_pipe in input_port {
+            guards.push(Arc::clone(input_pipe).lock_owned().await);
+        }
+
+        input_guards.push(guards);
+    }
+
+    let mut output_guards = Vec::with_capacity(outputs.len());
+    for output_port in outputs {
+        let mut guards = Vec::new();
+
+        for output_pipe in output_port {
+            guards.push(Arc::clone(output_pipe).lock_owned().await);
+        }
+
+        output_guards.push(guards);
+    }
+
+    (input_guards, output_guards)
+}
+
+/// Runs blocks of a node, skipping it while it's bypassed or asleep
+///
+/// This is shared by the per-node tasks and the [`Scheduler`], so the node's
+/// options behave the same however the graph is run.
+struct BlockRunner {
+    instance: Arc<Nodes>,
+    block_time: Arc<AtomicU64>,
+    sleep_when_idle: Arc<AtomicBool>,
+    bypassed: Arc<AtomicBool>,
+    /// Nodes without inputs are sources, and nodes without outputs are sinks
+    /// (devices, displays), neither should go to sleep
+    can_sleep: bool,
+    /// Samples the node has been silent for, if it's allowed to sleep
+    silent_for: usize,
+}
+
+impl BlockRunner {
+    async fn run_block(
+        &mut self,
+        inputs: NodeInputs<'_, '_, '_>,
+        outputs: NodeOutputs<'_, '_, '_>,
+    ) {
+        if self.bypassed.load(std::sync::atomic::Ordering::Relaxed) {
+            bypass_block(inputs, outputs).await;
+            return;
+        }
+
+        if self.can_sleep
+            && self
+                .sleep_when_idle
+                .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            if inputs_silent(inputs).await {
+                if self.silent_for >= idle_hold_samples() {
+                    skip_block(inputs, outputs).await;
+                    return;
+                }
+            } else {
+                self.silent_for = 0;
+            }
+        }
+
+        let (_, busy) = profiler::timed(self.instance.perform(inputs, outputs)).await;
+        profiler::record_block_time(&self.block_time, busy);
+
+        // the output counts too, so tails ring out before sleeping
+        if self.instance.outputs().take_block_peak() < idle_threshold() {
+            self.silent_for += buf_size();
+        } else {
+            self.silent_for = 0;
+        }
+    }
+}
+
+/// A single task driving every node of an acyclic graph
+///
+/// Each pass performs one block of every node in dependency order, so a block
+/// makes it through the whole graph in the same pass rather than queueing up
+/// in the link buffers between nodes.
+struct Scheduler {
+    /// How many nodes are being run
+    nodes: usize,
+    task: Option<(
+        tokio::task::JoinHandle<()>,
+        tokio::sync::oneshot::Sender<()>,
+    )>,
+}
+
+impl Scheduler {
+    /// Start running `nodes`, which must be in dependency order
+    fn start(
+        nodes: Vec<(
+            BlockRunner,
+            Vec<Vec<Arc<Mutex<splittable::View<Source<f32>>>>>>,
+            Vec<Vec<Arc<Mutex<Sink<f32>>>>>,
+        )>,
+    ) -> Self {
+        // like with unscheduled nodes, skip any that aren't connected to anything
+        let nodes = nodes
+            .into_iter()
+            .filter(|(_, inputs, outputs)| {
+                inputs.iter().any(|v| !v.is_empty()) || outputs.iter().any(|v| !v.is_empty())
+            })
+            .collect_vec();
+
+        tracing::debug!(nodes = nodes.len(), "Starting scheduled graph");
+
+        for (runner, _, _) in &nodes {
+            runner.instance.outputs().restart_fade();
+        }
+
+        let (cancel_in, mut cancel_out) = tokio::sync::oneshot::channel();
+        let mut frozen = FROZEN.subscribe();
+        let count = nodes.len();
+
+        let coro = async move {
+            let mut locked = Vec::with_capacity(nodes.len());
+            for (runner, inputs, outputs) in nodes {
+                let (inputs, outputs) = lock_pipes(&inputs, &outputs).await;
+                locked.push((runner, inputs, outputs));
+            }
+
+            let mut refs = locked
+                .iter_mut()
+                .map(|(runner, inputs, outputs)| {
+                    (
+                        runner,
+                        inputs
+                            .iter_mut()
+                            .map(|input| input.iter_mut().map(|g| g.deref_mut()).collect_vec())
+                            .collect_vec(),
+                        outputs
+                            .iter_mut()
+                            .map(|output| output.iter_mut().map(|g| g.deref_mut()).collect_vec())
+                            .collect_vec(),
+                    )
+                })
+                .collect_vec();
+
+            let mut slices = refs
+                .iter_mut()
+                .map(|(runner, inputs, outputs)| {
+                    (
+                        &mut **runner,
+                        inputs.iter_mut().map(|x| x.as_mut_slice()).collect_vec(),
+                        outputs.iter_mut().map(|x| x.as_mut_slice()).collect_vec(),
+                    )
+                })
+                .collect_vec();
+
+            loop {
+                if *frozen.borrow() {
+                    tokio::select! {
+                        _ = &mut cancel_out => {
+                            return;
+                        },
+                        _ = frozen.wait_for(|f| !*f) => {}
+                    }
+                }
+
+                let pass = async {
+                    for (runner, inputs, outputs) in &mut slices {
+                        runner.run_block(inputs, outputs).await;
+                    }
+                };
+                tokio::pin!(pass);
+
+                tokio::select! {
+                    _ = &mut cancel_out => {
+                        return;
+                    },
+                    _ = &mut pass => {}
+                }
+            }
+        };
+
+        let task = match DSP_RUNTIME.get() {
+            Some(rt) => rt.spawn(coro),
+            None => tokio::spawn(coro),
+        };
+
+        Self {
+            nodes: count,
+            task: Some((task, cancel_in)),
+        }
+    }
+
+    fn stop(&mut self) {
+        tracing::debug!("Stopping scheduled graph");
+        if let Some((handle, stop)) = self.task.take() {
+            let rthandle = Handle::current();
+            let _ = stop.send(());
+            let _ = rthandle.block_on(handle);
+        }
+    }
+}
+
 struct NodeInstance {
     id: NodeId,
     instance: Arc<Nodes>,
     position: egui::Pos2,
+    /// A user given name for the node, empty to use the node's title
+    label: String,
+    /// Skip processing while the node's inputs and outputs are silent
+    sleep_when_idle: Arc<AtomicBool>,
+    /// Pass the inputs straight through instead of running the node
+    bypassed: Arc<AtomicBool>,
+    /// Moving average of the nanoseconds taken to process a block
+    block_time: Arc<AtomicU64>,
+    /// The rate a rate dependent node was set up at, if the graph's rate has
+    /// changed since and the warning hasn't been dismissed
+    rate_warning: Option<u32>,
     task: Option<(
         tokio::task::JoinHandle<()>,
         tokio::sync::oneshot::Sender<()>,
     )>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct NodeConfig {
     id: NodeId,
     typename: String,
     position: (f32, f32),
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    sleep_when_idle: bool,
+    #[serde(default)]
+    gain_match: bool,
+    #[serde(default)]
+    bypassed: bool,
     cfg: serde_json::Value,
 }
 
@@ -617,6 +2984,11 @@ impl NodeInstance {
             id,
             instance,
             position: pos2(100.0, 100.0),
+            label: String::new(),
+            sleep_when_idle: Arc::default(),
+            bypassed: Arc::default(),
+            block_time: Arc::default(),
+            rate_warning: None,
             task: None,
         }
     }
@@ -626,33 +2998,71 @@ impl NodeInstance {
             id: self.id,
             typename: self.instance.cfg_name().to_owned(),
             position: self.position.into(),
+            label: self.label.clone(),
+            sleep_when_idle: self
+                .sleep_when_idle
+                .load(std::sync::atomic::Ordering::Relaxed),
+            gain_match: self
+                .instance
+                .outputs()
+                .gain_match()
+                .enabled
+                .load(std::sync::atomic::Ordering::Relaxed),
+            bypassed: self.bypassed.load(std::sync::atomic::Ordering::Relaxed),
             cfg: self.instance.save(),
         }
     }
 
-    fn restore(cfg: NodeConfig) -> Self {
+    fn restore(cfg: NodeConfig) -> Result<Self, String> {
         let (_, restorer) = crate::nodes::RESTORE
             .iter()
             .find(|(n, _)| n == &cfg.typename)
-            .unwrap();
+            .ok_or_else(|| format!("unknown node type {}", cfg.typename))?;
 
-        let inst = restorer(cfg.cfg);
+        let inst = restorer(cfg.cfg).map_err(|e| format!("{}: {}", cfg.typename, e))?;
 
         let mut this = Self::new(cfg.id, inst);
         this.position = egui::Pos2::from(cfg.position);
-        this
+        this.label = cfg.label;
+        this.sleep_when_idle = Arc::new(AtomicBool::new(cfg.sleep_when_idle));
+        this.bypassed = Arc::new(AtomicBool::new(cfg.bypassed));
+        this.instance
+            .outputs()
+            .gain_match()
+            .enabled
+            .store(cfg.gain_match, std::sync::atomic::Ordering::Relaxed);
+        Ok(this)
+    }
+
+    /// The name to show for this node, falling back to the node's title if
+    /// it hasn't been given one
+    fn title(&self) -> &str {
+        if self.label.is_empty() {
+            self.instance.title()
+        } else {
+            &self.label
+        }
+    }
+
+    fn block_runner(&self, num_inputs: usize, num_outputs: usize) -> BlockRunner {
+        BlockRunner {
+            instance: Arc::clone(&self.instance),
+            block_time: Arc::clone(&self.block_time),
+            sleep_when_idle: Arc::clone(&self.sleep_when_idle),
+            bypassed: Arc::clone(&self.bypassed),
+            can_sleep: num_inputs > 0 && num_outputs > 0,
+            silent_for: 0,
+        }
     }
 
     fn start(
         &mut self,
-        mut inputs: Vec<Vec<Arc<Mutex<splittable::View<Source<f32>>>>>>,
-        mut outputs: Vec<Vec<Arc<Mutex<Sink<f32>>>>>,
+        inputs: Vec<Vec<Arc<Mutex<splittable::View<Source<f32>>>>>>,
+        outputs: Vec<Vec<Arc<Mutex<Sink<f32>>>>>,
     ) {
         assert!(self.task.is_none());
         let id = self.id;
 
-        let instance = Arc::clone(&self.instance);
-
         let num_inputs: usize = inputs.iter().map(|v| v.len()).sum();
         let num_outputs: usize = outputs.iter().map(|v| v.len()).sum();
 
@@ -667,39 +3077,20 @@ impl NodeInstance {
             return;
         }
 
+        self.instance.outputs().restart_fade();
+
         let (cancel_in, mut cancel_out) = tokio::sync::oneshot::channel();
+        let mut frozen = FROZEN.subscribe();
+        let mut runner = self.block_runner(num_inputs, num_outputs);
 
         let coro = async move {
-            // this is horrible
-            // should be fine though since we only do this if the graph is edited
-
-            let mut input_slices_v = Vec::with_capacity(inputs.len());
-            for input_port in &mut inputs {
-                let mut guards = Vec::with_capacity(input_port.len());
-
-                for input_pipe in input_port {
-                    guards.push(Arc::clone(input_pipe).lock_owned().await);
-                }
-
-                input_slices_v.push(guards);
-            }
+            let (mut input_slices_v, mut output_slices_v) = lock_pipes(&inputs, &outputs).await;
 
             let mut input_slices = input_slices_v
                 .iter_mut()
                 .map(|input| input.iter_mut().map(|g| g.deref_mut()).collect::<Vec<_>>())
                 .collect_vec();
 
-            let mut output_slices_v = Vec::with_capacity(outputs.len());
-            for output_port in &mut outputs {
-                let mut guards = Vec::new();
-
-                for output_pipe in output_port {
-                    guards.push(Arc::clone(output_pipe).lock_owned().await);
-                }
-
-                output_slices_v.push(guards);
-            }
-
             let mut output_slices = output_slices_v
                 .iter_mut()
                 .map(|output| output.iter_mut().map(|g| g.deref_mut()).collect::<Vec<_>>())
@@ -716,7 +3107,18 @@ impl NodeInstance {
                 .collect_vec();
 
             loop {
-                let perform = instance.perform(&mut input_slices, &mut output_slices);
+                // hold here rather than between links so every node keeps its
+                // state until the graph is unfrozen
+                if *frozen.borrow() {
+                    tokio::select! {
+                        _ = &mut cancel_out => {
+                            return;
+                        },
+                        _ = frozen.wait_for(|f| !*f) => {}
+                    }
+                }
+
+                let perform = runner.run_block(&mut input_slices, &mut output_slices);
                 tokio::pin!(perform);
 
                 tokio::select! {
@@ -728,7 +3130,12 @@ impl NodeInstance {
             }
         };
 
-        self.task = Some((tokio::spawn(coro), cancel_in));
+        let task = match DSP_RUNTIME.get() {
+            Some(rt) => rt.spawn(coro),
+            None => tokio::spawn(coro),
+        };
+
+        self.task = Some((task, cancel_in));
     }
 
     fn stop(&mut self) {
@@ -750,3 +3157,412 @@ impl NodeInstance {
         self.start(inputs, outputs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::NodeStatic,
+        nodes::{feedback::Feedback, gain::Gain},
+    };
+    use std::time::{Duration, Instant};
+
+    fn breaks_cycles(nodes: &HashMap<NodeId, Nodes>) -> impl Fn(NodeId) -> bool + '_ {
+        |id| nodes[&id].breaks_cycles()
+    }
+
+    fn gain() -> (NodeId, Nodes) {
+        let id = NodeId::generate();
+        (id, Nodes::from(Gain::new(id)))
+    }
+
+    #[test]
+    fn three_node_cycle_is_refused() {
+        let [(a, a_node), (b, b_node), (c, c_node)] = [gain(), gain(), gain()];
+        let nodes = HashMap::from([(a, a_node), (b, b_node), (c, c_node)]);
+        let links = [(a, b), (b, c)];
+
+        assert!(closes_cycle(&links, breaks_cycles(&nodes), c, a));
+        assert!(!closes_cycle(&links, breaks_cycles(&nodes), a, c));
+    }
+
+    #[test]
+    fn cycle_through_feedback_is_allowed() {
+        let [(a, a_node), (b, b_node)] = [gain(), gain()];
+        let fb = NodeId::generate();
+        let nodes = HashMap::from([
+            (a, a_node),
+            (b, b_node),
+            (fb, Nodes::from(Feedback::new(fb))),
+        ]);
+
+        // closing the loop into the feedback node
+        let links = [(fb, a), (a, b)];
+        assert!(!closes_cycle(&links, breaks_cycles(&nodes), b, fb));
+
+        // and closing it elsewhere, with the feedback node in the middle
+        let links = [(a, fb), (fb, b)];
+        assert!(!closes_cycle(&links, breaks_cycles(&nodes), b, a));
+    }
+
+    fn ports(node: &NodeInstance) -> HashSet<PortId> {
+        let inputs = node.instance.inputs().get_all();
+        let outputs = node.instance.outputs().get_all();
+
+        inputs.into_values().chain(outputs.into_values()).collect()
+    }
+
+    #[test]
+    fn duplicated_nodes_are_relinked() {
+        let [a, b] = [NodeId::generate(), NodeId::generate()]
+            .map(|id| NodeInstance::new(id, Arc::new(Nodes::from(Gain::new(id)))));
+        let link = LinkConfig {
+            lhs: (a.id, a.instance.outputs().get_id("out").unwrap()),
+            rhs: (b.id, b.instance.inputs().get_id("in").unwrap()),
+        };
+
+        let (nodes, links) = duplicate_nodes(&[a.save(), b.save()], &[link], PASTE_OFFSET);
+        let [a_copy, b_copy] = <[NodeConfig; 2]>::try_from(nodes)
+            .ok()
+            .unwrap()
+            .map(|n| NodeInstance::restore(n).unwrap());
+
+        for (node, copy) in [(&a, &a_copy), (&b, &b_copy)] {
+            assert_ne!(node.id, copy.id);
+            assert!(ports(node).is_disjoint(&ports(copy)));
+            assert_eq!(node.position + PASTE_OFFSET, copy.position);
+        }
+
+        let [link] = links.as_slice() else {
+            panic!("expected one link, got {}", links.len());
+        };
+        assert_eq!(
+            link.lhs,
+            (a_copy.id, a_copy.instance.outputs().get_id("out").unwrap())
+        );
+        assert_eq!(
+            link.rhs,
+            (b_copy.id, b_copy.instance.inputs().get_id("in").unwrap())
+        );
+    }
+
+    /// A chain of gain nodes, with a link into the first and out of the last,
+    /// for timing the runtime
+    struct GainChain {
+        nodes: Vec<NodeInstance>,
+        /// The inputs and outputs of each node
+        pipes: Vec<(
+            Vec<Vec<Arc<Mutex<splittable::View<Source<f32>>>>>>,
+            Vec<Vec<Arc<Mutex<Sink<f32>>>>>,
+        )>,
+        head: Arc<Mutex<Sink<f32>>>,
+        tail: Arc<Mutex<splittable::View<Source<f32>>>>,
+    }
+
+    impl GainChain {
+        fn new(len: usize) -> Self {
+            let nodes = (0..len)
+                .map(|_| {
+                    let id = NodeId::generate();
+                    NodeInstance::new(id, Arc::new(Nodes::from(Gain::new(id))))
+                })
+                .collect_vec();
+
+            // link i feeds node i, and node i feeds link i + 1
+            let links = (0..=len)
+                .map(|_| {
+                    let (node, port) = (NodeId::generate(), PortId::generate());
+                    LinkInstance::new(
+                        LinkId::generate(),
+                        (node, port),
+                        (node, port),
+                        Settings::default().link_buffer_size,
+                    )
+                })
+                .collect_vec();
+
+            // every other port (the gain's level) is left unconnected
+            let pipes = nodes
+                .iter()
+                .zip(links.iter().tuple_windows())
+                .map(|(node, (input, output))| {
+                    let inputs = node.instance.inputs();
+                    let outputs = node.instance.outputs();
+
+                    let mut input_pipes = vec![Vec::new(); inputs.get_idxs().len()];
+                    input_pipes[inputs.get_idx("in").unwrap()].push(Arc::clone(&input.source));
+
+                    let mut output_pipes = vec![Vec::new(); outputs.get_idxs().len()];
+                    output_pipes[outputs.get_idx("out").unwrap()].push(Arc::clone(&output.sink));
+
+                    (input_pipes, output_pipes)
+                })
+                .collect_vec();
+
+            Self {
+                nodes,
+                pipes,
+                head: Arc::clone(&links[0].sink),
+                tail: Arc::clone(&links[len].source),
+            }
+        }
+
+        /// Run each node as its own task
+        fn start(&mut self) {
+            for (node, (inputs, outputs)) in self.nodes.iter_mut().zip(&self.pipes) {
+                node.start(inputs.clone(), outputs.clone());
+            }
+        }
+
+        /// Run every node in one task, in chain order
+        fn schedule(&self) -> Scheduler {
+            let nodes = self
+                .nodes
+                .iter()
+                .zip(&self.pipes)
+                .map(|(node, (inputs, outputs))| {
+                    (node.block_runner(1, 1), inputs.clone(), outputs.clone())
+                })
+                .collect_vec();
+
+            Scheduler::start(nodes)
+        }
+
+        fn stop(&mut self) {
+            for node in &mut self.nodes {
+                node.stop();
+            }
+        }
+
+        /// Push `blocks` blocks of `level` through the chain, each side as fast
+        /// as the chain allows unless given a pace, returning when each block
+        /// went in and came out
+        ///
+        /// Every node passes one block on for each it's given, so the nth block
+        /// out is the nth block in.
+        async fn pump(
+            &self,
+            blocks: usize,
+            level: f32,
+            feed_pace: Option<Duration>,
+            drain_pace: Option<Duration>,
+        ) -> Vec<(Instant, Instant)> {
+            let buf_size = buf_size();
+
+            let feed = async {
+                let mut head = self.head.lock().await;
+                let mut fed = Vec::with_capacity(blocks);
+
+                for _ in 0..blocks {
+                    if let Some(pace) = feed_pace {
+                        tokio::time::sleep(pace).await;
+                    }
+
+                    head.grant(buf_size).await.unwrap();
+                    head.view_mut()[..buf_size].fill(level);
+                    head.release(buf_size);
+                    fed.push(Instant::now());
+                }
+
+                fed
+            };
+
+            let drain = async {
+                let mut tail = self.tail.lock().await;
+                let mut drained = Vec::with_capacity(blocks);
+
+                for _ in 0..blocks {
+                    if let Some(pace) = drain_pace {
+                        tokio::time::sleep(pace).await;
+                    }
+
+                    tail.grant(buf_size).await.unwrap();
+                    tail.release(buf_size);
+                    drained.push(Instant::now());
+                }
+
+                drained
+            };
+
+            let (fed, drained) = tokio::join!(feed, drain);
+            fed.into_iter().zip(drained).collect()
+        }
+    }
+
+    /// Runtime to time a chain on, nodes are spawned onto it while it's entered
+    fn timing_runtime(workers: usize) -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(workers)
+            .enable_time()
+            .build()
+            .unwrap()
+    }
+
+    /// How much sleeping saves on a silent patch, run with
+    /// `cargo test -- --ignored idle_sleep_saves_cpu`
+    ///
+    /// The nodes share one worker thread and nothing is paced, so the time
+    /// taken is the time the nodes spend processing. Skipping every block
+    /// should take off at least a quarter of that.
+    #[test]
+    #[ignore = "measurement"]
+    fn idle_sleep_saves_cpu() {
+        const NODES: usize = 30;
+        const BLOCKS: usize = 10_000;
+
+        let rt = timing_runtime(1);
+        let _guard = rt.enter();
+
+        // sleep as soon as the silence starts, so every block is skipped
+        set_idle_sleep(-90.0, 0.0);
+
+        let per_block = [false, true].map(|sleep_when_idle| {
+            let mut chain = GainChain::new(NODES);
+            for node in &chain.nodes {
+                node.sleep_when_idle
+                    .store(sleep_when_idle, std::sync::atomic::Ordering::Relaxed);
+            }
+            chain.start();
+
+            let started = Instant::now();
+            rt.block_on(chain.pump(BLOCKS, 0.0, None, None));
+            let per_block = started.elapsed() / BLOCKS as u32;
+
+            chain.stop();
+            tracing::info!(nodes = NODES, sleep_when_idle, ?per_block, "Silent chain");
+            per_block
+        });
+
+        let defaults = Settings::default();
+        set_idle_sleep(defaults.idle_threshold, defaults.idle_hold);
+
+        let [awake, asleep] = per_block;
+        assert!(
+            asleep < awake.mul_f64(0.75),
+            "sleeping took {asleep:?} per block against {awake:?} awake"
+        );
+    }
+
+    /// How long a block takes to get through a chain, run with
+    /// `cargo test -- --ignored scheduled_chain_latency`
+    ///
+    /// The chain is fed as fast as it takes blocks and drained at the pace a
+    /// device would, so whatever queues up in the links shows as latency.
+    /// Running the chain in order should leave less of it queued.
+    #[test]
+    #[ignore = "measurement"]
+    fn scheduled_chain_latency() {
+        const NODES: usize = 10;
+        const BLOCKS: usize = 2000;
+
+        let rt = timing_runtime(4);
+        let _guard = rt.enter();
+        let pace = profiler::block_budget();
+
+        let means = [false, true].map(|scheduled| {
+            let mut chain = GainChain::new(NODES);
+            let mut scheduler = if scheduled {
+                Some(chain.schedule())
+            } else {
+                chain.start();
+                None
+            };
+
+            let times = rt.block_on(chain.pump(BLOCKS, 0.5, None, Some(pace)));
+
+            match &mut scheduler {
+                Some(scheduler) => scheduler.stop(),
+                None => chain.stop(),
+            }
+
+            // only the last quarter, by then every link has filled up
+            let latencies = times[BLOCKS * 3 / 4..]
+                .iter()
+                .map(|(fed, drained)| drained.saturating_duration_since(*fed))
+                .collect_vec();
+            let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+
+            tracing::info!(nodes = NODES, scheduled, ?mean, "Chain latency");
+            mean
+        });
+
+        let [unscheduled, scheduled] = means;
+        assert!(
+            scheduled < unscheduled,
+            "scheduled took {scheduled:?} against {unscheduled:?} unscheduled"
+        );
+    }
+
+    /// How much the time taken through a chain varies while other tasks hog
+    /// the main runtime, run with
+    /// `cargo test -- --ignored dedicated_dsp_threads_jitter`
+    ///
+    /// This has to be run on its own, as the dedicated runtime can't be turned
+    /// off again once it's been started. Away from the hogs the deviation
+    /// should at least halve.
+    #[test]
+    #[ignore = "measurement"]
+    fn dedicated_dsp_threads_jitter() {
+        const NODES: usize = 10;
+        const BLOCKS: usize = 2000;
+        const HOGS: usize = 8;
+
+        assert!(
+            DSP_RUNTIME.get().is_none(),
+            "the dedicated runtime was already started"
+        );
+
+        let rt = timing_runtime(2);
+        let _guard = rt.enter();
+        let pace = profiler::block_budget();
+
+        // stand ins for tasks doing blocking work on the main runtime
+        for _ in 0..HOGS {
+            tokio::spawn(async {
+                loop {
+                    std::thread::sleep(Duration::from_millis(1));
+                    tokio::task::yield_now().await;
+                }
+            });
+        }
+
+        let deviations = [false, true].map(|dedicated| {
+            if dedicated {
+                start_dsp_runtime();
+            }
+
+            let mut chain = GainChain::new(NODES);
+            chain.start();
+            let times = rt.block_on(chain.pump(BLOCKS, 0.5, Some(pace), None));
+            chain.stop();
+
+            let latencies = times
+                .iter()
+                .map(|(fed, drained)| drained.saturating_duration_since(*fed).as_secs_f64())
+                .collect_vec();
+            let mean = latencies.iter().sum::<f64>() / latencies.len() as f64;
+            let deviation = (latencies.iter().map(|l| (l - mean).powi(2)).sum::<f64>()
+                / latencies.len() as f64)
+                .sqrt();
+            let worst = latencies.iter().copied().fold(0.0, f64::max);
+
+            tracing::info!(
+                nodes = NODES,
+                dedicated,
+                mean_ms = mean * 1000.0,
+                deviation_ms = deviation * 1000.0,
+                worst_ms = worst * 1000.0,
+                "Chain jitter"
+            );
+            deviation
+        });
+
+        let [shared, dedicated] = deviations;
+        assert!(
+            dedicated < shared * 0.5,
+            "dedicated threads deviated by {:.3}ms against {:.3}ms shared",
+            dedicated * 1000.0,
+            shared * 1000.0
+        );
+    }
+}
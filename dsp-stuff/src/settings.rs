@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{devices, node, runtime};
+
+/// Global settings
+///
+/// These are persisted separately from the graph, as they depend on the
+/// machine being used rather than the patch.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// Capacity (in samples) of the buffer backing each link, raised to at
+    /// least two blocks
+    ///
+    /// Nodes run as fast as their links allow, so a link between a node that
+    /// runs ahead and one held back by an output device fills up entirely, and
+    /// everything in it is latency. Once full it stays full, as the device only
+    /// takes audio at its own rate. The only way back is the output catch-up
+    /// ([`devices::OutputControls`]), which drops the device's backlog beyond
+    /// the target latency on a resync, so the output node and everything
+    /// feeding it can drain their links into the space that frees up.
+    pub link_buffer_size: usize,
+
+    /// Samples each node processes at a time, a power of two between
+    /// [`node::MIN_BUF_SIZE`] and [`node::MAX_BUF_SIZE`]
+    ///
+    /// Smaller blocks lower the latency through the graph, larger ones reduce
+    /// the overhead of passing blocks between nodes. Output devices are fed a
+    /// block at a time, so blocks much larger than the device's callback add
+    /// jitter to how full its buffer is.
+    pub block_size: usize,
+
+    /// How many output callbacks a resync stays armed for
+    pub resync_callbacks: u8,
+
+    /// Factor to upsample by before converting to an output device's rate
+    ///
+    /// Off (1) by default. Oversampling adds a second resampling stage running
+    /// at the oversampled rate, so the resampling cost of each output grows
+    /// roughly with the factor (4x costs about five times as much as off).
+    pub output_oversampling: u32,
+
+    /// How long (in ms) an output fades out before switching device, and back
+    /// in afterwards
+    pub device_switch_fade: u32,
+
+    /// How long (in ms) a node's output fades in for after it's (re)started,
+    /// so editing the graph doesn't click
+    pub start_fade: u32,
+
+    /// Try to run audio threads at real-time priority
+    pub elevated_priority: bool,
+
+    /// Run node processing on its own pool of threads, separate from
+    /// everything else the runtime does
+    ///
+    /// Only read at startup.
+    pub dedicated_dsp_threads: bool,
+
+    /// Run acyclic graphs as a single task that processes every node in
+    /// dependency order, rather than a task per node
+    pub scheduled_graph: bool,
+
+    /// Level (in dB) below which nodes set to sleep when idle consider their
+    /// inputs and outputs silent
+    pub idle_threshold: f32,
+
+    /// How long (in seconds) a node must be silent for before it sleeps, long
+    /// enough for reverb and delay tails to die away
+    pub idle_hold: f32,
+
+    /// Gain applied to everything sent to output devices
+    pub master_gain: f32,
+
+    pub muted: bool,
+
+    /// Periodically save the graph to a file of its own, so a crash doesn't
+    /// lose it
+    pub autosave: bool,
+
+    /// How often (in seconds) the graph is autosaved
+    pub autosave_interval: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            link_buffer_size: 8192,
+            block_size: 128,
+            resync_callbacks: 5,
+            output_oversampling: 1,
+            device_switch_fade: 20,
+            start_fade: 5,
+            elevated_priority: false,
+            dedicated_dsp_threads: false,
+            scheduled_graph: false,
+            idle_threshold: -90.0,
+            idle_hold: 5.0,
+            master_gain: 1.0,
+            muted: false,
+            autosave: true,
+            autosave_interval: 60,
+        }
+    }
+}
+
+impl Settings {
+    /// Push the settings that live outside of the ui to where they're used
+    pub fn apply(&self) {
+        devices::set_catchup(self.resync_callbacks);
+        devices::set_output_oversampling(self.output_oversampling);
+        devices::set_switch_fade(self.device_switch_fade);
+        devices::set_elevated_priority(self.elevated_priority);
+        node::set_start_fade(self.start_fade);
+        node::set_buf_size(self.block_size);
+        runtime::set_idle_sleep(self.idle_threshold, self.idle_hold);
+        devices::set_master_gain(self.master_gain, self.muted);
+    }
+}
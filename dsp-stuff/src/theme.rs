@@ -1,19 +1,102 @@
 use eframe::egui;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
+use crate::nodes::Category;
+
+/// Colours are saved as `[r, g, b, a]`
+mod rgba {
+    use eframe::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(colour: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        colour.to_array().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let [r, g, b, a] = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(Color32::from_rgba_premultiplied(r, g, b, a))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategoryColors {
+    #[serde(with = "rgba")]
+    pub titlebar: egui::Color32,
+    #[serde(with = "rgba")]
+    pub titlebar_hovered: egui::Color32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Theme {
+    #[serde(with = "rgba")]
     pub titlebar: egui::Color32,
+    #[serde(with = "rgba")]
     pub titlebar_hovered: egui::Color32,
 
+    #[serde(with = "rgba")]
     pub text: egui::Color32,
     pub dark: bool,
 
+    #[serde(with = "rgba")]
     pub grid_background: egui::Color32,
 
+    #[serde(with = "rgba")]
     pub node_background: egui::Color32,
+    #[serde(with = "rgba")]
     pub node_background_hovered: egui::Color32,
 
+    #[serde(with = "rgba")]
     pub link: egui::Color32,
+    #[serde(with = "rgba")]
     pub link_hovered: egui::Color32,
+
+    /// Pins which have had signal pass through them recently
+    #[serde(with = "rgba")]
+    pub pin_active: egui::Color32,
+
+    /// Errors, and anything over its limit
+    #[serde(with = "rgba", default = "default_error")]
+    pub error: egui::Color32,
+    /// Anything getting close to its limit
+    #[serde(with = "rgba", default = "default_warning")]
+    pub warning: egui::Color32,
+
+    /// Title bar colours for each node category, indexed by `Category`
+    pub categories: [CategoryColors; 6],
+}
+
+impl Theme {
+    /// Read a theme from a json file, in the same format custom themes are
+    /// saved in
+    pub fn from_file(path: &std::path::Path) -> color_eyre::Result<Theme> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn category(&self, category: Category) -> &CategoryColors {
+        &self.categories[category as usize]
+    }
+}
+
+// for themes saved before these could be changed
+fn default_error() -> egui::Color32 {
+    MONOKAI.error
+}
+
+fn default_warning() -> egui::Color32 {
+    MONOKAI.warning
+}
+
+const fn category(titlebar: [u8; 3], titlebar_hovered: [u8; 3]) -> CategoryColors {
+    CategoryColors {
+        titlebar: egui::Color32::from_rgb(titlebar[0], titlebar[1], titlebar[2]),
+        titlebar_hovered: egui::Color32::from_rgb(
+            titlebar_hovered[0],
+            titlebar_hovered[1],
+            titlebar_hovered[2],
+        ),
+    }
 }
 
 pub static MONOKAI: Theme = Theme {
@@ -26,6 +109,18 @@ pub static MONOKAI: Theme = Theme {
     node_background_hovered: egui::Color32::from_rgba_premultiplied(0x5b, 0x53, 0x53, 0xff),
     link: egui::Color32::from_rgba_premultiplied(0xa8, 0xa9, 0xeb, 0xff),
     link_hovered: egui::Color32::from_rgba_premultiplied(0xb8, 0xb9, 0xfb, 0xff),
+    pin_active: egui::Color32::from_rgba_premultiplied(0xa6, 0xe2, 0x2e, 0xff),
+    error: egui::Color32::from_rgba_premultiplied(0xf9, 0x26, 0x72, 0xff),
+    warning: egui::Color32::from_rgba_premultiplied(0xe6, 0xdb, 0x74, 0xff),
+    // the monokai accents, darkened enough to keep the title text readable
+    categories: [
+        category([0x4f, 0x6a, 0x2a], [0x61, 0x80, 0x3a]),
+        category([0x2f, 0x64, 0x70], [0x3e, 0x7a, 0x88]),
+        category([0x6e, 0x67, 0x34], [0x85, 0x7d, 0x44]),
+        category([0x7a, 0x2f, 0x47], [0x92, 0x3c, 0x58]),
+        category([0x5b, 0x53, 0x53], [0x72, 0x69, 0x6a]),
+        category([0x5a, 0x4a, 0x80], [0x6d, 0x5b, 0x98]),
+    ],
 };
 
 pub static SOLARIZED: Theme = Theme {
@@ -38,6 +133,124 @@ pub static SOLARIZED: Theme = Theme {
     node_background_hovered: egui::Color32::from_rgba_premultiplied(0x58, 0x6e, 0x75, 0xff),
     link: egui::Color32::from_rgba_premultiplied(0x6c, 0x71, 0xc4, 0xff),
     link_hovered: egui::Color32::from_rgba_premultiplied(0x26, 0x8b, 0xd2, 0xff),
+    pin_active: egui::Color32::from_rgba_premultiplied(0x85, 0x99, 0x00, 0xff),
+    error: egui::Color32::from_rgba_premultiplied(0xdc, 0x32, 0x2f, 0xff),
+    warning: egui::Color32::from_rgba_premultiplied(0xb5, 0x89, 0x00, 0xff),
+    categories: [
+        category([0x4a, 0x68, 0x21], [0x5a, 0x7a, 0x2c]),
+        category([0x1c, 0x5f, 0x7e], [0x25, 0x6f, 0x92]),
+        category([0x6b, 0x5a, 0x1a], [0x7e, 0x6b, 0x24]),
+        category([0x7a, 0x2e, 0x5a], [0x8e, 0x3a, 0x6b]),
+        category([0x58, 0x6e, 0x75], [0x65, 0x7b, 0x83]),
+        category([0x4a, 0x50, 0x88], [0x58, 0x5e, 0x9c]),
+    ],
 };
 
 pub static THEMES: &[(&str, &Theme)] = &[("Monokai", &MONOKAI), ("Solarized", &SOLARIZED)];
+
+/// A window for tweaking the current theme, and saving it under a name
+#[derive(Default)]
+pub struct ThemeEditor {
+    /// The theme being edited, while the window is open
+    editing: Option<Theme>,
+    name: String,
+}
+
+impl ThemeEditor {
+    pub fn open(&mut self, theme: &Theme) {
+        self.editing = Some(theme.clone());
+    }
+
+    /// Draw the editor if it's open, returning the theme when it's been changed
+    /// so it can be applied straight away
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        custom_themes: &mut Vec<(String, Theme)>,
+    ) -> Option<Theme> {
+        let theme = self.editing.as_mut()?;
+        let mut open = true;
+        let mut changed = false;
+
+        egui::Window::new("Theme editor")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                changed |= ui.checkbox(&mut theme.dark, "Dark").changed();
+
+                egui::Grid::new("theme_colours").show(ui, |ui| {
+                    let mut colour = |ui: &mut egui::Ui, label: &str, c: &mut egui::Color32| {
+                        ui.label(label);
+                        changed |= egui::color_picker::color_edit_button_srgba(
+                            ui,
+                            c,
+                            egui::color_picker::Alpha::Opaque,
+                        )
+                        .changed();
+                        ui.end_row();
+                    };
+
+                    colour(ui, "Text", &mut theme.text);
+                    colour(ui, "Title bar", &mut theme.titlebar);
+                    colour(ui, "Title bar hovered", &mut theme.titlebar_hovered);
+                    colour(ui, "Grid", &mut theme.grid_background);
+                    colour(ui, "Node", &mut theme.node_background);
+                    colour(ui, "Node hovered", &mut theme.node_background_hovered);
+                    colour(ui, "Link", &mut theme.link);
+                    colour(ui, "Link hovered", &mut theme.link_hovered);
+                    colour(ui, "Active pin", &mut theme.pin_active);
+                    colour(ui, "Error", &mut theme.error);
+                    colour(ui, "Warning", &mut theme.warning);
+
+                    for category in Category::iter() {
+                        let name: &'static str = category.into();
+                        let colours = &mut theme.categories[category as usize];
+                        colour(ui, name, &mut colours.titlebar);
+                        colour(
+                            ui,
+                            &format!("{name} hovered"),
+                            &mut colours.titlebar_hovered,
+                        );
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.name).hint_text("Name"));
+
+                    let name = self.name.trim();
+                    if ui
+                        .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                        .on_hover_text_at_pointer(
+                            "Add to the theme menu, replacing any theme with the same name",
+                        )
+                        .clicked()
+                    {
+                        match custom_themes.iter_mut().find(|(n, _)| n == name) {
+                            Some((_, saved)) => *saved = theme.clone(),
+                            None => custom_themes.push((name.to_owned(), theme.clone())),
+                        }
+                    }
+
+                    let saved = custom_themes.iter().position(|(n, _)| n == name);
+                    if ui
+                        .add_enabled(saved.is_some(), egui::Button::new("Delete"))
+                        .clicked()
+                    {
+                        if let Some(idx) = saved {
+                            custom_themes.remove(idx);
+                        }
+                    }
+                });
+            });
+
+        let edited = changed.then(|| theme.clone());
+
+        if !open {
+            self.editing = None;
+        }
+
+        edited
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+/// How to read between samples, in increasing order of quality and cost
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    atomig::Atom,
+    strum::EnumIter,
+    strum::IntoStaticStr,
+    Clone,
+    Copy,
+    Debug,
+)]
+#[repr(u8)]
+pub enum Interpolation {
+    /// Take the nearest earlier sample
+    None,
+    Linear,
+    /// Four point Catmull-Rom spline
+    Cubic,
+    /// Eight point Lanczos windowed sinc
+    Sinc,
+}
+
+/// Half the width (in samples) of the sinc kernel
+const SINC_HALF_WIDTH: isize = 4;
+
+fn lanczos(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        return 1.0;
+    }
+
+    if x.abs() >= SINC_HALF_WIDTH as f32 {
+        return 0.0;
+    }
+
+    let a = SINC_HALF_WIDTH as f32;
+    let px = std::f32::consts::PI * x;
+
+    a * px.sin() * (px / a).sin() / (px * px)
+}
+
+/// Read the signal at the fractional position `pos`
+///
+/// `sample_at` gives the sample at an index, it's responsible for handling
+/// indexes past the end of the signal. Indexes before the start are clamped to
+/// zero before being passed to it.
+pub fn read_fractional(sample_at: impl Fn(usize) -> f32, pos: f32, mode: Interpolation) -> f32 {
+    let idx = pos.floor() as isize;
+    let frac = pos - idx as f32;
+
+    let at = |k: isize| sample_at((idx + k).max(0) as usize);
+
+    match mode {
+        Interpolation::None => at(0),
+        Interpolation::Linear => at(0) * (1.0 - frac) + at(1) * frac,
+        Interpolation::Cubic => {
+            let (xm1, x0, x1, x2) = (at(-1), at(0), at(1), at(2));
+
+            let c1 = 0.5 * (x1 - xm1);
+            let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+            let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+
+            ((c3 * frac + c2) * frac + c1) * frac + x0
+        }
+        Interpolation::Sinc => {
+            let mut sum = 0.0;
+            let mut weights = 0.0;
+
+            for k in (1 - SINC_HALF_WIDTH)..=SINC_HALF_WIDTH {
+                let w = lanczos(frac - k as f32);
+                sum += at(k) * w;
+                weights += w;
+            }
+
+            // normalise so the kernel passes DC through at unity gain
+            sum / weights
+        }
+    }
+}
+
+/// The index of the zero crossing nearest to `pos`, for placing loop points
+/// where they won't click
+///
+/// Of the two samples either side of a crossing this picks whichever is closer
+/// to zero. If the signal never crosses zero `pos` is returned unchanged.
+pub fn nearest_zero_crossing(buf: &[f32], pos: usize) -> usize {
+    let crossing = |i: usize| -> Option<usize> {
+        let (a, b) = (*buf.get(i)?, *buf.get(i + 1)?);
+
+        if a == 0.0 {
+            return Some(i);
+        }
+
+        ((a < 0.0) != (b < 0.0)).then_some(if a.abs() <= b.abs() { i } else { i + 1 })
+    };
+
+    let start = pos.min(buf.len().saturating_sub(1));
+
+    for d in 0..buf.len() {
+        let found = start
+            .checked_sub(d)
+            .and_then(crossing)
+            .or_else(|| crossing(start + d));
+
+        if let Some(i) = found {
+            return i;
+        }
+    }
+
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RMS error reading a sine between its samples
+    fn interpolation_error(mode: Interpolation) -> f32 {
+        let frequency = 0.05;
+        let sine = |t: f32| (t * frequency * std::f32::consts::TAU).sin();
+        let buf = (0..256).map(|i| sine(i as f32)).collect::<Vec<_>>();
+        let sample_at = |i: usize| buf.get(i).copied().unwrap_or(0.0);
+
+        // stay clear of the ends, where the kernels run off the buffer
+        let positions = (0..1000).map(|i| 16.0 + (i as f32 * 0.2137) % 200.0);
+        let squared = positions
+            .map(|pos| (read_fractional(sample_at, pos, mode) - sine(pos)).powi(2))
+            .sum::<f32>();
+
+        (squared / 1000.0).sqrt()
+    }
+
+    #[test]
+    fn interpolation_error_on_a_sine() {
+        let none = interpolation_error(Interpolation::None);
+        let linear = interpolation_error(Interpolation::Linear);
+        let cubic = interpolation_error(Interpolation::Cubic);
+        let sinc = interpolation_error(Interpolation::Sinc);
+
+        assert!(linear < 0.01, "linear error {linear}");
+        assert!(cubic < 0.001, "cubic error {cubic}");
+        assert!(sinc < 0.005, "sinc error {sinc}");
+        assert!(none > linear && linear > cubic && linear > sinc);
+    }
+
+    #[test]
+    fn interpolation_hits_samples() {
+        let buf = [0.0, 1.0, -0.5, 0.25, 0.75, -1.0, 0.5, 0.0, 0.3, -0.2];
+        let sample_at = |i: usize| buf.get(i).copied().unwrap_or(0.0);
+
+        for mode in <Interpolation as strum::IntoEnumIterator>::iter() {
+            let x = read_fractional(sample_at, 5.0, mode);
+            assert!((x - buf[5]).abs() < 1e-5, "{mode:?} read {x}");
+        }
+    }
+
+    #[test]
+    fn zero_crossings_of_a_sine() {
+        // 100 samples per period, crossing zero every 50 samples
+        let buf = (0..1000)
+            .map(|i| (i as f32 / 100.0 * std::f32::consts::TAU + 0.1).sin())
+            .collect::<Vec<_>>();
+
+        for pos in [0, 37, 260, 499, 990] {
+            let i = nearest_zero_crossing(&buf, pos);
+
+            assert!(buf[i].abs() < 0.1, "{pos} snapped to {i} ({})", buf[i]);
+            let crosses_before = i > 0 && (buf[i - 1] < 0.0) != (buf[i] < 0.0);
+            let crosses_after = i + 1 < buf.len() && (buf[i] < 0.0) != (buf[i + 1] < 0.0);
+            assert!(crosses_before || crosses_after, "{pos} snapped to {i}");
+            assert!(i.abs_diff(pos) <= 50, "{pos} snapped to {i}");
+        }
+    }
+
+    #[test]
+    fn no_zero_crossing() {
+        assert_eq!(nearest_zero_crossing(&[0.5; 64], 10), 10);
+    }
+}